@@ -0,0 +1,70 @@
+//! Throughput of the server state, posting concurrently across thousands of rooms while other threads read them.
+//! Run it with `cargo bench --bench concurrent_posting`.
+
+use rust_live_chat::entities::server::Server;
+use std::time::Instant;
+
+const ROOMS_COUNT: usize = 2000;
+const POSTERS_COUNT: usize = 16;
+const READERS_COUNT: usize = 4;
+const MESSAGES_PER_POSTER: usize = 5000;
+
+fn main() {
+    let server = Server::new_shared();
+    {
+        let mut server = server.write().unwrap();
+        for poster in 0..POSTERS_COUNT {
+            server.register_user(&format!("poster_{}", poster)).unwrap();
+        }
+        for room in 0..ROOMS_COUNT {
+            server.create_room(&format!("room_{}", room), "poster_0").unwrap();
+            for poster in 1..POSTERS_COUNT {
+                server.add_user_to_room(&format!("room_{}", room), &format!("poster_{}", poster)).unwrap();
+            }
+        }
+    }
+
+    let start = Instant::now();
+    let posters: Vec<_> = (0..POSTERS_COUNT).map(|poster| {
+        let server = server.clone();
+        std::thread::spawn(move || {
+            let username = format!("poster_{}", poster);
+            for message in 0..MESSAGES_PER_POSTER {
+                let room_name = format!("room_{}", (poster * 7919 + message) % ROOMS_COUNT);
+                server.read().unwrap().post_message_to_room(&room_name, &username, "benchmark").unwrap();
+            }
+        })
+    }).collect();
+    let readers: Vec<_> = (0..READERS_COUNT).map(|reader| {
+        let server = server.clone();
+        std::thread::spawn(move || {
+            let mut reads = 0;
+            for message in 0..MESSAGES_PER_POSTER {
+                let room_name = format!("room_{}", (reader * 104729 + message) % ROOMS_COUNT);
+                reads += server.read().unwrap().get_room_messages(&room_name).unwrap().len().min(1);
+            }
+            reads
+        })
+    }).collect();
+
+    for poster in posters {
+        poster.join().unwrap();
+    }
+    for reader in readers {
+        reader.join().unwrap();
+    }
+    let elapsed = start.elapsed();
+
+    let server = server.read().unwrap();
+    let stored_messages: usize = (0..ROOMS_COUNT)
+        .map(|room| server.get_room_messages(&format!("room_{}", room)).unwrap().len())
+        .sum();
+    assert_eq!(stored_messages, POSTERS_COUNT * MESSAGES_PER_POSTER);
+
+    let operations = (POSTERS_COUNT + READERS_COUNT) * MESSAGES_PER_POSTER;
+    println!(
+        "{} posts and {} reads across {} rooms in {:?} ({:.0} ops/s)",
+        POSTERS_COUNT * MESSAGES_PER_POSTER, READERS_COUNT * MESSAGES_PER_POSTER, ROOMS_COUNT, elapsed,
+        operations as f64 / elapsed.as_secs_f64()
+    );
+}
@@ -0,0 +1,554 @@
+pub mod retry;
+
+use crate::entities::{error::error_ids, webhook::Webhook};
+use crate::web_server::handlers::{
+    health::LivenessResponse,
+    rooms::{PostMessageRequest, RegisterWebhookRequest, RegisteredWebhook},
+    views::{MessageView, RoomSummary, UserView},
+    ErrorDetailsResponse
+};
+use crate::web_server::{export::ExportQuery, routes::API_VERSION};
+use reqwest::{header::{self, HeaderMap, HeaderValue}, RequestBuilder, Response, StatusCode, Url};
+use retry::{RetryEvent, RetryPolicy};
+use serde::de::DeserializeOwned;
+use std::{fmt, fs, path::Path, sync::Arc, time::Duration};
+use uuid::Uuid;
+
+/// Version reported for the servers predating the health probes, which only answer `GET /status`.
+pub const UNKNOWN_SERVER_VERSION: &str = "unknown";
+
+/// Options of the connections opened by a `ChatClient`.
+#[derive(Debug, Clone)]
+pub struct ChatClientOptions {
+    /// Time allowed for a whole request, from connecting to reading the response body
+    pub timeout: Duration,
+    pub connect_timeout: Duration,
+    /// Sent as a bearer token with every request, for the servers deployed behind an authenticating proxy
+    pub auth_token: Option<String>,
+    /// Certificate authority trusted in addition to the system ones, for servers using self-signed certificates
+    pub ca_certificate: Option<reqwest::Certificate>,
+    /// How the idempotent requests are retried while the server cannot be reached
+    pub retry_policy: RetryPolicy
+}
+
+impl Default for ChatClientOptions {
+    fn default() -> Self {
+        ChatClientOptions {
+            timeout: Duration::from_secs(30),
+            connect_timeout: Duration::from_secs(10),
+            auth_token: None,
+            ca_certificate: None,
+            retry_policy: RetryPolicy::default()
+        }
+    }
+}
+
+/// Typed client of the REST API of the chat server, for the bots and services built on top of it.
+/// Requests share the connections of a single HTTP client, so a `ChatClient` is meant to be built once and cloned cheaply.
+/// The idempotent requests are retried with backoff while the server cannot be reached, following `ChatClientOptions::retry_policy`.
+/// Failures are reported as the `ErrorDetailsResponse` answered by the server, or raised by the client with an `ERR__CLIENT_*` id.
+#[derive(Clone)]
+pub struct ChatClient {
+    base_url: Url,
+    /// Version prefixing the paths of the REST API, or `None` for the servers predating the versioned paths
+    api_version: Option<&'static str>,
+    http_client: reqwest::Client,
+    retry_policy: RetryPolicy,
+    /// Told about the reconnection attempts, if any
+    on_retry: Option<Arc<dyn Fn(RetryEvent) + Send + Sync>>
+}
+
+impl fmt::Debug for ChatClient {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ChatClient")
+            .field("base_url", &self.base_url)
+            .field("api_version", &self.api_version)
+            .field("retry_policy", &self.retry_policy)
+            .finish_non_exhaustive()
+    }
+}
+
+impl ChatClient {
+    /// Creates a client of the server at the given endpoint, e.g. `http://127.0.0.1:3000`, with the default options.
+    pub fn new(server_endpoint: &str) -> Result<ChatClient, ErrorDetailsResponse> {
+        ChatClient::with_options(server_endpoint, ChatClientOptions::default())
+    }
+
+    pub fn with_options(server_endpoint: &str, options: ChatClientOptions) -> Result<ChatClient, ErrorDetailsResponse> {
+        let client_error = |error_id: &str, error_message: String| ErrorDetailsResponse {
+            error_id: error_id.to_string(),
+            error_message,
+            request_id: None
+        };
+        let base_url = Url::parse(server_endpoint)
+            .ok()
+            .filter(|url| matches!(url.scheme(), "http" | "https") && url.has_host())
+            .ok_or_else(|| client_error(error_ids::CLIENT_INVALID_ENDPOINT, format!("Invalid server endpoint {}, expected an http(s) URL", server_endpoint)))?;
+
+        let mut default_headers = HeaderMap::new();
+        if let Some(auth_token) = &options.auth_token {
+            let mut authorization = HeaderValue::from_str(&format!("Bearer {}", auth_token))
+                .map_err(|_| client_error(error_ids::CLIENT_INVALID_AUTH_TOKEN, "Invalid auth token, expected visible ASCII characters only".to_string()))?;
+            authorization.set_sensitive(true);
+            default_headers.insert(header::AUTHORIZATION, authorization);
+        }
+        let mut builder = reqwest::Client::builder()
+            .default_headers(default_headers)
+            .timeout(options.timeout)
+            .connect_timeout(options.connect_timeout);
+        if let Some(ca_certificate) = options.ca_certificate {
+            builder = builder.add_root_certificate(ca_certificate);
+        }
+        let http_client = builder.build()
+            .map_err(|error| client_error(error_ids::CLIENT_BUILD_FAILED, format!("Could not build the HTTP client: {}", error)))?;
+
+        Ok(ChatClient { base_url, api_version: Some(API_VERSION), http_client, retry_policy: options.retry_policy, on_retry: None })
+    }
+
+    /// The same client talking the given version of the REST API, e.g. as negotiated from the `api_versions` of the liveness probe,
+    /// or the unversioned paths if `None`.
+    pub fn with_api_version(self, api_version: Option<&'static str>) -> ChatClient {
+        ChatClient { api_version, ..self }
+    }
+
+    /// The same client retrying its idempotent requests with the given policy, e.g. for as long as it takes to follow a room.
+    pub fn with_retry_policy(self, retry_policy: RetryPolicy) -> ChatClient {
+        ChatClient { retry_policy, ..self }
+    }
+
+    /// The same client telling the callback about its reconnection attempts, e.g. to display them.
+    pub fn on_retry(self, on_retry: impl Fn(RetryEvent) + Send + Sync + 'static) -> ChatClient {
+        ChatClient { on_retry: Some(Arc::new(on_retry)), ..self }
+    }
+
+    /// The server endpoint, as normalized when the client was created, e.g. `http://127.0.0.1:3000/`.
+    pub fn server_endpoint(&self) -> &str {
+        self.base_url.as_str()
+    }
+
+    /// URL of an unversioned route, e.g. the health probes.
+    fn url(&self, segments: &[&str]) -> Url {
+        let mut url = self.base_url.clone();
+        url.path_segments_mut()
+            .expect("http(s) URLs have path segments")
+            .pop_if_empty()
+            .extend(segments);
+        url
+    }
+
+    /// URL of a route of the REST API, under the version this client talks.
+    fn api_url(&self, segments: &[&str]) -> Url {
+        match self.api_version {
+            Some(api_version) => self.url(&[&[api_version], segments].concat()),
+            None => self.url(segments)
+        }
+    }
+
+    /// Sends the request once, since retrying it could apply it twice.
+    async fn send<T: DeserializeOwned>(&self, request: RequestBuilder) -> Result<T, ErrorDetailsResponse> {
+        decode(successful_response(request.send().await).await?).await
+    }
+
+    /// Sends an idempotent request, retried with backoff while the server cannot be reached.
+    async fn send_idempotent<T: DeserializeOwned>(&self, request: RequestBuilder) -> Result<T, ErrorDetailsResponse> {
+        decode(self.send_idempotent_streamed(request).await?).await
+    }
+
+    /// Sends an idempotent request like `send_idempotent`, returning the successful response before its body is read.
+    async fn send_idempotent_streamed(&self, request: RequestBuilder) -> Result<Response, ErrorDetailsResponse> {
+        successful_response(self.send_with_retry(request).await).await
+    }
+
+    async fn send_with_retry(&self, request: RequestBuilder) -> reqwest::Result<Response> {
+        let request = || request.try_clone().expect("requests without a streamed body can be cloned");
+        let on_retry = |event| if let Some(on_retry) = &self.on_retry {
+            on_retry(event);
+        };
+        retry::send_with_retry(self.retry_policy, request, on_retry).await
+    }
+
+    /// Checks that the server is up, falling back to `GET /status` for the servers predating the health probes,
+    /// whose version is then reported as `UNKNOWN_SERVER_VERSION`.
+    pub async fn liveness(&self) -> Result<LivenessResponse, ErrorDetailsResponse> {
+        let response = self.send_with_retry(self.http_client.get(self.url(&["health", "live"]))).await;
+        if !matches!(&response, Ok(response) if response.status() == StatusCode::NOT_FOUND) {
+            return decode(successful_response(response).await?).await;
+        }
+        let response = self.send_with_retry(self.http_client.get(self.url(&["status"]))).await;
+        successful_response(response).await?;
+        Ok(LivenessResponse {
+            status: "OK__SERVER_REACHABLE".to_string(),
+            version: UNKNOWN_SERVER_VERSION.to_string(),
+            uptime_seconds: 0,
+            api_versions: Vec::new()
+        })
+    }
+
+    pub async fn register_user(&self, username: &str) -> Result<UserView, ErrorDetailsResponse> {
+        self.send(self.http_client.post(self.api_url(&["users", username]))).await
+    }
+
+    pub async fn get_user(&self, username: &str) -> Result<UserView, ErrorDetailsResponse> {
+        self.send_idempotent(self.http_client.get(self.api_url(&["users", username]))).await
+    }
+
+    pub async fn get_user_by_id(&self, user_id: Uuid) -> Result<UserView, ErrorDetailsResponse> {
+        self.send_idempotent(self.http_client.get(self.api_url(&["users", "by-id", &user_id.to_string()]))).await
+    }
+
+    pub async fn get_rooms(&self) -> Result<Vec<RoomSummary>, ErrorDetailsResponse> {
+        self.send_idempotent(self.http_client.get(self.api_url(&["rooms"]))).await
+    }
+
+    pub async fn get_room(&self, room_name: &str) -> Result<RoomSummary, ErrorDetailsResponse> {
+        self.send_idempotent(self.http_client.get(self.api_url(&["rooms", room_name]))).await
+    }
+
+    pub async fn get_room_by_id(&self, room_id: Uuid) -> Result<RoomSummary, ErrorDetailsResponse> {
+        self.send_idempotent(self.http_client.get(self.api_url(&["rooms", "by-id", &room_id.to_string()]))).await
+    }
+
+    /// Creates a room, joined by its creator.
+    pub async fn create_room(&self, room_name: &str, creator_username: &str) -> Result<RoomSummary, ErrorDetailsResponse> {
+        let request = self.http_client.post(self.api_url(&["rooms", room_name]))
+            .query(&[("creator_username", creator_username)]);
+        self.send(request).await
+    }
+
+    pub async fn join_room(&self, room_name: &str, username: &str) -> Result<RoomSummary, ErrorDetailsResponse> {
+        self.send(self.http_client.post(self.api_url(&["rooms", room_name, "users", username]))).await
+    }
+
+    pub async fn leave_room(&self, room_name: &str, username: &str) -> Result<RoomSummary, ErrorDetailsResponse> {
+        self.send(self.http_client.delete(self.api_url(&["rooms", room_name, "users", username]))).await
+    }
+
+    /// Returns the user if they are in the room, failing with `ERR__USER_NOT_IN_ROOM` otherwise.
+    pub async fn get_room_user(&self, room_name: &str, username: &str) -> Result<UserView, ErrorDetailsResponse> {
+        self.send_idempotent(self.http_client.get(self.api_url(&["rooms", room_name, "users", username]))).await
+    }
+
+    pub async fn get_room_messages(&self, room_name: &str) -> Result<Vec<MessageView>, ErrorDetailsResponse> {
+        self.send_idempotent(self.http_client.get(self.api_url(&["rooms", room_name, "messages"]))).await
+    }
+
+    /// Returns the messages posted after the given sequence number, e.g. the `seq` of the last message received, to catch up with the room.
+    pub async fn get_room_messages_after(&self, room_name: &str, seq: u64) -> Result<Vec<MessageView>, ErrorDetailsResponse> {
+        let request = self.http_client.get(self.api_url(&["rooms", room_name, "messages"]))
+            .query(&[("after_seq", seq)]);
+        self.send_idempotent(request).await
+    }
+
+    pub async fn post_message(&self, room_name: &str, username: &str, message: &str) -> Result<MessageView, ErrorDetailsResponse> {
+        self.post_message_with_id(room_name, username, message, None).await
+    }
+
+    /// Posts a message with an id generated by the caller, to send it again after a timeout without posting it twice:
+    /// the server then answers the message posted the first time. Messages with an id are retried like the idempotent requests.
+    pub async fn post_message_with_id(&self, room_name: &str, username: &str, message: &str, client_message_id: Option<Uuid>) -> Result<MessageView, ErrorDetailsResponse> {
+        let request = self.http_client.post(self.api_url(&["rooms", room_name, "messages"]))
+            .json(&PostMessageRequest { username: username.to_string(), message: message.to_string(), client_message_id });
+        match client_message_id {
+            Some(_) => self.send_idempotent(request).await,
+            None => self.send(request).await
+        }
+    }
+
+    /// Registers an outgoing webhook, whose secret is only disclosed in the returned `RegisteredWebhook`.
+    pub async fn register_webhook(&self, room_name: &str, webhook: &RegisterWebhookRequest) -> Result<RegisteredWebhook, ErrorDetailsResponse> {
+        self.send(self.http_client.post(self.api_url(&["rooms", room_name, "webhooks"])).json(webhook)).await
+    }
+
+    pub async fn get_room_webhooks(&self, room_name: &str) -> Result<Vec<Webhook>, ErrorDetailsResponse> {
+        self.send_idempotent(self.http_client.get(self.api_url(&["rooms", room_name, "webhooks"]))).await
+    }
+
+    /// Starts exporting the messages of the room, the export being read chunk by chunk as the server renders it.
+    pub async fn export_room(&self, room_name: &str, query: &ExportQuery) -> Result<RoomExport, ErrorDetailsResponse> {
+        let request = self.http_client.get(self.api_url(&["rooms", room_name, "export"]))
+            .query(&query.to_query_params());
+        Ok(RoomExport { response: self.send_idempotent_streamed(request).await? })
+    }
+}
+
+/// Export of a room being received from the server.
+#[derive(Debug)]
+pub struct RoomExport {
+    response: Response,
+}
+
+impl RoomExport {
+    /// Next chunk of the export, or `None` once it was entirely received.
+    pub async fn chunk(&mut self) -> Result<Option<Vec<u8>>, ErrorDetailsResponse> {
+        let chunk = self.response.chunk().await.map_err(|error| fetch_error(format!("Export interrupted: {}", error)))?;
+        Ok(chunk.map(|chunk| chunk.to_vec()))
+    }
+}
+
+/// Loads a PEM certificate authority, to be trusted with `ChatClientOptions::ca_certificate`.
+pub fn load_ca_certificate(path: &Path) -> Result<reqwest::Certificate, String> {
+    let pem = fs::read(path).map_err(|error| format!("Could not read CA certificate {}: {}", path.display(), error))?;
+    reqwest::Certificate::from_pem(&pem).map_err(|error| format!("Invalid CA certificate {}: {}", path.display(), error))
+}
+
+/// The response if successful, or the error answered by the server,
+/// `ERR__CLIENT_FETCH_API` if its body is not an `ErrorDetailsResponse`, e.g. the plain text of a 502 answered by a proxy.
+async fn successful_response(response: reqwest::Result<Response>) -> Result<Response, ErrorDetailsResponse> {
+    let response = response.map_err(|error| fetch_error(format!("Failed to fetch API: {}", error)))?;
+    let status = response.status();
+    if status.is_success() {
+        Ok(response)
+    } else {
+        Err(response.json().await.unwrap_or_else(|_| fetch_error(format!("Server answered with status {}", status))))
+    }
+}
+
+async fn decode<T: DeserializeOwned>(response: Response) -> Result<T, ErrorDetailsResponse> {
+    response.json().await.map_err(|error| fetch_error(format!("Unexpected response: {}", error)))
+}
+
+fn fetch_error(error_message: String) -> ErrorDetailsResponse {
+    ErrorDetailsResponse {
+        error_id: error_ids::CLIENT_FETCH_API.to_string(),
+        error_message,
+        request_id: None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::entities::server::Server;
+    use crate::web_server::{export::ExportFormat, routes, tls::TestCertificates};
+    use warp::Filter;
+
+    #[test]
+    fn test_chat_client_invalid_endpoint() {
+        for server_endpoint in ["127.0.0.1:3000", "ftp://127.0.0.1", "not a url"] {
+            let error = ChatClient::new(server_endpoint).unwrap_err();
+            assert_eq!(error.error_id, "ERR__CLIENT_INVALID_ENDPOINT");
+        }
+    }
+
+    #[test]
+    fn test_chat_client_invalid_auth_token() {
+        let error = ChatClient::with_options("http://127.0.0.1:3000", ChatClientOptions {
+            auth_token: Some("token\n".to_string()),
+            ..ChatClientOptions::default()
+        }).unwrap_err();
+        assert_eq!(error.error_id, "ERR__CLIENT_INVALID_AUTH_TOKEN");
+    }
+
+    #[test]
+    fn test_chat_client_api_url() {
+        let chat_client = ChatClient::new("https://chat.example.com/chat/").unwrap();
+        assert_eq!(chat_client.api_url(&["rooms", "a room/1"]).as_str(), "https://chat.example.com/chat/v1/rooms/a%20room%2F1");
+        assert_eq!(chat_client.url(&["health", "live"]).as_str(), "https://chat.example.com/chat/health/live");
+    }
+
+    #[tokio::test]
+    async fn test_chat_client_against_server() {
+        let chat_client = ChatClient::new(&routes::serve_ephemeral(Server::new_shared())).unwrap();
+
+        let liveness = chat_client.liveness().await.unwrap();
+        assert_eq!(liveness.api_versions, vec![API_VERSION]);
+
+        let user = chat_client.register_user("test_user").await.unwrap();
+        assert_eq!(chat_client.get_user_by_id(user.id).await.unwrap().username, "test_user");
+        chat_client.register_user("other_user").await.unwrap();
+
+        let room = chat_client.create_room("test_room", "test_user").await.unwrap();
+        assert_eq!(room.users.len(), 1);
+        let room = chat_client.join_room("test_room", "other_user").await.unwrap();
+        assert_eq!(room.users.len(), 2);
+        assert_eq!(chat_client.get_room_by_id(room.id).await.unwrap().name, "test_room");
+        assert_eq!(chat_client.get_rooms().await.unwrap().len(), 1);
+
+        let message = chat_client.post_message("test_room", "other_user", "Hello").await.unwrap();
+        assert_eq!(message.author.username, "other_user");
+        let messages = chat_client.get_room_messages("test_room").await.unwrap();
+        assert_eq!(messages.last().unwrap().id, message.id);
+        let client_message_id = Some(Uuid::new_v4());
+        let message = chat_client.post_message_with_id("test_room", "other_user", "Hello again", client_message_id).await.unwrap();
+        let replayed_message = chat_client.post_message_with_id("test_room", "other_user", "Hello again", client_message_id).await.unwrap();
+        assert_eq!(replayed_message, message);
+        assert_eq!(chat_client.get_room_messages("test_room").await.unwrap().len(), messages.len() + 1);
+        let messages_after = chat_client.get_room_messages_after("test_room", messages.last().unwrap().seq).await.unwrap();
+        assert_eq!(messages_after, vec![message]);
+        let mut export = chat_client.export_room("test_room", &ExportQuery { format: ExportFormat::JsonLines, since: Some(messages_after[0].timestamp), until: None }).await.unwrap();
+        let mut exported = Vec::new();
+        while let Some(chunk) = export.chunk().await.unwrap() {
+            exported.extend(chunk);
+        }
+        assert_eq!(serde_json::from_slice::<MessageView>(&exported).unwrap(), messages_after[0]);
+
+        let room = chat_client.leave_room("test_room", "other_user").await.unwrap();
+        assert_eq!(room.users.len(), 1);
+        let error = chat_client.get_room_user("test_room", "other_user").await.unwrap_err();
+        assert_eq!(error.error_id, "ERR__USER_NOT_IN_ROOM");
+        assert!(error.request_id.is_some());
+
+        let registered_webhook = chat_client.register_webhook("test_room", &RegisterWebhookRequest {
+            url: "https://example.com/hook".to_string(),
+            secret: Some("test_secret".to_string())
+        }).await.unwrap();
+        assert_eq!(registered_webhook.secret, "test_secret");
+        let webhooks = chat_client.get_room_webhooks("test_room").await.unwrap();
+        assert_eq!(webhooks[0].id, registered_webhook.id);
+    }
+
+    #[tokio::test]
+    async fn test_chat_client_unversioned_server() {
+        let mut server = mockito::Server::new_async().await;
+        let mock = server.mock("GET", "/rooms")
+            .with_status(200)
+            .with_body("[]")
+            .create_async().await;
+
+        let chat_client = ChatClient::new(&server.url()).unwrap().with_api_version(None);
+        assert!(chat_client.get_rooms().await.unwrap().is_empty());
+        mock.assert();
+    }
+
+    #[tokio::test]
+    async fn test_chat_client_liveness_server_without_probes() {
+        let mut server = mockito::Server::new_async().await;
+        let liveness_mock = server.mock("GET", "/health/live")
+            .with_status(404)
+            .create_async().await;
+        let status_mock = server.mock("GET", "/status")
+            .with_status(200)
+            .with_body(r#"{"status":"OK__SERVER_REACHABLE"}"#)
+            .create_async().await;
+
+        let liveness = ChatClient::new(&server.url()).unwrap().liveness().await.unwrap();
+        assert_eq!(liveness.version, UNKNOWN_SERVER_VERSION);
+        assert!(liveness.api_versions.is_empty());
+        liveness_mock.assert();
+        status_mock.assert();
+    }
+
+    #[tokio::test]
+    async fn test_chat_client_liveness_error() {
+        let mut server = mockito::Server::new_async().await;
+        let liveness_mock = server.mock("GET", "/health/live")
+            .with_status(404)
+            .create_async().await;
+        let status_mock = server.mock("GET", "/status")
+            .with_status(404)
+            .create_async().await;
+
+        let error = ChatClient::new(&server.url()).unwrap().liveness().await.unwrap_err();
+        assert_eq!(error.error_id, "ERR__CLIENT_FETCH_API");
+        liveness_mock.assert();
+        status_mock.assert();
+    }
+
+    #[tokio::test]
+    async fn test_chat_client_retries_idempotent_requests() {
+        let mut server = mockito::Server::new_async().await;
+        let unavailable_mock = server.mock("GET", "/v1/rooms")
+            .with_status(503)
+            .expect(2)
+            .create_async().await;
+
+        let chat_client = ChatClient::with_options(&server.url(), ChatClientOptions {
+            retry_policy: RetryPolicy { max_attempts: 2, initial_backoff: Duration::from_millis(1), max_backoff: Duration::from_millis(1) },
+            ..ChatClientOptions::default()
+        }).unwrap();
+        let events = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let reported_events = events.clone();
+        let error = chat_client.on_retry(move |event| reported_events.lock().unwrap().push(event)).get_rooms().await.unwrap_err();
+        assert_eq!(error.error_id, "ERR__CLIENT_FETCH_API");
+        assert!(matches!(events.lock().unwrap()[..], [RetryEvent::Retrying { attempt: 2, max_attempts: 2, .. }]));
+        unavailable_mock.assert();
+    }
+
+    #[tokio::test]
+    async fn test_chat_client_server_error() {
+        let chat_client = ChatClient::new(&routes::serve_ephemeral(Server::new_shared())).unwrap();
+
+        let error = chat_client.get_room("unknown_room").await.unwrap_err();
+        assert_eq!(error.error_id, "ERR__ROOM_NOT_FOUND");
+    }
+
+    #[tokio::test]
+    async fn test_chat_client_sends_auth_token() {
+        let mut server = mockito::Server::new_async().await;
+        let mock = server.mock("GET", "/v1/users/test_user")
+            .match_header("authorization", "Bearer test_token")
+            .with_status(200)
+            .with_body(serde_json::json!({"id": Uuid::new_v4(), "username": "test_user", "is_bot": false}).to_string())
+            .create_async().await;
+
+        let chat_client = ChatClient::with_options(&server.url(), ChatClientOptions {
+            auth_token: Some("test_token".to_string()),
+            ..ChatClientOptions::default()
+        }).unwrap();
+        assert_eq!(chat_client.get_user("test_user").await.unwrap().username, "test_user");
+        mock.assert();
+    }
+
+    #[tokio::test]
+    async fn test_chat_client_unexpected_response() {
+        let mut server = mockito::Server::new_async().await;
+        let mock = server.mock("GET", "/v1/rooms")
+            .with_status(500)
+            .with_body("Internal Server Error")
+            .create_async().await;
+
+        let error = ChatClient::new(&server.url()).unwrap().get_rooms().await.unwrap_err();
+        assert_eq!(error.error_id, "ERR__CLIENT_FETCH_API");
+        assert_eq!(error.error_message, "Server answered with status 500 Internal Server Error");
+        mock.assert();
+    }
+
+    #[tokio::test]
+    async fn test_chat_client_timeout() {
+        let routes = warp::any().then(|| async {
+            tokio::time::sleep(Duration::from_secs(5)).await;
+            "[]"
+        });
+        let (address, serving) = warp::serve(routes).bind_ephemeral(([127, 0, 0, 1], 0));
+        tokio::spawn(serving);
+
+        let chat_client = ChatClient::with_options(&format!("http://{}", address), ChatClientOptions {
+            timeout: Duration::from_millis(100),
+            retry_policy: RetryPolicy { max_attempts: 2, initial_backoff: Duration::from_millis(1), max_backoff: Duration::from_millis(1) },
+            ..ChatClientOptions::default()
+        }).unwrap();
+        let error = chat_client.get_rooms().await.unwrap_err();
+        assert_eq!(error.error_id, "ERR__CLIENT_FETCH_API");
+    }
+
+    #[tokio::test]
+    async fn test_chat_client_trusts_ca_certificate() {
+        let certificates = TestCertificates::generate();
+        let (address, serving) = warp::serve(warp::any().map(|| "[]")).tls()
+            .cert(&certificates.cert_pem)
+            .key(&certificates.key_pem)
+            .bind_ephemeral(([127, 0, 0, 1], 0));
+        tokio::spawn(serving);
+        let ca_cert_path = std::env::temp_dir().join(format!("rust-live-chat-ca-{}.pem", Uuid::new_v4()));
+        fs::write(&ca_cert_path, &certificates.ca_cert_pem).unwrap();
+
+        let server_endpoint = format!("https://127.0.0.1:{}", address.port());
+        let chat_client = ChatClient::with_options(&server_endpoint, ChatClientOptions {
+            ca_certificate: Some(load_ca_certificate(&ca_cert_path).unwrap()),
+            ..ChatClientOptions::default()
+        }).unwrap();
+        assert!(chat_client.get_rooms().await.unwrap().is_empty());
+        // The certificate is refused when connecting, hence retried like the unreachable servers
+        let untrusting_chat_client = ChatClient::with_options(&server_endpoint, ChatClientOptions {
+            retry_policy: RetryPolicy { max_attempts: 2, initial_backoff: Duration::from_millis(1), max_backoff: Duration::from_millis(1) },
+            ..ChatClientOptions::default()
+        }).unwrap();
+        assert!(untrusting_chat_client.get_rooms().await.is_err());
+        fs::remove_file(ca_cert_path).unwrap();
+    }
+
+    #[test]
+    fn test_load_ca_certificate_error() {
+        assert!(load_ca_certificate(Path::new("/nonexistent/ca.pem")).unwrap_err().starts_with("Could not read CA certificate"));
+    }
+}
@@ -0,0 +1,173 @@
+use std::collections::hash_map::RandomState;
+use std::fmt;
+use std::hash::{BuildHasher, Hasher};
+use std::time::Duration;
+use reqwest::StatusCode;
+
+/// How the idempotent API calls are retried while the server cannot be reached.
+#[derive(Debug, Clone, Copy)]
+pub struct RetryPolicy {
+    pub max_attempts: u32,
+    pub initial_backoff: Duration,
+    pub max_backoff: Duration
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        RetryPolicy {
+            max_attempts: 5,
+            initial_backoff: Duration::from_millis(250),
+            max_backoff: Duration::from_secs(8)
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Max attempts retrying for as long as the server cannot be reached.
+    pub const UNLIMITED_ATTEMPTS: u32 = u32::MAX;
+
+    /// Backoff before the given retry, counted from 1: doubled on every retry up to the max backoff,
+    /// then jittered between half and the whole of it so that the clients of a restarted server do not retry all at once.
+    pub fn backoff(&self, retry: u32) -> Duration {
+        let exponential_backoff = self.initial_backoff
+            .saturating_mul(2u32.saturating_pow(retry.saturating_sub(1)))
+            .min(self.max_backoff);
+        exponential_backoff.mul_f64(0.5 + random_fraction() / 2.0)
+    }
+}
+
+/// Reconnection attempt reported while a request is retried, e.g. to tell the user the client is reconnecting.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RetryEvent {
+    /// The server could not be reached, the request being sent again after the backoff
+    Retrying { attempt: u32, max_attempts: u32, backoff: Duration },
+    /// The server answered after failed attempts
+    Reconnected
+}
+
+impl fmt::Display for RetryEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RetryEvent::Retrying { attempt, max_attempts, backoff } if *max_attempts == RetryPolicy::UNLIMITED_ATTEMPTS => {
+                write!(f, "Server unreachable, reconnecting in {:.1}s (attempt {})…", backoff.as_secs_f64(), attempt)
+            },
+            RetryEvent::Retrying { attempt, max_attempts, backoff } => {
+                write!(f, "Server unreachable, reconnecting in {:.1}s (attempt {}/{})…", backoff.as_secs_f64(), attempt, max_attempts)
+            },
+            RetryEvent::Reconnected => write!(f, "Reconnected to the server")
+        }
+    }
+}
+
+/// Random number in [0, 1], from the random keys std seeds its hash maps with.
+fn random_fraction() -> f64 {
+    RandomState::new().build_hasher().finish() as f64 / u64::MAX as f64
+}
+
+/// Whether the call may succeed if retried: the server could not be reached, or it or the proxy in front of it answered it was unavailable.
+fn is_retryable(response: &reqwest::Result<reqwest::Response>) -> bool {
+    match response {
+        Ok(response) => matches!(response.status(), StatusCode::BAD_GATEWAY | StatusCode::SERVICE_UNAVAILABLE | StatusCode::GATEWAY_TIMEOUT),
+        Err(error) => error.is_connect() || error.is_timeout()
+    }
+}
+
+/// Sends an idempotent request, built anew for every attempt, retrying it with backoff while the server cannot be reached.
+/// The reconnection attempts are reported to `on_retry`, the caller handling the last failure if the attempts are exhausted.
+pub async fn send_with_retry(policy: RetryPolicy, request: impl Fn() -> reqwest::RequestBuilder, on_retry: impl Fn(RetryEvent)) -> reqwest::Result<reqwest::Response> {
+    let mut retry = 0;
+    loop {
+        let response = request().send().await;
+        if !is_retryable(&response) || retry + 1 >= policy.max_attempts {
+            if retry > 0 && !is_retryable(&response) {
+                on_retry(RetryEvent::Reconnected);
+            }
+            return response;
+        }
+        retry += 1;
+        let backoff = policy.backoff(retry);
+        on_retry(RetryEvent::Retrying { attempt: retry + 1, max_attempts: policy.max_attempts, backoff });
+        tokio::time::sleep(backoff).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_retry_policy_backoff() {
+        let policy = RetryPolicy {
+            max_attempts: 10,
+            initial_backoff: Duration::from_millis(100),
+            max_backoff: Duration::from_millis(1000)
+        };
+
+        for (retry, exponential_backoff) in [(1, 100), (2, 200), (3, 400), (4, 800), (5, 1000), (10, 1000)] {
+            let backoff = policy.backoff(retry);
+            assert!(backoff >= Duration::from_millis(exponential_backoff / 2), "retry {}: {:?}", retry, backoff);
+            assert!(backoff <= Duration::from_millis(exponential_backoff), "retry {}: {:?}", retry, backoff);
+        }
+    }
+
+    #[test]
+    fn test_retry_policy_backoff_jitter() {
+        let policy = RetryPolicy::default();
+        let backoffs: std::collections::HashSet<_> = (0..10).map(|_| policy.backoff(5)).collect();
+        assert!(backoffs.len() > 1);
+    }
+
+    #[test]
+    fn test_retry_event_display() {
+        let backoff = Duration::from_millis(1500);
+        assert_eq!(RetryEvent::Retrying { attempt: 2, max_attempts: 5, backoff }.to_string(), "Server unreachable, reconnecting in 1.5s (attempt 2/5)…");
+        assert_eq!(RetryEvent::Retrying { attempt: 2, max_attempts: RetryPolicy::UNLIMITED_ATTEMPTS, backoff }.to_string(), "Server unreachable, reconnecting in 1.5s (attempt 2)…");
+        assert_eq!(RetryEvent::Reconnected.to_string(), "Reconnected to the server");
+    }
+
+    #[tokio::test]
+    async fn test_send_with_retry_until_available() {
+        use std::sync::{atomic::{AtomicU32, Ordering}, Arc};
+        use warp::Filter;
+
+        let requests = Arc::new(AtomicU32::new(0));
+        let server_requests = requests.clone();
+        let routes = warp::any().map(move || {
+            let status = if server_requests.fetch_add(1, Ordering::SeqCst) < 2 {
+                warp::http::StatusCode::SERVICE_UNAVAILABLE
+            } else {
+                warp::http::StatusCode::OK
+            };
+            warp::reply::with_status("", status)
+        });
+        let (address, serving) = warp::serve(routes).bind_ephemeral(([127, 0, 0, 1], 0));
+        tokio::spawn(serving);
+
+        let url = format!("http://{}/", address);
+        let policy = RetryPolicy { max_attempts: 5, initial_backoff: Duration::from_millis(1), max_backoff: Duration::from_millis(1) };
+        let events = std::sync::Mutex::new(Vec::new());
+        let response = send_with_retry(policy, || reqwest::Client::new().get(&url), |event| events.lock().unwrap().push(event)).await.unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(requests.load(Ordering::SeqCst), 3);
+        let events = events.into_inner().unwrap();
+        assert!(matches!(events[..], [
+            RetryEvent::Retrying { attempt: 2, max_attempts: 5, .. },
+            RetryEvent::Retrying { attempt: 3, max_attempts: 5, .. },
+            RetryEvent::Reconnected
+        ]), "{:?}", events);
+    }
+
+    #[tokio::test]
+    async fn test_send_with_retry_gives_up() {
+        let attempts = std::sync::atomic::AtomicU32::new(0);
+        let policy = RetryPolicy { max_attempts: 3, initial_backoff: Duration::from_millis(1), max_backoff: Duration::from_millis(1) };
+        let response = send_with_retry(policy, || {
+            attempts.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            reqwest::Client::new().get("http://127.0.0.1:1")
+        }, |_| ()).await;
+
+        assert!(response.is_err());
+        assert_eq!(attempts.into_inner(), 3);
+    }
+}
@@ -0,0 +1,403 @@
+use crate::chat_client::{retry::RetryPolicy, ChatClient};
+use crate::cli_client::{chat_client_from_config, flows::room_chat::format_message, profiles::{DisplayPreferences, Profile}};
+use crate::config::Config;
+use crate::entities::{error::error_ids, timestamp};
+use crate::web_server::export::{ExportFormat, ExportQuery};
+use crate::web_server::handlers::{views::MessageView, ErrorDetailsResponse};
+use clap::{Subcommand, ValueEnum};
+use serde::Serialize;
+use std::io::{self, Write};
+use std::time::{Duration, SystemTime};
+
+/// Interval between two fetches of the room messages by `tail --follow`.
+const FOLLOW_POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Exit codes of the client subcommands, for the scripts running them.
+pub mod exit_codes {
+    pub const SUCCESS: i32 = 0;
+    /// The server rejected the request, e.g. the user is not in the room
+    pub const REQUEST_FAILED: i32 = 1;
+    /// Invalid arguments or configuration, as reported by clap
+    pub const INVALID_USAGE: i32 = 2;
+    /// The server could not be reached, or answered unexpectedly
+    pub const SERVER_UNREACHABLE: i32 = 3;
+    /// The room or user does not exist
+    pub const NOT_FOUND: i32 = 4;
+    /// The result could not be written, e.g. the disk is full
+    pub const OUTPUT_FAILED: i32 = 5;
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, ValueEnum)]
+pub enum OutputFormat {
+    /// Human readable lines
+    Text,
+    /// JSON documents, one per line for the streamed messages
+    Json
+}
+
+#[derive(Debug, Subcommand)]
+pub enum ClientCommand {
+    /// Post a message to a room
+    Send {
+        #[arg(long)]
+        room: String,
+        /// The author of the message, who must be in the room, the user of the profile by default
+        #[arg(long)]
+        user: Option<String>,
+        /// The message text
+        text: String
+    },
+    /// Print the last messages of a room
+    Tail {
+        #[arg(long)]
+        room: String,
+        /// The number of messages to print
+        #[arg(long, short = 'n', default_value_t = 10)]
+        lines: usize,
+        /// Keep printing the messages as they are posted
+        #[arg(long, short = 'f')]
+        follow: bool
+    },
+    /// Write the messages of a room, streamed as the server renders them
+    Export {
+        #[arg(long)]
+        room: String,
+        #[arg(long, value_enum, default_value_t = ExportFormat::JsonLines)]
+        format: ExportFormat,
+        /// Export only the messages posted from this RFC 3339 timestamp, included
+        #[arg(long, value_parser = timestamp::parse_rfc3339)]
+        since: Option<SystemTime>,
+        /// Export only the messages posted before this RFC 3339 timestamp, excluded
+        #[arg(long, value_parser = timestamp::parse_rfc3339)]
+        until: Option<SystemTime>
+    },
+    /// Query the rooms
+    Rooms {
+        #[command(subcommand)]
+        command: RoomsCommand
+    },
+    /// Query the users
+    Users {
+        #[command(subcommand)]
+        command: UsersCommand
+    }
+}
+
+#[derive(Debug, Subcommand)]
+pub enum RoomsCommand {
+    /// List the rooms of the server
+    List
+}
+
+#[derive(Debug, Subcommand)]
+pub enum UsersCommand {
+    /// Show a user registered in the server
+    Show {
+        username: String
+    }
+}
+
+#[derive(Debug)]
+pub enum CommandError {
+    Api(ErrorDetailsResponse),
+    /// The result could not be written, which is not a failure of the command if the output was closed, e.g. by `head`
+    Output(io::Error)
+}
+
+impl CommandError {
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            CommandError::Api(error) => match error.error_id.as_str() {
+                error_ids::CLIENT_FETCH_API => exit_codes::SERVER_UNREACHABLE,
+                error_ids::CLIENT_INVALID_ENDPOINT
+                | error_ids::CLIENT_INVALID_AUTH_TOKEN
+                | error_ids::CLIENT_INVALID_CA_CERTIFICATE
+                | error_ids::CLIENT_MISSING_USER => exit_codes::INVALID_USAGE,
+                error_ids::USER_NOT_FOUND | error_ids::ROOM_NOT_FOUND => exit_codes::NOT_FOUND,
+                _ => exit_codes::REQUEST_FAILED
+            },
+            CommandError::Output(error) if error.kind() == io::ErrorKind::BrokenPipe => exit_codes::SUCCESS,
+            CommandError::Output(_) => exit_codes::OUTPUT_FAILED
+        }
+    }
+}
+
+impl From<ErrorDetailsResponse> for CommandError {
+    fn from(error: ErrorDetailsResponse) -> Self {
+        CommandError::Api(error)
+    }
+}
+
+impl From<io::Error> for CommandError {
+    fn from(error: io::Error) -> Self {
+        CommandError::Output(error)
+    }
+}
+
+/// Runs a subcommand, writing its result to the output, and returns the exit code of the process.
+/// Errors are written to the error output, as JSON in the JSON output format.
+pub async fn run_command(config: &Config, profile: &Profile, command: ClientCommand, output_format: OutputFormat) -> i32 {
+    let result = match chat_client_from_config(config, profile) {
+        Ok(chat_client) => {
+            let chat_client = chat_client.on_retry(|event| eprintln!("{}", event));
+            execute_command(&chat_client, profile, command, output_format, &mut io::stdout()).await
+        },
+        Err(error) => Err(CommandError::Api(error))
+    };
+    match result {
+        Ok(()) => exit_codes::SUCCESS,
+        Err(error) => {
+            let print_error = |error: &ErrorDetailsResponse| match output_format {
+                OutputFormat::Text => eprintln!("{}: {}", error.error_id, error),
+                OutputFormat::Json => eprintln!("{}", serde_json::to_string(error).unwrap())
+            };
+            match &error {
+                CommandError::Api(error) => print_error(error),
+                CommandError::Output(error) if error.kind() == io::ErrorKind::BrokenPipe => (),
+                CommandError::Output(error) => print_error(&ErrorDetailsResponse {
+                    error_id: error_ids::CLIENT_OUTPUT_FAILED.to_string(),
+                    error_message: format!("Could not write the output: {}", error),
+                    request_id: None
+                })
+            }
+            error.exit_code()
+        }
+    }
+}
+
+fn write_json(out: &mut impl Write, value: &impl Serialize) -> io::Result<()> {
+    writeln!(out, "{}", serde_json::to_string(value).map_err(io::Error::other)?)
+}
+
+fn write_message(out: &mut impl Write, message: &MessageView, output_format: OutputFormat, display: &DisplayPreferences) -> io::Result<()> {
+    match output_format {
+        OutputFormat::Text => writeln!(out, "{}", format_message(message, display, None)),
+        OutputFormat::Json => write_json(out, message)
+    }
+}
+
+pub async fn execute_command(chat_client: &ChatClient, profile: &Profile, command: ClientCommand, output_format: OutputFormat, out: &mut impl Write) -> Result<(), CommandError> {
+    match command {
+        ClientCommand::Send { room, user, text } => {
+            let Some(user) = user.or_else(|| profile.username.clone()) else {
+                return Err(CommandError::Api(ErrorDetailsResponse {
+                    error_id: error_ids::CLIENT_MISSING_USER.to_string(),
+                    error_message: "Missing --user, and no user in the profile".to_string(),
+                    request_id: None
+                }));
+            };
+            let message = chat_client.post_message(&room, &user, &text).await?;
+            match output_format {
+                OutputFormat::Text => writeln!(out, "{}", message.id)?,
+                OutputFormat::Json => write_json(out, &message)?
+            }
+        },
+        ClientCommand::Tail { room, lines, follow } => {
+            let messages = chat_client.get_room_messages(&room).await?;
+            let mut last_seq = messages.last().map(|message| message.seq).unwrap_or(0);
+            for message in messages.iter().skip(messages.len().saturating_sub(lines)) {
+                write_message(out, message, output_format, &profile.display)?;
+            }
+            out.flush()?;
+
+            if follow {
+                // Reconnects for as long as it takes, then prints the messages missed in the meantime
+                let chat_client = chat_client.clone().with_retry_policy(RetryPolicy { max_attempts: RetryPolicy::UNLIMITED_ATTEMPTS, ..RetryPolicy::default() });
+                loop {
+                    tokio::time::sleep(FOLLOW_POLL_INTERVAL).await;
+                    for message in chat_client.get_room_messages_after(&room, last_seq).await? {
+                        write_message(out, &message, output_format, &profile.display)?;
+                        last_seq = message.seq;
+                    }
+                    out.flush()?;
+                }
+            }
+        },
+        ClientCommand::Export { room, format, since, until } => {
+            // Written as is whatever the output format, the export format being chosen on its own
+            let mut export = chat_client.export_room(&room, &ExportQuery { format, since, until }).await?;
+            while let Some(chunk) = export.chunk().await? {
+                out.write_all(&chunk)?;
+            }
+            out.flush()?;
+        },
+        ClientCommand::Rooms { command: RoomsCommand::List } => {
+            let rooms = chat_client.get_rooms().await?;
+            match output_format {
+                OutputFormat::Text => for room in rooms {
+                    writeln!(out, "{} ({} users)", room.name, room.users.len())?;
+                },
+                OutputFormat::Json => write_json(out, &rooms)?
+            }
+        },
+        ClientCommand::Users { command: UsersCommand::Show { username } } => {
+            let user = chat_client.get_user(&username).await?;
+            match output_format {
+                OutputFormat::Text => {
+                    let bot_marker = if user.is_bot { " [bot]" } else { "" };
+                    writeln!(out, "{}{} ({})", user.username, bot_marker, user.id)?
+                },
+                OutputFormat::Json => write_json(out, &user)?
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chat_client::ChatClientOptions;
+    use crate::entities::server::{Server, SharedServer};
+    use crate::web_server::{handlers::views::{RoomSummary, UserView}, routes};
+
+    fn test_server() -> SharedServer {
+        let server = Server::new_shared();
+        server.write().unwrap().register_user("test_user").unwrap();
+        server.write().unwrap().create_room("test_room", "test_user").unwrap();
+        server
+    }
+
+    async fn execute(chat_client: &ChatClient, command: ClientCommand, output_format: OutputFormat) -> Result<String, CommandError> {
+        let mut out = Vec::new();
+        execute_command(chat_client, &Profile::default(), command, output_format, &mut out).await?;
+        Ok(String::from_utf8(out).unwrap())
+    }
+
+    #[tokio::test]
+    async fn test_execute_command_send() {
+        let server = test_server();
+        let chat_client = ChatClient::new(&routes::serve_ephemeral(server.clone())).unwrap();
+
+        let command = ClientCommand::Send { room: "test_room".to_string(), user: Some("test_user".to_string()), text: "Hello".to_string() };
+        let out = execute(&chat_client, command, OutputFormat::Json).await.unwrap();
+
+        let message: MessageView = serde_json::from_str(&out).unwrap();
+        assert_eq!(message.content, "Hello");
+        assert_eq!(server.read().unwrap().get_room_messages("test_room").unwrap().last().unwrap().id, message.id);
+    }
+
+    #[tokio::test]
+    async fn test_execute_command_send_user_not_in_room() {
+        let server = test_server();
+        server.write().unwrap().register_user("other_user").unwrap();
+        let chat_client = ChatClient::new(&routes::serve_ephemeral(server)).unwrap();
+
+        let command = ClientCommand::Send { room: "test_room".to_string(), user: Some("other_user".to_string()), text: "Hello".to_string() };
+        let error = execute(&chat_client, command, OutputFormat::Text).await.unwrap_err();
+        assert_eq!(error.exit_code(), exit_codes::REQUEST_FAILED);
+
+        let command = ClientCommand::Send { room: "unknown_room".to_string(), user: Some("test_user".to_string()), text: "Hello".to_string() };
+        let error = execute(&chat_client, command, OutputFormat::Text).await.unwrap_err();
+        assert_eq!(error.exit_code(), exit_codes::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_execute_command_send_as_profile_user() {
+        let server = test_server();
+        let chat_client = ChatClient::new(&routes::serve_ephemeral(server.clone())).unwrap();
+        let profile = Profile { username: Some("test_user".to_string()), ..Profile::default() };
+
+        let command = ClientCommand::Send { room: "test_room".to_string(), user: None, text: "Hello".to_string() };
+        execute_command(&chat_client, &profile, command, OutputFormat::Text, &mut Vec::new()).await.unwrap();
+        assert_eq!(server.read().unwrap().get_room_messages("test_room").unwrap().last().unwrap().author.username, "test_user");
+
+        let command = ClientCommand::Send { room: "test_room".to_string(), user: None, text: "Hello".to_string() };
+        let error = execute(&chat_client, command, OutputFormat::Text).await.unwrap_err();
+        assert_eq!(error.exit_code(), exit_codes::INVALID_USAGE);
+    }
+
+    #[tokio::test]
+    async fn test_execute_command_tail() {
+        let server = test_server();
+        for index in 0..3 {
+            server.read().unwrap().post_message_to_room("test_room", "test_user", &format!("message {}", index)).unwrap();
+        }
+        let chat_client = ChatClient::new(&routes::serve_ephemeral(server)).unwrap();
+
+        let command = ClientCommand::Tail { room: "test_room".to_string(), lines: 2, follow: false };
+        let out = execute(&chat_client, command, OutputFormat::Text).await.unwrap();
+        let lines: Vec<&str> = out.lines().collect();
+        assert_eq!(lines.len(), 2);
+        assert!(lines[0].ends_with("test_user: message 1"));
+        assert!(lines[1].ends_with("test_user: message 2"));
+
+        let command = ClientCommand::Tail { room: "test_room".to_string(), lines: 10, follow: false };
+        let out = execute(&chat_client, command, OutputFormat::Json).await.unwrap();
+        let messages: Vec<MessageView> = out.lines().map(|line| serde_json::from_str(line).unwrap()).collect();
+        assert_eq!(messages.len(), 3);
+        assert_eq!(messages[2].content, "message 2");
+    }
+
+    #[tokio::test]
+    async fn test_execute_command_export() {
+        let server = test_server();
+        for index in 0..3 {
+            server.read().unwrap().post_message_to_room("test_room", "test_user", &format!("message {}", index)).unwrap();
+        }
+        let chat_client = ChatClient::new(&routes::serve_ephemeral(server)).unwrap();
+
+        let command = ClientCommand::Export { room: "test_room".to_string(), format: ExportFormat::Markdown, since: None, until: None };
+        let out = execute(&chat_client, command, OutputFormat::Json).await.unwrap();
+        let lines: Vec<&str> = out.lines().collect();
+        assert_eq!(lines.len(), 5);
+        assert_eq!(lines[0], "# test\\_room");
+        assert!(lines[4].starts_with("- **test\\_user** (") && lines[4].ends_with("): message 2"));
+
+        let command = ClientCommand::Export { room: "missing_room".to_string(), format: ExportFormat::Csv, since: None, until: None };
+        let error = execute(&chat_client, command, OutputFormat::Text).await.unwrap_err();
+        assert_eq!(error.exit_code(), exit_codes::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_execute_command_rooms_list() {
+        let chat_client = ChatClient::new(&routes::serve_ephemeral(test_server())).unwrap();
+
+        let out = execute(&chat_client, ClientCommand::Rooms { command: RoomsCommand::List }, OutputFormat::Text).await.unwrap();
+        assert_eq!(out, "test_room (1 users)\n");
+
+        let out = execute(&chat_client, ClientCommand::Rooms { command: RoomsCommand::List }, OutputFormat::Json).await.unwrap();
+        let rooms: Vec<RoomSummary> = serde_json::from_str(&out).unwrap();
+        assert_eq!(rooms[0].name, "test_room");
+    }
+
+    #[tokio::test]
+    async fn test_execute_command_users_show() {
+        let chat_client = ChatClient::new(&routes::serve_ephemeral(test_server())).unwrap();
+
+        let command = ClientCommand::Users { command: UsersCommand::Show { username: "test_user".to_string() } };
+        let out = execute(&chat_client, command, OutputFormat::Json).await.unwrap();
+        let user: UserView = serde_json::from_str(&out).unwrap();
+        assert_eq!(user.username, "test_user");
+
+        let command = ClientCommand::Users { command: UsersCommand::Show { username: "unknown_user".to_string() } };
+        let error = execute(&chat_client, command, OutputFormat::Json).await.unwrap_err();
+        assert_eq!(error.exit_code(), exit_codes::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_execute_command_server_unreachable() {
+        let chat_client = ChatClient::with_options("http://localhost-non-existent:3012", ChatClientOptions {
+            retry_policy: RetryPolicy { max_attempts: 2, initial_backoff: Duration::from_millis(1), max_backoff: Duration::from_millis(1) },
+            ..ChatClientOptions::default()
+        }).unwrap();
+
+        let error = execute(&chat_client, ClientCommand::Rooms { command: RoomsCommand::List }, OutputFormat::Text).await.unwrap_err();
+        assert_eq!(error.exit_code(), exit_codes::SERVER_UNREACHABLE);
+    }
+
+    #[test]
+    fn test_chat_client_from_config_invalid_auth_token() {
+        let profile = Profile { auth_token: Some("token\n".to_string()), ..Profile::default() };
+
+        let error = CommandError::Api(chat_client_from_config(&Config::default(), &profile).unwrap_err());
+        assert!(matches!(&error, CommandError::Api(error) if error.error_id == error_ids::CLIENT_INVALID_AUTH_TOKEN));
+        assert_eq!(error.exit_code(), exit_codes::INVALID_USAGE);
+    }
+
+    #[test]
+    fn test_command_error_exit_code_output() {
+        assert_eq!(CommandError::Output(io::Error::from(io::ErrorKind::BrokenPipe)).exit_code(), exit_codes::SUCCESS);
+        assert_eq!(CommandError::Output(io::Error::from(io::ErrorKind::StorageFull)).exit_code(), exit_codes::OUTPUT_FAILED);
+    }
+}
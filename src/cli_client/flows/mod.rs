@@ -1,3 +1,4 @@
 pub mod user_authentication;
 pub mod room_choice;
-pub mod room_chat;
\ No newline at end of file
+pub mod room_chat;
+pub mod slash_commands;
\ No newline at end of file
@@ -1,19 +1,27 @@
 use std::io::{self, Write};
-use chrono::{DateTime, Utc};
-use crate::cli_client::apis::rooms::{fetch_api_get_room_messages, fetch_api_post_message_to_room};
+use std::time::SystemTime;
+use crate::chat_client::ChatClient;
+use crate::cli_client::outbox::{Outbox, PendingMessage};
+use crate::cli_client::flows::slash_commands::{execute_slash_command, parse_slash_command, SlashCommand, SlashCommandOutcome};
 use crate::web_server::handlers::ErrorDetailsResponse;
-use crate::entities::message::Message;
+use crate::web_server::handlers::views::MessageView;
+use crate::cli_client::profiles::DisplayPreferences;
 
 enum RoomChatChoice {
     ViewMessages,
-    SendMessage
+    SendMessage,
+    SlashCommand(SlashCommand)
 }
 
-fn ask_if_wants_to_view_messages_or_send_new_message(username: &str, room_name: &str) -> RoomChatChoice {
+fn ask_if_wants_to_view_messages_or_send_new_message(username: &str, room_name: &str, pending_count: usize) -> RoomChatChoice {
     println!("You are in room '{}' as '{}'", room_name, username);
+    if pending_count > 0 {
+        println!("{} message(s) pending delivery", pending_count);
+    }
     println!("Do you want to view messages or send a new message?");
     println!("1. View messages");
     println!("2. Send a new message");
+    println!("Or type a command, /help to list them");
 
     loop {
         print!("Enter your choice: ");
@@ -22,6 +30,15 @@ fn ask_if_wants_to_view_messages_or_send_new_message(username: &str, room_name:
         io::stdin().read_line(&mut input_choice).expect("Failed to read line");
         let choice = input_choice.trim().to_string();
 
+        match parse_slash_command(&choice) {
+            Some(Ok(slash_command)) => return RoomChatChoice::SlashCommand(slash_command),
+            Some(Err(error_message)) => {
+                println!("{}", error_message);
+                continue;
+            },
+            None => ()
+        }
+
         match choice.as_str() {
             "1" => return RoomChatChoice::ViewMessages,
             "2" => return RoomChatChoice::SendMessage,
@@ -30,70 +47,226 @@ fn ask_if_wants_to_view_messages_or_send_new_message(username: &str, room_name:
     }
 }
 
-fn print_messages(messages: &Vec<Message>) {
+/// Prints the messages of the room, followed by the messages typed in it but not delivered yet.
+fn print_messages<'a>(messages: &[MessageView], pending_messages: impl Iterator<Item = &'a PendingMessage>, display: &DisplayPreferences) {
     print!("{esc}[2J{esc}[1;1H", esc = 27 as char);
 
-    if messages.is_empty() {
+    let mut pending_messages = pending_messages.peekable();
+    if messages.is_empty() && pending_messages.peek().is_none() {
         println!("No messages in this room yet.");
         return;
     }
 
-    for message in messages {
-        let datetime: DateTime<Utc> = message.timestamp.into();
-        let formatted_timestamp: String = datetime.format("%Y-%m-%d %H:%M:%S").to_string();
-        println!("[{}] {}: {}", formatted_timestamp, message.author.username, message.content);
+    let now = SystemTime::now();
+    let lines = messages.iter()
+        .map(|message| (message.timestamp, format_message(message, display, Some(now))))
+        .chain(pending_messages.map(|message| (message.queued_at, format_pending_message(message, display, now))));
+    for line in with_day_separators(lines, display) {
+        println!("{}", line);
+    }
+}
+
+fn print_pending_messages<'a>(pending_messages: impl Iterator<Item = &'a PendingMessage>, display: &DisplayPreferences) {
+    let now = SystemTime::now();
+    for pending_message in pending_messages {
+        println!("{}", format_pending_message(pending_message, display, now));
     }
 }
 
-async fn get_room_messages(server_endpoint: &str, room_name: &str) -> Result<Vec<Message>, ErrorDetailsResponse> {
-    let messages = fetch_api_get_room_messages(server_endpoint, room_name).await;
-    match messages {
-        Ok(messages) => Ok(messages),
-        Err(err) => Err(err)
+/// Inserts a line with the date before the lines of each day, if the display preferences ask for it.
+fn with_day_separators(lines: impl Iterator<Item = (SystemTime, String)>, display: &DisplayPreferences) -> Vec<String> {
+    let mut previous_date = None;
+    let mut lines_with_separators = Vec::new();
+    for (timestamp, line) in lines {
+        let date = display.timezone.date_time(timestamp).date_naive();
+        if display.day_separators && previous_date != Some(date) {
+            lines_with_separators.push(format!("--- {} ---", date.format("%A %-d %B %Y")));
+        }
+        previous_date = Some(date);
+        lines_with_separators.push(line);
     }
+    lines_with_separators
+}
+
+/// Formats a message as displayed in the room, e.g. `[2024-01-01 12:00:00] alice: Hello`,
+/// or `[5 min ago] alice: Hello` if given the current time and the display preferences ask for relative times.
+pub fn format_message(message: &MessageView, display: &DisplayPreferences, now: Option<SystemTime>) -> String {
+    let bot_marker = if message.author.is_bot { " [bot]" } else { "" };
+    format_line(message.timestamp, &format!("{}{}", message.author.username, bot_marker), &message.content, display, now)
+}
+
+/// Formats a message not delivered yet, e.g. `[just now] alice: Hello (pending)`.
+fn format_pending_message(message: &PendingMessage, display: &DisplayPreferences, now: SystemTime) -> String {
+    format!("{} (pending)", format_line(message.queued_at, &message.username, &message.content, display, Some(now)))
 }
 
-async fn send_message(server_endpoint: &str, room_name: &str, username: &str) -> Result<(), ErrorDetailsResponse> {
+fn format_line(timestamp: SystemTime, author: &str, content: &str, display: &DisplayPreferences, now: Option<SystemTime>) -> String {
+    if !display.show_timestamps {
+        return format!("{}: {}", author, content);
+    }
+    let formatted_timestamp = now
+        .filter(|_| display.relative_times)
+        .and_then(|now| format_elapsed(timestamp, now))
+        .unwrap_or_else(|| display.timezone.date_time(timestamp).format(&display.timestamp_format).to_string());
+    format!("[{}] {}: {}", formatted_timestamp, author, content)
+}
+
+/// How long before now the timestamp is, e.g. `5 min ago`, if less than a day.
+/// Timestamps after now, from a server whose clock is ahead, are `just now`.
+fn format_elapsed(timestamp: SystemTime, now: SystemTime) -> Option<String> {
+    let elapsed_seconds = now.duration_since(timestamp).unwrap_or_default().as_secs();
+    match elapsed_seconds {
+        0..=59 => Some("just now".to_string()),
+        60..=3599 => Some(format!("{} min ago", elapsed_seconds / 60)),
+        3600..=86399 => Some(format!("{} h ago", elapsed_seconds / 3600)),
+        _ => None
+    }
+}
+
+/// Delivers the messages pending in the outbox, telling about the ones refused by the server and the ones left while it is unreachable.
+async fn deliver_pending_messages(chat_client: &ChatClient, outbox: &mut Outbox) {
+    if outbox.pending_count(chat_client.server_endpoint()) == 0 {
+        return;
+    }
+    let report = outbox.deliver(chat_client).await;
+    for (message, error) in report.rejected {
+        println!("Could not deliver your message \"{}\" to room {}, it was dropped: {}", message.content, message.room_name, error);
+    }
+    if report.unreachable.is_some() {
+        println!("Server unreachable, {} message(s) pending delivery once it is back", outbox.pending_count(chat_client.server_endpoint()));
+    } else if report.delivered > 0 {
+        println!("Delivered {} pending message(s)", report.delivered);
+    }
+}
+
+/// Queues the typed message in the outbox, to be delivered after the messages still pending.
+/// If a slash command is typed instead, it is returned without sending anything.
+async fn send_message(chat_client: &ChatClient, room_name: &str, username: &str, outbox: &mut Outbox) -> Option<SlashCommand> {
     loop {
         print!("Enter the message you want to send: ");
         io::stdout().flush().unwrap();
         let mut message_content = String::new();
         io::stdin().read_line(&mut message_content).expect("Failed to read line");
         let message_content = message_content.trim().to_string();
+        match parse_slash_command(&message_content) {
+            Some(Ok(slash_command)) => return Some(slash_command),
+            Some(Err(error_message)) => {
+                println!("{}", error_message);
+                continue;
+            },
+            None => ()
+        }
         if !message_content.is_empty() {
-            let send_message_response = fetch_api_post_message_to_room(server_endpoint, room_name, username, &message_content).await;
-            match send_message_response {
-                Ok(_) => return Ok(()),
-                Err(err) => return Err(err)
-            }
+            outbox.queue(chat_client.server_endpoint(), room_name, username, &message_content);
+            return None;
         }
         println!("Message cannot be empty. Please try again.");
     }
 }
 
-async fn room_chat_flow(server_endpoint: &str, username: &str, room_name: &str) -> Result<(), ErrorDetailsResponse> {
+/// Chats in the room until a slash command asks to move elsewhere.
+async fn room_chat_flow(chat_client: &ChatClient, username: &str, room_name: &str, display: &DisplayPreferences, outbox: &mut Outbox) -> Result<SlashCommandOutcome, ErrorDetailsResponse> {
     loop {
-        let choice = ask_if_wants_to_view_messages_or_send_new_message(username, room_name);
+        // Delivers the message just typed, and retries the ones typed while the server was unreachable.
+        deliver_pending_messages(chat_client, outbox).await;
+        let server_endpoint = chat_client.server_endpoint();
+        let choice = ask_if_wants_to_view_messages_or_send_new_message(username, room_name, outbox.pending_count(server_endpoint));
 
-        match choice {
+        let slash_command = match choice {
             RoomChatChoice::ViewMessages => {
-                let messages = get_room_messages(server_endpoint, room_name).await?;
-                print_messages(&messages);
+                match chat_client.get_room_messages(room_name).await {
+                    Ok(messages) => print_messages(&messages, outbox.pending(server_endpoint, room_name), display),
+                    Err(error) => {
+                        print_pending_messages(outbox.pending(server_endpoint, room_name), display);
+                        return Err(error);
+                    }
+                }
+                None
             },
-            RoomChatChoice::SendMessage => {
-                send_message(server_endpoint, room_name, username).await?;
+            RoomChatChoice::SendMessage => send_message(chat_client, room_name, username, outbox).await,
+            RoomChatChoice::SlashCommand(slash_command) => Some(slash_command)
+        };
+
+        if let Some(slash_command) = slash_command {
+            match execute_slash_command(chat_client, username, room_name, slash_command, outbox).await? {
+                SlashCommandOutcome::Continue => (),
+                outcome => return Ok(outcome)
             }
         }
     }
 }
 
-pub async fn loop_room_chat_flow(server_endpoint: &str, username: &str, room_name: &str) {
+pub async fn loop_room_chat_flow(chat_client: &ChatClient, username: &str, room_name: &str, display: &DisplayPreferences, outbox: &mut Outbox) -> SlashCommandOutcome {
     loop {
-        match room_chat_flow(server_endpoint, username, room_name).await {
-            Ok(_) => (),
+        match room_chat_flow(chat_client, username, room_name, display, outbox).await {
+            Ok(outcome) => return outcome,
             Err(error) => {
-                println!("Could not chat in the room - Please try again. Error was: {}", error.error_message);
+                println!("Could not chat in the room - Please try again. Error was: {}", error);
             }
         }
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli_client::profiles::DisplayTimezone;
+    use crate::web_server::handlers::views::UserView;
+    use std::time::Duration;
+
+    fn display() -> DisplayPreferences {
+        DisplayPreferences { timezone: DisplayTimezone::try_from("+02:00".to_string()).unwrap(), ..DisplayPreferences::default() }
+    }
+
+    fn message(timestamp: SystemTime) -> MessageView {
+        MessageView {
+            id: uuid::Uuid::new_v4(),
+            author: UserView { id: uuid::Uuid::new_v4(), username: "alice".to_string(), is_bot: false },
+            content: "Hello".to_string(),
+            timestamp,
+            seq: 1,
+            client_message_id: None
+        }
+    }
+
+    #[test]
+    fn test_format_message() {
+        let timestamp = SystemTime::UNIX_EPOCH + Duration::from_secs(1700000000);
+        let now = timestamp + Duration::from_secs(5 * 60 + 30);
+
+        assert_eq!(format_message(&message(timestamp), &display(), None), "[2023-11-15 00:13:20] alice: Hello");
+        assert_eq!(format_message(&message(timestamp), &display(), Some(now)), "[5 min ago] alice: Hello");
+        let display_without_relative_times = DisplayPreferences { relative_times: false, ..display() };
+        assert_eq!(format_message(&message(timestamp), &display_without_relative_times, Some(now)), "[2023-11-15 00:13:20] alice: Hello");
+        let display_without_timestamps = DisplayPreferences { show_timestamps: false, ..display() };
+        assert_eq!(format_message(&message(timestamp), &display_without_timestamps, Some(now)), "alice: Hello");
+    }
+
+    #[test]
+    fn test_format_elapsed() {
+        let now = SystemTime::UNIX_EPOCH + Duration::from_secs(1700000000);
+        let ago = |seconds| format_elapsed(now - Duration::from_secs(seconds), now);
+
+        assert_eq!(ago(0).as_deref(), Some("just now"));
+        assert_eq!(ago(59).as_deref(), Some("just now"));
+        assert_eq!(ago(60).as_deref(), Some("1 min ago"));
+        assert_eq!(ago(3599).as_deref(), Some("59 min ago"));
+        assert_eq!(ago(3 * 3600 + 10).as_deref(), Some("3 h ago"));
+        assert_eq!(ago(86400), None);
+        assert_eq!(format_elapsed(now + Duration::from_secs(30), now).as_deref(), Some("just now"));
+    }
+
+    #[test]
+    fn test_with_day_separators() {
+        // 21:30 and 22:30 UTC on November 14th, the second one being on November 15th at +02:00
+        let first = SystemTime::UNIX_EPOCH + Duration::from_secs(1699997400);
+        let second = first + Duration::from_secs(3600);
+        let lines = || vec![(first, "first".to_string()), (first, "again".to_string()), (second, "second".to_string())].into_iter();
+
+        assert_eq!(with_day_separators(lines(), &display()), vec![
+            "--- Tuesday 14 November 2023 ---", "first", "again", "--- Wednesday 15 November 2023 ---", "second"
+        ]);
+        let display_without_separators = DisplayPreferences { day_separators: false, ..display() };
+        assert_eq!(with_day_separators(lines(), &display_without_separators), vec!["first", "again", "second"]);
+    }
+}
@@ -1,6 +1,6 @@
 use std::io::{self, Write};
 
-use crate::{cli_client::apis::rooms::*, web_server::handlers::ErrorDetailsResponse};
+use crate::{chat_client::ChatClient, entities::error::error_ids, web_server::handlers::ErrorDetailsResponse};
 
 fn ask_for_room_name_to_enter() -> String {
     loop {
@@ -58,82 +58,61 @@ fn ask_if_wants_to_be_added_to_room() -> bool {
     }
 }
 
-async fn choose_room(server_endpoint: &str) -> Result<String, ErrorDetailsResponse> {
+async fn choose_room(chat_client: &ChatClient) -> Result<String, ErrorDetailsResponse> {
     let room_name = ask_for_room_name_to_enter();
-    let room_exists_response = fetch_api_get_room_in_server_by_name(server_endpoint, &room_name).await;
-    match room_exists_response {
-        Ok(_) => Ok(room_name),
-        Err(error) => Err(error)
-    }
+    chat_client.get_room(&room_name).await?;
+    Ok(room_name)
 }
 
-async fn is_user_in_room(server_endpoint: &str, room_name: &str, username: &str) -> Result<bool, ErrorDetailsResponse> {
-    let user_in_room_response = fetch_api_get_user_in_room_by_name(server_endpoint, room_name, username).await;
-    match user_in_room_response {
+async fn is_user_in_room(chat_client: &ChatClient, room_name: &str, username: &str) -> Result<bool, ErrorDetailsResponse> {
+    match chat_client.get_room_user(room_name, username).await {
         Ok(_) => Ok(true),
-        Err(_) => Ok(false)
+        Err(error) if error.error_id == error_ids::USER_NOT_IN_ROOM => Ok(false),
+        Err(error) => Err(error)
     }
 }
 
-async fn create_room(server_endpoint: &str, username: &str) -> Result<String, ErrorDetailsResponse> {
+async fn create_room(chat_client: &ChatClient, username: &str) -> Result<String, ErrorDetailsResponse> {
     let room_name = ask_for_room_name_to_create();
-    let room_create_response = fetch_api_create_room_to_server(server_endpoint, &room_name, username).await;
-    match room_create_response {
-        Ok(_) => Ok(room_name),
-        Err(error) => Err(error)
-    }
+    chat_client.create_room(&room_name, username).await?;
+    Ok(room_name)
 }
 
-async fn room_choice_flow(server_endpoint: &str, username: &str) -> Result<String, ErrorDetailsResponse> {
-    let room_choice_trial = choose_room(server_endpoint).await;
-    if room_choice_trial.is_ok() {
-        let room_name = room_choice_trial.unwrap();
-        let user_in_room_trial = is_user_in_room(server_endpoint, &room_name, username).await;
-        if user_in_room_trial.is_ok() {
-            let user_in_room = user_in_room_trial.unwrap();
-            if user_in_room {
-                return Ok(room_name);
-            }
-
-            let wants_to_be_added = ask_if_wants_to_be_added_to_room();
-            if wants_to_be_added {
-                let add_user_to_room_response = fetch_api_add_user_to_room(server_endpoint, &room_name, username).await;
-                if add_user_to_room_response.is_ok() {
-                    return Ok(room_name);
-                }
-
-                return Err(add_user_to_room_response.unwrap_err());
+async fn room_choice_flow(chat_client: &ChatClient, username: &str) -> Result<String, ErrorDetailsResponse> {
+    let room_name = match choose_room(chat_client).await {
+        Ok(room_name) => room_name,
+        Err(error) if error.error_id == error_ids::ROOM_NOT_FOUND => {
+            if ask_if_wants_to_create_room() {
+                return create_room(chat_client, username).await;
             }
+            return Err(error);
+        },
+        Err(error) => return Err(error)
+    };
 
-            return Err(ErrorDetailsResponse {
-                error_id: "ERR__USER_NOT_ADDED_TO_ROOM".to_string(),
-                error_message: "User chose not to be added to the room.".to_string()
-            });
-        }
-
-        return Err(user_in_room_trial.unwrap_err());
+    if is_user_in_room(chat_client, &room_name, username).await? {
+        return Ok(room_name);
     }
 
-    let wants_to_create_room = ask_if_wants_to_create_room();
-    if wants_to_create_room {
-        let room_create_trial = create_room(server_endpoint, username).await;
-        if room_create_trial.is_ok() {
-            return Ok(room_create_trial.unwrap());
-        }
-
-        return Err(room_create_trial.unwrap_err());
+    if ask_if_wants_to_be_added_to_room() {
+        chat_client.join_room(&room_name, username).await?;
+        return Ok(room_name);
     }
 
-    return Err(room_choice_trial.unwrap_err());
+    Err(ErrorDetailsResponse {
+        error_id: error_ids::CLIENT_USER_NOT_ADDED_TO_ROOM.to_string(),
+        error_message: "User chose not to be added to the room.".to_string(),
+        request_id: None
+    })
 }
 
-pub async fn loop_room_choice_flow(server_endpoint: &str, username: &str) -> String {
+pub async fn loop_room_choice_flow(chat_client: &ChatClient, username: &str) -> String {
     loop {
-        let room_choice_result = room_choice_flow(server_endpoint, username).await;
+        let room_choice_result = room_choice_flow(chat_client, username).await;
         match room_choice_result {
             Ok(room_name) => return room_name,
             Err(error) => {
-                println!("Could not choose room - Please try again. Error was: {}", error.error_message);
+                println!("Could not choose room - Please try again. Error was: {}", error);
             }
         }
     }
@@ -0,0 +1,255 @@
+use crate::chat_client::ChatClient;
+use crate::cli_client::outbox::Outbox;
+use crate::entities::error::error_ids;
+use crate::web_server::handlers::ErrorDetailsResponse;
+
+#[derive(Debug, PartialEq)]
+pub enum SlashCommand {
+    Join(String),
+    Leave,
+    Rooms,
+    Who,
+    Me(String),
+    Dm(String),
+    Quit,
+    Help
+}
+
+/// What the room chat should do once a slash command has been executed.
+#[derive(Debug, PartialEq)]
+pub enum SlashCommandOutcome {
+    Continue,
+    SwitchRoom(String),
+    ChooseRoom,
+    /// The user asked to exit the client
+    Quit
+}
+
+const HELP_TEXT: &str = "Available commands:
+  /join <room>   Enter a room, creating it if it does not exist
+  /leave         Leave the current room and choose another one
+  /rooms         List the rooms in the server
+  /who           List the users in the current room
+  /me <action>   Send an action message, e.g. /me waves
+  /dm <user>     Open the room dm:<you>:<user>, adding the user to it;
+                 it is a plain room, listed by /rooms and open to anyone
+  /quit          Exit the client
+  /help          Show this help";
+
+/// Parses the user input as a slash command.
+/// Returns None if the input is not a command, so that it can be handled as a regular message.
+pub fn parse_slash_command(input: &str) -> Option<Result<SlashCommand, String>> {
+    let input = input.trim();
+    let command_line = input.strip_prefix('/')?;
+    let (command, argument) = match command_line.split_once(char::is_whitespace) {
+        Some((command, argument)) => (command, argument.trim()),
+        None => (command_line, "")
+    };
+
+    let missing_argument = |usage: &str| Err(format!("Missing argument, usage: {}", usage));
+    let slash_command = match command {
+        "join" if argument.is_empty() => missing_argument("/join <room>"),
+        "join" => Ok(SlashCommand::Join(argument.to_string())),
+        "leave" => Ok(SlashCommand::Leave),
+        "rooms" => Ok(SlashCommand::Rooms),
+        "who" => Ok(SlashCommand::Who),
+        "me" if argument.is_empty() => missing_argument("/me <action>"),
+        "me" => Ok(SlashCommand::Me(argument.to_string())),
+        "dm" if argument.is_empty() => missing_argument("/dm <user>"),
+        "dm" => Ok(SlashCommand::Dm(argument.to_string())),
+        "quit" => Ok(SlashCommand::Quit),
+        "help" => Ok(SlashCommand::Help),
+        _ => Err(format!("Unknown command /{}, type /help to list the available commands", command))
+    };
+    Some(slash_command)
+}
+
+/// Name of the room opened by `/dm`, the same whoever of the two users opens it.
+/// It is a plain room shared by the two users, not a private conversation: anyone can list and join it.
+pub fn direct_message_room_name(username: &str, other_username: &str) -> String {
+    let mut usernames = [username, other_username];
+    usernames.sort();
+    format!("dm:{}:{}", usernames[0], usernames[1])
+}
+
+async fn ensure_user_in_room(chat_client: &ChatClient, room_name: &str, username: &str) -> Result<(), ErrorDetailsResponse> {
+    match chat_client.get_room_user(room_name, username).await {
+        Ok(_) => Ok(()),
+        Err(error) if error.error_id == error_ids::USER_NOT_IN_ROOM => {
+            chat_client.join_room(room_name, username).await?;
+            Ok(())
+        },
+        Err(error) => Err(error)
+    }
+}
+
+async fn join_room(chat_client: &ChatClient, username: &str, room_name: &str) -> Result<(), ErrorDetailsResponse> {
+    match chat_client.get_room(room_name).await {
+        Ok(_) => ensure_user_in_room(chat_client, room_name, username).await,
+        Err(error) if error.error_id == error_ids::ROOM_NOT_FOUND => {
+            chat_client.create_room(room_name, username).await?;
+            Ok(())
+        },
+        Err(error) => Err(error)
+    }
+}
+
+/// Joins the room shared with the other user, creating it if needed, and adds the other user to it.
+async fn open_direct_message_room(chat_client: &ChatClient, username: &str, other_username: &str) -> Result<String, ErrorDetailsResponse> {
+    if username == other_username {
+        return Err(ErrorDetailsResponse {
+            error_id: error_ids::CLIENT_DM_TO_SELF.to_string(),
+            error_message: "Cannot open a room shared with yourself".to_string(),
+            request_id: None
+        });
+    }
+
+    chat_client.get_user(other_username).await?;
+    let room_name = direct_message_room_name(username, other_username);
+    join_room(chat_client, username, &room_name).await?;
+    ensure_user_in_room(chat_client, &room_name, other_username).await?;
+    Ok(room_name)
+}
+
+/// Executes the slash command typed in the room, the action messages of `/me` being queued in the outbox like the other messages.
+pub async fn execute_slash_command(chat_client: &ChatClient, username: &str, room_name: &str, slash_command: SlashCommand, outbox: &mut Outbox) -> Result<SlashCommandOutcome, ErrorDetailsResponse> {
+    match slash_command {
+        SlashCommand::Join(target_room_name) => {
+            join_room(chat_client, username, &target_room_name).await?;
+            Ok(SlashCommandOutcome::SwitchRoom(target_room_name))
+        },
+        SlashCommand::Leave => {
+            chat_client.leave_room(room_name, username).await?;
+            println!("Left room {}", room_name);
+            Ok(SlashCommandOutcome::ChooseRoom)
+        },
+        SlashCommand::Rooms => {
+            let rooms = chat_client.get_rooms().await?;
+            println!("Rooms in the server:");
+            for room in rooms {
+                let current_marker = if room.name == room_name { " (current)" } else { "" };
+                println!("  {}{}", room.name, current_marker);
+            }
+            Ok(SlashCommandOutcome::Continue)
+        },
+        SlashCommand::Who => {
+            let room = chat_client.get_room(room_name).await?;
+            println!("Users in room {}:", room_name);
+            for user in room.users {
+                println!("  {}", user.username);
+            }
+            Ok(SlashCommandOutcome::Continue)
+        },
+        SlashCommand::Me(action) => {
+            outbox.queue(chat_client.server_endpoint(), room_name, username, &format!("* {} {}", username, action));
+            Ok(SlashCommandOutcome::Continue)
+        },
+        SlashCommand::Dm(other_username) => {
+            let direct_message_room = open_direct_message_room(chat_client, username, &other_username).await?;
+            Ok(SlashCommandOutcome::SwitchRoom(direct_message_room))
+        },
+        SlashCommand::Quit => Ok(SlashCommandOutcome::Quit),
+        SlashCommand::Help => {
+            println!("{}", HELP_TEXT);
+            Ok(SlashCommandOutcome::Continue)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::entities::server::{Server, SharedServer};
+    use crate::web_server::routes;
+
+    #[test]
+    fn test_parse_slash_command_not_a_command() {
+        assert_eq!(parse_slash_command("hello"), None);
+    }
+
+    #[test]
+    fn test_parse_slash_command_with_argument() {
+        assert_eq!(parse_slash_command("/join  general "), Some(Ok(SlashCommand::Join("general".to_string()))));
+        assert_eq!(parse_slash_command("/me waves at everyone"), Some(Ok(SlashCommand::Me("waves at everyone".to_string()))));
+        assert_eq!(parse_slash_command("/dm alice"), Some(Ok(SlashCommand::Dm("alice".to_string()))));
+    }
+
+    #[test]
+    fn test_parse_slash_command_without_argument() {
+        assert_eq!(parse_slash_command("/leave"), Some(Ok(SlashCommand::Leave)));
+        assert_eq!(parse_slash_command("/rooms"), Some(Ok(SlashCommand::Rooms)));
+        assert_eq!(parse_slash_command("/who"), Some(Ok(SlashCommand::Who)));
+        assert_eq!(parse_slash_command("/quit"), Some(Ok(SlashCommand::Quit)));
+        assert_eq!(parse_slash_command("/help"), Some(Ok(SlashCommand::Help)));
+    }
+
+    #[test]
+    fn test_parse_slash_command_missing_argument() {
+        assert!(parse_slash_command("/join").unwrap().is_err());
+        assert!(parse_slash_command("/dm ").unwrap().is_err());
+    }
+
+    #[test]
+    fn test_parse_slash_command_unknown() {
+        assert!(parse_slash_command("/dance").unwrap().is_err());
+    }
+
+    #[test]
+    fn test_direct_message_room_name() {
+        assert_eq!(direct_message_room_name("bob", "alice"), "dm:alice:bob");
+        assert_eq!(direct_message_room_name("alice", "bob"), "dm:alice:bob");
+    }
+
+    fn test_chat_client(server: &SharedServer) -> ChatClient {
+        server.write().unwrap().register_user("test_user").unwrap();
+        server.write().unwrap().create_room("test_room", "test_user").unwrap();
+        ChatClient::new(&routes::serve_ephemeral(server.clone())).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_execute_slash_command_join_creates_missing_room() {
+        let chat_client = test_chat_client(&Server::new_shared());
+
+        let outcome = execute_slash_command(&chat_client, "test_user", "test_room", SlashCommand::Join("general".to_string()), &mut Outbox::in_memory()).await;
+        assert_eq!(outcome.unwrap(), SlashCommandOutcome::SwitchRoom("general".to_string()));
+        assert!(chat_client.get_room_user("general", "test_user").await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_execute_slash_command_leave() {
+        let chat_client = test_chat_client(&Server::new_shared());
+
+        let outcome = execute_slash_command(&chat_client, "test_user", "test_room", SlashCommand::Leave, &mut Outbox::in_memory()).await;
+        assert_eq!(outcome.unwrap(), SlashCommandOutcome::ChooseRoom);
+        assert!(chat_client.get_room_user("test_room", "test_user").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_execute_slash_command_quit() {
+        let chat_client = ChatClient::new("http://localhost-non-existent:3012").unwrap();
+        let outcome = execute_slash_command(&chat_client, "test_user", "test_room", SlashCommand::Quit, &mut Outbox::in_memory()).await;
+        assert_eq!(outcome.unwrap(), SlashCommandOutcome::Quit);
+    }
+
+    #[tokio::test]
+    async fn test_execute_slash_command_me() {
+        let server = Server::new_shared();
+        let chat_client = test_chat_client(&server);
+
+        let mut outbox = Outbox::in_memory();
+        let outcome = execute_slash_command(&chat_client, "test_user", "test_room", SlashCommand::Me("waves".to_string()), &mut outbox).await;
+        assert_eq!(outcome.unwrap(), SlashCommandOutcome::Continue);
+        let pending_message = outbox.pending(chat_client.server_endpoint(), "test_room").next().unwrap();
+        assert_eq!(pending_message.content, "* test_user waves");
+        outbox.deliver(&chat_client).await;
+        assert_eq!(server.read().unwrap().get_room_messages("test_room").unwrap().last().unwrap().content, "* test_user waves");
+    }
+
+    #[tokio::test]
+    async fn test_execute_slash_command_dm_user_not_found() {
+        let chat_client = test_chat_client(&Server::new_shared());
+
+        let outcome = execute_slash_command(&chat_client, "test_user", "test_room", SlashCommand::Dm("alice".to_string()), &mut Outbox::in_memory()).await;
+        assert_eq!(outcome.unwrap_err().error_id, "ERR__USER_NOT_FOUND");
+    }
+}
@@ -1,4 +1,4 @@
-use crate::{cli_client::apis::users::*, web_server::handlers::ErrorDetailsResponse};
+use crate::{chat_client::ChatClient, entities::error::error_ids, web_server::handlers::ErrorDetailsResponse};
 use std::io::{self, Write};
 
 fn ask_for_authentication_username() -> String {
@@ -57,61 +57,44 @@ fn ask_if_wants_to_authenticate() -> bool {
     }
 }
 
-async fn authenticate_user(server_endpoint: &str) -> Result<String, ErrorDetailsResponse> {
+async fn authenticate_user(chat_client: &ChatClient) -> Result<String, ErrorDetailsResponse> {
     let username = ask_for_authentication_username();
-    let get_user_response = fetch_api_get_user_in_server_by_username(server_endpoint, &username).await;
-    match get_user_response {
-        Ok(_) => Ok(username),
-        Err(error) => Err(error)
-    }
+    chat_client.get_user(&username).await?;
+    Ok(username)
 }
 
-async fn register_user(server_endpoint: &str) -> Result<String, ErrorDetailsResponse> {
+async fn register_user(chat_client: &ChatClient) -> Result<String, ErrorDetailsResponse> {
     let username = ask_for_registration_username();
-    let register_user_response = fetch_api_register_user_to_server(server_endpoint, &username).await;
-    match register_user_response {
-        Ok(_) => Ok(username),
-        Err(error) => Err(error)
-    }
+    chat_client.register_user(&username).await?;
+    Ok(username)
 }
 
-async fn user_authentication_flow(server_endpoint: &str) -> Result<String, ErrorDetailsResponse> {
-    let authentication_trial_response = authenticate_user(server_endpoint).await;
-    if authentication_trial_response.is_ok() {
-        return Ok(authentication_trial_response.unwrap());
-    }
-
-    let authentication_trial_error = authentication_trial_response.err().unwrap();
-    if authentication_trial_error.error_id == "ERR__USER_NOT_FOUND" {
-        let wants_to_register = ask_if_wants_to_register();
-        if wants_to_register {
-            let registration_trial_response = register_user(server_endpoint).await;
-            if registration_trial_response.is_ok() {
-                return Ok(registration_trial_response.unwrap());
-            }
-
-            let registration_trial_error = registration_trial_response.err().unwrap();
-            if registration_trial_error.error_id == "ERR__USER_ALREADY_EXISTS" {
-                let wants_to_authenticate = ask_if_wants_to_authenticate();
-                if wants_to_authenticate {
-                    return authenticate_user(server_endpoint).await;
-                }
-            }
+async fn user_authentication_flow(chat_client: &ChatClient) -> Result<String, ErrorDetailsResponse> {
+    let authentication_trial_error = match authenticate_user(chat_client).await {
+        Ok(username) => return Ok(username),
+        Err(error) => error
+    };
 
-            return Err(registration_trial_error);
-        }
+    if authentication_trial_error.error_id != error_ids::USER_NOT_FOUND || !ask_if_wants_to_register() {
+        return Err(authentication_trial_error);
     }
 
-    Err(authentication_trial_error)
+    match register_user(chat_client).await {
+        Ok(username) => Ok(username),
+        Err(error) if error.error_id == error_ids::USER_ALREADY_EXISTS && ask_if_wants_to_authenticate() => {
+            authenticate_user(chat_client).await
+        },
+        Err(error) => Err(error)
+    }
 }
 
-pub async fn loop_user_authentication_flow(server_endpoint: &str) -> String {
+pub async fn loop_user_authentication_flow(chat_client: &ChatClient) -> String {
     loop {
-        let authentication_result = user_authentication_flow(server_endpoint).await;
+        let authentication_result = user_authentication_flow(chat_client).await;
         match authentication_result {
             Ok(username) => return username,
             Err(error) => {
-                println!("Could not authenticate to server - Please try again. Error was: {}", error.error_message);
+                println!("Could not authenticate to server - Please try again. Error was: {}", error);
             }
         }
     }
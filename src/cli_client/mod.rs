@@ -1,53 +1,207 @@
-use std::process::exit;
+use std::time::Duration;
+use flows::slash_commands::SlashCommandOutcome;
+use outbox::Outbox;
+use profiles::{ActiveProfile, DisplayPreferences, Profile};
+use crate::chat_client::{load_ca_certificate, retry::RetryPolicy, ChatClient, ChatClientOptions, UNKNOWN_SERVER_VERSION};
+use crate::config::Config;
+use crate::entities::error::error_ids;
+use crate::web_server::handlers::{health::is_version_compatible, ErrorDetailsResponse};
+
+const CLIENT_VERSION: &str = env!("CARGO_PKG_VERSION");
+/// Versions of the REST API the client can talk, from the oldest to the newest
+const CLIENT_API_VERSIONS: [&str; 1] = ["v1"];
+/// Interval between two liveness checks while the server answers them with an error
+const RECONNECT_INTERVAL: Duration = Duration::from_secs(5);
 
-mod apis;
 mod flows;
+pub mod commands;
+pub mod outbox;
+pub mod profiles;
+
+/// Creates the client of the server configured with the host, port and TLS settings, sending the auth token of the profile.
+pub fn chat_client_from_config(config: &Config, profile: &Profile) -> Result<ChatClient, ErrorDetailsResponse> {
+    let invalid_configuration = |error_id: &str, error_message: String| ErrorDetailsResponse {
+        error_id: error_id.to_string(),
+        error_message,
+        request_id: None
+    };
+    let server_endpoint = config.server_endpoint().map_err(|error| invalid_configuration(error_ids::CLIENT_INVALID_ENDPOINT, error.to_string()))?;
+    let ca_certificate = config.tls.ca_cert_path.as_deref()
+        .map(load_ca_certificate)
+        .transpose()
+        .map_err(|error| invalid_configuration(error_ids::CLIENT_INVALID_CA_CERTIFICATE, error))?;
+    ChatClient::with_options(&server_endpoint, ChatClientOptions {
+        ca_certificate,
+        auth_token: profile.auth_token.clone(),
+        ..ChatClientOptions::default()
+    })
+}
 
 pub struct CliClient {
-    server_endpoint: String,
+    chat_client: ChatClient,
     current_username: String,
-    current_room: String
+    current_room: String,
+    /// The profile remembering the user and the last room, if selected with `--profile`
+    profile: Option<ActiveProfile>,
+    display: DisplayPreferences,
+    /// The messages typed while the server could not be reached, delivered once it is back
+    outbox: Outbox
 }
 
 impl CliClient {
-    pub fn new(server_host: String, server_port: u16) -> CliClient {
+    pub fn new(chat_client: ChatClient, profile: Option<ActiveProfile>, outbox: Outbox) -> CliClient {
+        let display = profile.as_ref().map(|active_profile| active_profile.profile.display.clone()).unwrap_or_default();
         CliClient {
-            server_endpoint: format!("http://{}:{}", server_host, server_port),
+            chat_client: chat_client.on_retry(|event| println!("{}", event)),
             current_username: String::new(),
-            current_room: String::new()
+            current_room: String::new(),
+            profile,
+            display,
+            outbox
         }
     }
 
-    pub async fn run(&mut self) {
+    /// Runs the client until the user quits, failing only if the server talks no REST API version this client supports.
+    pub async fn run(&mut self) -> Result<(), String> {
         println!("Starting CLI client");
-        self.is_server_alive().await;
+        self.is_server_alive().await?;
         self.authenticate_user().await;
-        self.choose_room().await;
-        self.chat_in_room().await;
+        match self.enter_remembered_room().await {
+            Some(room_name) => self.enter_room(room_name),
+            None => self.choose_room().await
+        }
+        loop {
+            match self.chat_in_room().await {
+                SlashCommandOutcome::SwitchRoom(room_name) => self.enter_room(room_name),
+                SlashCommandOutcome::ChooseRoom => self.choose_room().await,
+                SlashCommandOutcome::Continue => (),
+                SlashCommandOutcome::Quit => {
+                    println!("Goodbye!");
+                    return Ok(());
+                }
+            }
+        }
     }
 
-    async fn is_server_alive(&self) -> () {
-        println!("Checking connection with server at {}", self.server_endpoint);
-        let is_alive = apis::fetch_api_is_server_alive(&self.server_endpoint).await;
-        if is_alive {
-            println!("Server is reachable, connection established");
+    /// Updates the selected profile, if any, and saves it.
+    fn remember(&mut self, update: impl FnOnce(&mut Profile)) {
+        if let Some(active_profile) = &mut self.profile {
+            update(&mut active_profile.profile);
+            if let Err(error) = active_profile.save() {
+                println!("Warning: could not save profile {}: {}", active_profile.name, error);
+            }
+        }
+    }
+
+    /// Waits for the server to be reachable, reconnecting with backoff meanwhile, then negotiates the REST API version.
+    async fn is_server_alive(&mut self) -> Result<(), String> {
+        println!("Checking connection with server at {}", self.chat_client.server_endpoint());
+        let reconnecting_chat_client = self.chat_client.clone().with_retry_policy(RetryPolicy { max_attempts: RetryPolicy::UNLIMITED_ATTEMPTS, ..RetryPolicy::default() });
+        let liveness = loop {
+            match reconnecting_chat_client.liveness().await {
+                Ok(liveness) => break liveness,
+                Err(error) => {
+                    println!("Server is not available: {}, checking again in {}s", error, RECONNECT_INTERVAL.as_secs());
+                    tokio::time::sleep(RECONNECT_INTERVAL).await;
+                }
+            }
+        };
+        if liveness.version == UNKNOWN_SERVER_VERSION {
+            println!("Server is reachable (version unknown, predating the health probes), connection established");
         } else {
-            println!("Server is unreachable, connection failed");
-            exit(1);
+            println!("Server is reachable (version {}, up for {}s), connection established", liveness.version, liveness.uptime_seconds);
+        }
+        if !is_version_compatible(CLIENT_VERSION, &liveness.version) {
+            println!("Warning: client version {} may be incompatible with server version {}", CLIENT_VERSION, liveness.version);
         }
+        let api_version = negotiate_api_version(&CLIENT_API_VERSIONS, &liveness.api_versions).map_err(|()| {
+            format!("Server API versions {} are not supported by this client, which supports {}", liveness.api_versions.join(", "), CLIENT_API_VERSIONS.join(", "))
+        })?;
+        self.chat_client = self.chat_client.clone().with_api_version(api_version);
+        Ok(())
     }
 
+    /// Authenticates as the user remembered by the profile, asking for a user if there is none or if it does not exist anymore.
     async fn authenticate_user(&mut self) {
-        self.current_username = flows::user_authentication::loop_user_authentication_flow(&self.server_endpoint).await;
+        let remembered_username = self.profile.as_ref().and_then(|active_profile| active_profile.profile.username.clone());
+        let authenticated_username = match remembered_username {
+            Some(username) => match self.chat_client.get_user(&username).await {
+                Ok(_) => Some(username),
+                Err(error) => {
+                    println!("Could not authenticate as {} from the profile: {}", username, error);
+                    None
+                }
+            },
+            None => None
+        };
+        self.current_username = match authenticated_username {
+            Some(username) => username,
+            None => flows::user_authentication::loop_user_authentication_flow(&self.chat_client).await
+        };
         println!("Authenticated as {}", self.current_username);
+        let username = self.current_username.clone();
+        self.remember(|profile| profile.username = Some(username));
+    }
+
+    /// Enters the room remembered by the profile, joining it if needed.
+    /// Returns `None` if there is no such room, to ask for a room instead.
+    async fn enter_remembered_room(&self) -> Option<String> {
+        let room_name = self.profile.as_ref()?.profile.room_to_enter()?.to_string();
+        let entered = match self.chat_client.get_room_user(&room_name, &self.current_username).await {
+            Err(error) if error.error_id == error_ids::USER_NOT_IN_ROOM => {
+                self.chat_client.join_room(&room_name, &self.current_username).await.map(|_| ())
+            },
+            entered => entered.map(|_| ())
+        };
+        match entered {
+            Ok(()) => Some(room_name),
+            Err(error) => {
+                println!("Could not enter room {} from the profile: {}", room_name, error);
+                None
+            }
+        }
+    }
+
+    fn enter_room(&mut self, room_name: String) {
+        println!("Entered room {}", room_name);
+        self.current_room = room_name.clone();
+        self.remember(|profile| profile.last_room = Some(room_name));
     }
 
     async fn choose_room(&mut self) -> () {
-        self.current_room = flows::room_choice::loop_room_choice_flow(&self.server_endpoint, &self.current_username).await;
-        println!("Entered room {}", self.current_room);
+        let room_name = flows::room_choice::loop_room_choice_flow(&self.chat_client, &self.current_username).await;
+        self.enter_room(room_name);
     }
 
-    async fn chat_in_room(&self) -> () {
-        flows::room_chat::loop_room_chat_flow(&self.server_endpoint, &self.current_username, &self.current_room).await;
+    async fn chat_in_room(&mut self) -> SlashCommandOutcome {
+        flows::room_chat::loop_room_chat_flow(&self.chat_client, &self.current_username, &self.current_room, &self.display, &mut self.outbox).await
+    }
+}
+
+/// Picks the newest REST API version supported by both the client and the server.
+/// Servers reporting no version predate the versioned paths and are talked to on the unversioned ones, hence `Ok(None)`.
+fn negotiate_api_version<'a>(client_api_versions: &[&'a str], server_api_versions: &[String]) -> Result<Option<&'a str>, ()> {
+    if server_api_versions.is_empty() {
+        return Ok(None);
+    }
+    client_api_versions.iter().rev()
+        .find(|client_api_version| server_api_versions.iter().any(|server_api_version| server_api_version == *client_api_version))
+        .map(|client_api_version| Some(*client_api_version))
+        .ok_or(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_negotiate_api_version() {
+        let server_api_versions = |versions: &[&str]| versions.iter().map(|version| version.to_string()).collect::<Vec<_>>();
+
+        assert_eq!(negotiate_api_version(&["v1"], &server_api_versions(&["v1"])), Ok(Some("v1")));
+        assert_eq!(negotiate_api_version(&["v1", "v2"], &server_api_versions(&["v1", "v2", "v3"])), Ok(Some("v2")));
+        assert_eq!(negotiate_api_version(&["v1", "v2"], &server_api_versions(&["v1"])), Ok(Some("v1")));
+        assert_eq!(negotiate_api_version(&["v1"], &server_api_versions(&[])), Ok(None));
+        assert_eq!(negotiate_api_version(&["v1"], &server_api_versions(&["v2"])), Err(()));
     }
 }
\ No newline at end of file
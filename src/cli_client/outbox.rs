@@ -0,0 +1,352 @@
+use crate::chat_client::ChatClient;
+use crate::cli_client::profiles::write_private_file;
+use crate::entities::error::error_ids;
+use crate::web_server::handlers::ErrorDetailsResponse;
+use serde::{Deserialize, Serialize};
+use std::time::{SystemTime, UNIX_EPOCH};
+use std::{fs, io, path::{Path, PathBuf}};
+use uuid::Uuid;
+
+/// Path of the outbox file relative to the user state directory.
+const OUTBOX_FILE_NAME: &str = "rust-live-chat/outbox.json";
+
+/// Message typed in the client and not received by the server yet.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PendingMessage {
+    /// Sent with every delivery attempt, for the server to post the message once however many times it is sent
+    pub client_message_id: Uuid,
+    /// The server endpoint the message is delivered to
+    pub server: String,
+    pub room_name: String,
+    pub username: String,
+    pub content: String,
+    #[serde(with = "crate::entities::timestamp")]
+    pub queued_at: SystemTime,
+}
+
+/// Outcome of an attempt to deliver the pending messages.
+#[derive(Debug, Default)]
+pub struct DeliveryReport {
+    pub delivered: usize,
+    /// Messages refused by the server, e.g. too long, dropped since sending them again would fail again
+    pub rejected: Vec<(PendingMessage, ErrorDetailsResponse)>,
+    /// Set if the server could not be reached or failed on its side, the messages left being delivered on the next attempt
+    pub unreachable: Option<ErrorDetailsResponse>,
+}
+
+/// Messages queued while the server cannot be reached, persisted so that they survive a restart of the client,
+/// and delivered in the order they were typed once the server is back.
+#[derive(Debug, Default)]
+pub struct Outbox {
+    /// The outbox file, or `None` if the messages are only kept in memory
+    path: Option<PathBuf>,
+    messages: Vec<PendingMessage>,
+}
+
+impl Outbox {
+    /// Reads the outbox file, a missing or corrupt file having no pending messages.
+    pub fn load(path: &Path) -> Result<Outbox, String> {
+        Ok(Outbox { path: Some(path.to_path_buf()), messages: read_messages(path)?.unwrap_or_default() })
+    }
+
+    /// Outbox kept in memory only, for when no outbox file could be located.
+    pub fn in_memory() -> Outbox {
+        Outbox::default()
+    }
+
+    /// Messages pending delivery to the room, oldest first.
+    pub fn pending<'a>(&'a self, server: &'a str, room_name: &'a str) -> impl Iterator<Item = &'a PendingMessage> {
+        self.messages.iter().filter(move |message| message.server == server && message.room_name == room_name)
+    }
+
+    pub fn pending_count(&self, server: &str) -> usize {
+        self.messages.iter().filter(|message| message.server == server).count()
+    }
+
+    /// Queues a message, to be sent after the messages already pending for the server.
+    pub fn queue(&mut self, server: &str, room_name: &str, username: &str, content: &str) {
+        let message = PendingMessage {
+            client_message_id: Uuid::new_v4(),
+            server: server.to_string(),
+            room_name: room_name.to_string(),
+            username: username.to_string(),
+            content: content.to_string(),
+            queued_at: SystemTime::now(),
+        };
+        self.update(|messages| messages.push(message));
+    }
+
+    /// Sends the messages pending for the server of the client in order, stopping at the first one the server could not be reached for.
+    /// Each message is retried with backoff first, its `client_message_id` making it safe to send again.
+    pub async fn deliver(&mut self, chat_client: &ChatClient) -> DeliveryReport {
+        let server = chat_client.server_endpoint();
+        let mut report = DeliveryReport::default();
+        // Reloaded first, to deliver the messages queued by other clients sharing the outbox file too.
+        self.update(|_| ());
+        let pending_messages: Vec<PendingMessage> = self.messages.iter().filter(|message| message.server == server).cloned().collect();
+        for message in pending_messages {
+            match chat_client.post_message_with_id(&message.room_name, &message.username, &message.content, Some(message.client_message_id)).await {
+                Ok(_) => report.delivered += 1,
+                Err(error) if is_server_failure(&error) => {
+                    report.unreachable = Some(error);
+                    break;
+                },
+                Err(error) => report.rejected.push((message.clone(), error))
+            }
+            self.update(|messages| messages.retain(|pending_message| pending_message.client_message_id != message.client_message_id));
+        }
+        report
+    }
+
+    /// Applies the change to the messages of the outbox file as it is now, and saves them,
+    /// the file being locked meanwhile against the other clients sharing it.
+    /// A file that cannot be read or written is warned about, the messages being kept in memory meanwhile.
+    fn update(&mut self, change: impl FnOnce(&mut Vec<PendingMessage>)) {
+        let Some(path) = &self.path else {
+            change(&mut self.messages);
+            return;
+        };
+        let _lock = lock(path).inspect_err(|error| println!("Warning: could not lock outbox file {}: {}", path.display(), error));
+        match read_messages(path) {
+            Ok(Some(messages)) => self.messages = messages,
+            Ok(None) => (),
+            Err(error) => println!("Warning: {}", error)
+        }
+        change(&mut self.messages);
+        if let Err(error) = write_messages(path, &self.messages) {
+            println!("Warning: {}", error);
+        }
+    }
+}
+
+/// Whether the message failed to be delivered because of the server rather than of the message, to be sent again later:
+/// the server could not be reached, answered unexpectedly, e.g. with the 502 page of a proxy, or failed on its side.
+fn is_server_failure(error: &ErrorDetailsResponse) -> bool {
+    [error_ids::CLIENT_FETCH_API, error_ids::INTERNAL, error_ids::SERVER_UNAVAILABLE].contains(&error.error_id.as_str())
+}
+
+/// Reads the outbox file, a missing file having no pending messages.
+/// A corrupt file is moved aside with a warning, for the messages to be recovered by hand, and `None` is returned.
+fn read_messages(path: &Path) -> Result<Option<Vec<PendingMessage>>, String> {
+    let content = match fs::read_to_string(path) {
+        Ok(content) => content,
+        Err(error) if error.kind() == io::ErrorKind::NotFound => return Ok(Some(Vec::new())),
+        Err(error) => return Err(format!("Could not read outbox file {}: {}", path.display(), error)),
+    };
+    match serde_json::from_str(&content) {
+        Ok(messages) => Ok(Some(messages)),
+        Err(error) => {
+            let mut corrupt_path = path.as_os_str().to_owned();
+            corrupt_path.push(format!(".corrupt-{}", SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs()));
+            let corrupt_path = PathBuf::from(corrupt_path);
+            fs::rename(path, &corrupt_path)
+                .map_err(|rename_error| format!("Invalid outbox file {}: {}, and it could not be moved aside: {}", path.display(), error, rename_error))?;
+            println!("Warning: invalid outbox file {}: {}, moved to {}", path.display(), error, corrupt_path.display());
+            Ok(None)
+        }
+    }
+}
+
+/// Writes the outbox file, readable by its owner only since it holds the messages not sent yet.
+fn write_messages(path: &Path, messages: &[PendingMessage]) -> Result<(), String> {
+    let write_error = |error: io::Error| format!("Could not write outbox file {}: {}", path.display(), error);
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(write_error)?;
+    }
+    let content = serde_json::to_string_pretty(messages).expect("pending messages are serializable");
+    write_private_file(path, content.as_bytes()).map_err(write_error)
+}
+
+/// Takes the advisory lock of the outbox file, held until the returned lock file is closed.
+fn lock(path: &Path) -> io::Result<fs::File> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let mut lock_path = path.as_os_str().to_owned();
+    lock_path.push(".lock");
+    let lock_file = fs::OpenOptions::new().write(true).create(true).truncate(false).open(lock_path)?;
+    lock_file.lock()?;
+    Ok(lock_file)
+}
+
+/// Default path of the outbox file, in the user state directory, e.g. `~/.local/state/rust-live-chat/outbox.json`.
+pub fn default_outbox_path(env: impl Fn(&str) -> Option<String>) -> Option<PathBuf> {
+    let state_dir = env("XDG_STATE_HOME")
+        .filter(|state_dir| !state_dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| env("HOME").filter(|home| !home.is_empty()).map(|home| PathBuf::from(home).join(".local/state")))?;
+    Some(state_dir.join(OUTBOX_FILE_NAME))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chat_client::{retry::RetryPolicy, ChatClientOptions};
+    use crate::entities::server::Server;
+    use std::time::Duration;
+    use crate::web_server::routes;
+
+    fn temp_outbox_path() -> PathBuf {
+        std::env::temp_dir().join(format!("rust-live-chat-outbox-{}", uuid::Uuid::new_v4())).join("outbox.json")
+    }
+
+    #[test]
+    fn test_outbox_queue_persists_messages() {
+        let path = temp_outbox_path();
+        let mut outbox = Outbox::load(&path).unwrap();
+        assert_eq!(outbox.pending_count("http://chat"), 0);
+        outbox.queue("http://chat", "general", "alice", "first");
+        outbox.queue("http://chat", "random", "alice", "second");
+        outbox.queue("http://chat", "general", "alice", "third");
+
+        let outbox = Outbox::load(&path).unwrap();
+        assert_eq!(outbox.pending_count("http://chat"), 3);
+        assert_eq!(outbox.pending_count("http://other"), 0);
+        let contents: Vec<&str> = outbox.pending("http://chat", "general").map(|message| message.content.as_str()).collect();
+        assert_eq!(contents, vec!["first", "third"]);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            assert_eq!(fs::metadata(&path).unwrap().permissions().mode() & 0o777, 0o600);
+        }
+        fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn test_outbox_queue_from_concurrent_clients() {
+        let path = temp_outbox_path();
+        let clients: Vec<_> = (0..4).map(|client| {
+            let path = path.clone();
+            std::thread::spawn(move || {
+                let mut outbox = Outbox::load(&path).unwrap();
+                for message in 0..10 {
+                    outbox.queue("http://chat", "general", "alice", &format!("{} {}", client, message));
+                }
+            })
+        }).collect();
+        for client in clients {
+            client.join().unwrap();
+        }
+        assert_eq!(Outbox::load(&path).unwrap().pending_count("http://chat"), 40);
+        fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn test_outbox_load_invalid() {
+        let path = temp_outbox_path();
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(&path, "not json").unwrap();
+        let mut outbox = Outbox::load(&path).unwrap();
+        assert_eq!(outbox.pending_count("http://chat"), 0);
+        let corrupt_files: Vec<_> = fs::read_dir(path.parent().unwrap()).unwrap()
+            .map(|entry| entry.unwrap().path())
+            .filter(|entry_path| entry_path.to_string_lossy().contains("outbox.json.corrupt-"))
+            .collect();
+        assert_eq!(corrupt_files.len(), 1);
+        assert_eq!(fs::read_to_string(&corrupt_files[0]).unwrap(), "not json");
+
+        outbox.queue("http://chat", "general", "alice", "hello");
+        assert_eq!(Outbox::load(&path).unwrap().pending_count("http://chat"), 1);
+        fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_outbox_deliver_in_order() {
+        let server = Server::new_shared();
+        server.write().unwrap().register_user("test_user").unwrap();
+        server.write().unwrap().create_room("test_room", "test_user").unwrap();
+        let chat_client = ChatClient::new(&routes::serve_ephemeral(server.clone())).unwrap();
+        let server_endpoint = chat_client.server_endpoint();
+
+        let mut outbox = Outbox::in_memory();
+        outbox.queue(server_endpoint, "test_room", "test_user", "first");
+        outbox.queue(server_endpoint, "missing_room", "test_user", "rejected");
+        outbox.queue(server_endpoint, "test_room", "test_user", "second");
+        outbox.queue("http://127.0.0.1:1", "test_room", "test_user", "elsewhere");
+
+        let report = outbox.deliver(&chat_client).await;
+        assert_eq!(report.delivered, 2);
+        assert_eq!(report.rejected.len(), 1);
+        assert_eq!(report.rejected[0].1.error_id, error_ids::ROOM_NOT_FOUND);
+        assert!(report.unreachable.is_none());
+        assert_eq!(outbox.pending_count(server_endpoint), 0);
+        assert_eq!(outbox.pending_count("http://127.0.0.1:1"), 1);
+
+        let messages = server.read().unwrap().get_room_messages("test_room").unwrap();
+        let contents: Vec<&str> = messages.iter().map(|message| message.content.as_str()).collect();
+        assert_eq!(contents, vec!["first", "second"]);
+    }
+
+    #[tokio::test]
+    async fn test_outbox_deliver_keeps_messages_while_unreachable() {
+        let mut outbox = Outbox::in_memory();
+        outbox.queue("http://127.0.0.1:1/", "test_room", "test_user", "first");
+        outbox.queue("http://127.0.0.1:1/", "test_room", "test_user", "second");
+
+        let chat_client = ChatClient::with_options("http://127.0.0.1:1", ChatClientOptions {
+            retry_policy: RetryPolicy { max_attempts: 2, initial_backoff: Duration::from_millis(1), max_backoff: Duration::from_millis(1) },
+            ..ChatClientOptions::default()
+        }).unwrap();
+        let report = outbox.deliver(&chat_client).await;
+        assert_eq!(report.delivered, 0);
+        assert_eq!(report.unreachable.unwrap().error_id, error_ids::CLIENT_FETCH_API);
+        let contents: Vec<&str> = outbox.pending("http://127.0.0.1:1/", "test_room").map(|message| message.content.as_str()).collect();
+        assert_eq!(contents, vec!["first", "second"]);
+    }
+
+    #[tokio::test]
+    async fn test_outbox_deliver_keeps_messages_on_server_failures() {
+        let failures = [
+            (502, "Bad Gateway"),
+            (500, r#"{"error_id":"ERR__INTERNAL","error_message":"Internal error"}"#),
+            (201, "<html></html>")
+        ];
+        for (status, body) in failures {
+            let mut server = mockito::Server::new_async().await;
+            let mock = server.mock("POST", "/v1/rooms/test_room/messages")
+                .with_status(status)
+                .with_body(body)
+                .create_async().await;
+            let chat_client = ChatClient::with_options(&server.url(), ChatClientOptions {
+                retry_policy: RetryPolicy { max_attempts: 1, initial_backoff: Duration::from_millis(1), max_backoff: Duration::from_millis(1) },
+                ..ChatClientOptions::default()
+            }).unwrap();
+
+            let mut outbox = Outbox::in_memory();
+            outbox.queue(chat_client.server_endpoint(), "test_room", "test_user", "hello");
+            let report = outbox.deliver(&chat_client).await;
+            assert!(report.unreachable.is_some(), "status {}", status);
+            assert!(report.rejected.is_empty(), "status {}", status);
+            assert_eq!(outbox.pending_count(chat_client.server_endpoint()), 1, "status {}", status);
+            mock.assert();
+        }
+    }
+
+    #[tokio::test]
+    async fn test_outbox_deliver_again_posts_once() {
+        let server = Server::new_shared();
+        server.write().unwrap().register_user("test_user").unwrap();
+        server.write().unwrap().create_room("test_room", "test_user").unwrap();
+        let chat_client = ChatClient::new(&routes::serve_ephemeral(server.clone())).unwrap();
+        let server_endpoint = chat_client.server_endpoint();
+
+        let mut outbox = Outbox::in_memory();
+        outbox.queue(server_endpoint, "test_room", "test_user", "hello");
+        let message = outbox.messages[0].clone();
+        outbox.deliver(&chat_client).await;
+        // As if the client stopped after the server received the message but before the outbox was saved.
+        outbox.messages.push(message);
+        outbox.deliver(&chat_client).await;
+
+        assert_eq!(server.read().unwrap().get_room_messages("test_room").unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_default_outbox_path() {
+        let env = |vars: &'static [(&'static str, &'static str)]| move |key: &str| vars.iter().find(|(name, _)| *name == key).map(|(_, value)| value.to_string());
+
+        assert_eq!(default_outbox_path(env(&[("XDG_STATE_HOME", "/xdg"), ("HOME", "/home/alice")])), Some(PathBuf::from("/xdg/rust-live-chat/outbox.json")));
+        assert_eq!(default_outbox_path(env(&[("HOME", "/home/alice")])), Some(PathBuf::from("/home/alice/.local/state/rust-live-chat/outbox.json")));
+        assert_eq!(default_outbox_path(env(&[])), None);
+    }
+}
@@ -0,0 +1,312 @@
+use crate::config;
+use chrono::format::{Item, StrftimeItems};
+use chrono::{DateTime, FixedOffset, Local};
+use chrono_tz::Tz;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::io::{self, Write};
+use std::time::SystemTime;
+use std::{fs, path::{Path, PathBuf}};
+
+/// Path of the profiles file relative to the user configuration directory.
+const PROFILES_FILE_NAME: &str = "rust-live-chat/profiles.toml";
+
+/// Named client settings, remembering the server and identity between two launches of the client.
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Profile {
+    /// The host or URL of the server, e.g. `https://chat.example.com`, overridden by `--host`
+    pub server: Option<String>,
+    /// The user signed in without being asked, remembered on the first sign in
+    pub username: Option<String>,
+    /// The token sent by the client, see [`ChatClientOptions::auth_token`](crate::chat_client::ChatClientOptions::auth_token)
+    pub auth_token: Option<String>,
+    /// The room entered when no room was entered with this profile yet
+    pub default_room: Option<String>,
+    /// The room last entered, entered again on the next launch
+    pub last_room: Option<String>,
+    pub display: DisplayPreferences,
+}
+
+impl Profile {
+    /// The room to enter on launch, instead of asking for one.
+    pub fn room_to_enter(&self) -> Option<&str> {
+        self.last_room.as_deref().or(self.default_room.as_deref())
+    }
+}
+
+/// How the messages of a room are displayed.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DisplayPreferences {
+    pub show_timestamps: bool,
+    /// Format of the message timestamps, in the strftime syntax
+    pub timestamp_format: String,
+    /// Timezone of the message timestamps: `local`, `UTC`, an IANA name such as `Europe/Paris` or a fixed offset such as `+02:00`
+    pub timezone: DisplayTimezone,
+    /// Whether the messages of the last day show how long ago they were posted, e.g. `5 min ago`, instead of their timestamp
+    pub relative_times: bool,
+    /// Whether the messages of each day are preceded by a line with the date
+    pub day_separators: bool,
+}
+
+impl Default for DisplayPreferences {
+    fn default() -> Self {
+        DisplayPreferences {
+            show_timestamps: true,
+            timestamp_format: "%Y-%m-%d %H:%M:%S".to_string(),
+            timezone: DisplayTimezone::Local,
+            relative_times: true,
+            day_separators: true,
+        }
+    }
+}
+
+/// Timezone the timestamps are displayed in, written `local`, `UTC`, an IANA name such as `Europe/Paris`
+/// or a fixed offset such as `+02:00` in the profiles file.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub enum DisplayTimezone {
+    /// The timezone of the system the client runs on
+    Local,
+    /// A timezone of the IANA database, whose offset follows the daylight saving time
+    Named(Tz),
+    Fixed(FixedOffset),
+}
+
+impl DisplayTimezone {
+    pub fn date_time(&self, timestamp: SystemTime) -> DateTime<FixedOffset> {
+        match self {
+            DisplayTimezone::Local => DateTime::<Local>::from(timestamp).fixed_offset(),
+            DisplayTimezone::Named(timezone) => DateTime::<chrono::Utc>::from(timestamp).with_timezone(timezone).fixed_offset(),
+            DisplayTimezone::Fixed(offset) => DateTime::<chrono::Utc>::from(timestamp).with_timezone(offset),
+        }
+    }
+}
+
+impl TryFrom<String> for DisplayTimezone {
+    type Error = String;
+
+    fn try_from(timezone: String) -> Result<Self, Self::Error> {
+        match timezone.as_str() {
+            "local" => Ok(DisplayTimezone::Local),
+            "UTC" | "Z" => Ok(DisplayTimezone::Fixed(FixedOffset::east_opt(0).unwrap())),
+            name => name.parse().map(DisplayTimezone::Named)
+                .or_else(|_| name.parse().map(DisplayTimezone::Fixed))
+                .map_err(|_| format!("invalid timezone {}, expected local, UTC, a name such as Europe/Paris or an offset such as +02:00", timezone)),
+        }
+    }
+}
+
+impl From<DisplayTimezone> for String {
+    fn from(timezone: DisplayTimezone) -> Self {
+        match timezone {
+            DisplayTimezone::Local => "local".to_string(),
+            DisplayTimezone::Named(timezone) => timezone.name().to_string(),
+            DisplayTimezone::Fixed(offset) if offset.local_minus_utc() == 0 => "UTC".to_string(),
+            DisplayTimezone::Fixed(offset) => offset.to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ProfilesFile {
+    pub profiles: BTreeMap<String, Profile>,
+}
+
+impl ProfilesFile {
+    /// Reads the profiles file, a missing file having no profiles yet.
+    pub fn load(path: &Path) -> Result<ProfilesFile, String> {
+        let content = match fs::read_to_string(path) {
+            Ok(content) => content,
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => return Ok(ProfilesFile::default()),
+            Err(error) => return Err(format!("Could not read profiles file {}: {}", path.display(), error)),
+        };
+        let profiles_file: ProfilesFile = toml::from_str(&content)
+            .map_err(|error| format!("Invalid profiles file {}: {}", path.display(), error.message()))?;
+        for (name, profile) in &profiles_file.profiles {
+            if let Some(server) = &profile.server {
+                config::host_is_valid(server).map_err(|reason| format!("Invalid server of profile {} in {}: {}", name, path.display(), reason))?;
+            }
+            if StrftimeItems::new(&profile.display.timestamp_format).any(|item| item == Item::Error) {
+                return Err(format!("Invalid timestamp format of profile {} in {}: {}", name, path.display(), profile.display.timestamp_format));
+            }
+        }
+        Ok(profiles_file)
+    }
+
+    /// Writes the profiles file, readable by its owner only since it may store auth tokens.
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        let write_error = |error: std::io::Error| format!("Could not write profiles file {}: {}", path.display(), error);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).map_err(write_error)?;
+        }
+        let content = toml::to_string_pretty(self).expect("profiles are serializable");
+        write_private_file(path, content.as_bytes()).map_err(write_error)
+    }
+}
+
+/// Writes a file readable by its owner only, to a temporary file renamed over it once synced,
+/// so that the file is never readable by others nor left half written.
+pub(super) fn write_private_file(path: &Path, content: &[u8]) -> io::Result<()> {
+    let mut temp_path = path.as_os_str().to_owned();
+    temp_path.push(format!(".{}.tmp", std::process::id()));
+    let temp_path = PathBuf::from(temp_path);
+    let _ = fs::remove_file(&temp_path);
+
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    let written = options.open(&temp_path).and_then(|mut file| {
+        file.write_all(content)?;
+        file.sync_all()
+    });
+    match written.and_then(|()| fs::rename(&temp_path, path)) {
+        Ok(()) => Ok(()),
+        Err(error) => {
+            let _ = fs::remove_file(&temp_path);
+            Err(error)
+        }
+    }
+}
+
+/// Default path of the profiles file, in the user configuration directory, e.g. `~/.config/rust-live-chat/profiles.toml`.
+pub fn default_profiles_path(env: impl Fn(&str) -> Option<String>) -> Option<PathBuf> {
+    let config_dir = env("XDG_CONFIG_HOME")
+        .filter(|config_dir| !config_dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| env("HOME").filter(|home| !home.is_empty()).map(|home| PathBuf::from(home).join(".config")))?;
+    Some(config_dir.join(PROFILES_FILE_NAME))
+}
+
+/// The profile selected with `--profile`, created on first use and saved as the client remembers the user and room.
+#[derive(Debug, Clone)]
+pub struct ActiveProfile {
+    pub name: String,
+    pub path: PathBuf,
+    pub profile: Profile,
+}
+
+impl ActiveProfile {
+    pub fn load(path: &Path, name: &str) -> Result<ActiveProfile, String> {
+        let profile = ProfilesFile::load(path)?.profiles.remove(name).unwrap_or_default();
+        Ok(ActiveProfile { name: name.to_string(), path: path.to_path_buf(), profile })
+    }
+
+    /// Saves this profile, leaving the other profiles of the file as they are now.
+    pub fn save(&self) -> Result<(), String> {
+        let mut profiles_file = ProfilesFile::load(&self.path)?;
+        profiles_file.profiles.insert(self.name.clone(), self.profile.clone());
+        profiles_file.save(&self.path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_profiles_path() -> PathBuf {
+        std::env::temp_dir().join(format!("rust-live-chat-profiles-{}", uuid::Uuid::new_v4())).join("profiles.toml")
+    }
+
+    #[test]
+    fn test_profiles_file_load() {
+        let path = temp_profiles_path();
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(&path, r#"
+            [profiles.work]
+            server = "https://chat.example.com"
+            username = "alice"
+            default_room = "general"
+
+            [profiles.work.display]
+            show_timestamps = false
+        "#).unwrap();
+
+        let profiles_file = ProfilesFile::load(&path).unwrap();
+        let profile = &profiles_file.profiles["work"];
+        assert_eq!(profile.server.as_deref(), Some("https://chat.example.com"));
+        assert_eq!(profile.username.as_deref(), Some("alice"));
+        assert_eq!(profile.room_to_enter(), Some("general"));
+        assert!(!profile.display.show_timestamps);
+        assert_eq!(profile.display.timestamp_format, DisplayPreferences::default().timestamp_format);
+        fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn test_profiles_file_load_missing_file() {
+        assert_eq!(ProfilesFile::load(&temp_profiles_path()).unwrap(), ProfilesFile::default());
+    }
+
+    #[test]
+    fn test_profiles_file_load_invalid() {
+        let path = temp_profiles_path();
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+
+        fs::write(&path, "[profiles.work]\nservr = \"chat.example.com\"\n").unwrap();
+        assert!(ProfilesFile::load(&path).unwrap_err().starts_with("Invalid profiles file"));
+        fs::write(&path, "[profiles.work]\nserver = \"not a host\"\n").unwrap();
+        assert!(ProfilesFile::load(&path).unwrap_err().starts_with("Invalid server of profile work"));
+        fs::write(&path, "[profiles.work.display]\ntimestamp_format = \"%Q\"\n").unwrap();
+        assert!(ProfilesFile::load(&path).unwrap_err().starts_with("Invalid timestamp format of profile work"));
+        fs::write(&path, "[profiles.work.display]\ntimezone = \"Mars/Olympus\"\n").unwrap();
+        assert!(ProfilesFile::load(&path).unwrap_err().contains("invalid timezone Mars/Olympus"));
+        fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn test_display_timezone() {
+        let timezone = |timezone: &str| DisplayTimezone::try_from(timezone.to_string());
+        let timestamp = SystemTime::UNIX_EPOCH + std::time::Duration::from_secs(1700000000);
+
+        assert_eq!(timezone("local"), Ok(DisplayTimezone::Local));
+        assert_eq!(timezone("UTC").unwrap().date_time(timestamp).to_rfc3339(), "2023-11-14T22:13:20+00:00");
+        assert_eq!(timezone("+05:30").unwrap().date_time(timestamp).to_rfc3339(), "2023-11-15T03:43:20+05:30");
+        assert_eq!(timezone("-0800").unwrap().date_time(timestamp).to_rfc3339(), "2023-11-14T14:13:20-08:00");
+        assert_eq!(timezone("Europe/Paris").unwrap().date_time(timestamp).to_rfc3339(), "2023-11-14T23:13:20+01:00");
+        assert_eq!(timezone("Europe/Paris").unwrap().date_time(SystemTime::UNIX_EPOCH + std::time::Duration::from_secs(1690000000)).to_rfc3339(), "2023-07-22T06:26:40+02:00");
+        assert!(timezone("Mars/Olympus").is_err());
+        for name in ["local", "UTC", "Europe/Paris", "America/Los_Angeles", "+05:30", "-08:00"] {
+            assert_eq!(String::from(timezone(name).unwrap()), name);
+        }
+    }
+
+    #[test]
+    fn test_active_profile_save_keeps_other_profiles() {
+        let path = temp_profiles_path();
+        let mut home = ActiveProfile::load(&path, "home").unwrap();
+        assert_eq!(home.profile, Profile::default());
+        home.profile.username = Some("bob".to_string());
+        home.save().unwrap();
+
+        let mut work = ActiveProfile::load(&path, "work").unwrap();
+        work.profile.username = Some("alice".to_string());
+        work.profile.last_room = Some("standup".to_string());
+        work.save().unwrap();
+
+        let profiles_file = ProfilesFile::load(&path).unwrap();
+        assert_eq!(profiles_file.profiles["home"].username.as_deref(), Some("bob"));
+        assert_eq!(profiles_file.profiles["work"].room_to_enter(), Some("standup"));
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            assert_eq!(fs::metadata(&path).unwrap().permissions().mode() & 0o777, 0o600);
+        }
+        assert_eq!(fs::read_dir(path.parent().unwrap()).unwrap().count(), 1, "no temporary file is left");
+        fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn test_default_profiles_path() {
+        let env = |vars: &'static [(&'static str, &'static str)]| move |key: &str| vars.iter().find(|(name, _)| *name == key).map(|(_, value)| value.to_string());
+
+        assert_eq!(default_profiles_path(env(&[("XDG_CONFIG_HOME", "/xdg"), ("HOME", "/home/alice")])), Some(PathBuf::from("/xdg/rust-live-chat/profiles.toml")));
+        assert_eq!(default_profiles_path(env(&[("HOME", "/home/alice")])), Some(PathBuf::from("/home/alice/.config/rust-live-chat/profiles.toml")));
+        assert_eq!(default_profiles_path(env(&[])), None);
+    }
+}
@@ -0,0 +1,567 @@
+use crate::web_server::logging::LogFormat;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::{fmt, fs, path::{Path, PathBuf}};
+use std::net::{IpAddr, SocketAddr, ToSocketAddrs};
+
+/// Prefix of the environment variables overriding the configuration file, e.g. `CHAT_PORT=3000`.
+pub const ENV_PREFIX: &str = "CHAT_";
+
+const HOSTNAME_REGEX: &str = r"^[A-Za-z0-9]([A-Za-z0-9-]{0,61}[A-Za-z0-9])?(\.[A-Za-z0-9]([A-Za-z0-9-]{0,61}[A-Za-z0-9])?)*\.?$";
+const HOSTNAME_MAX_LENGTH: usize = 253;
+const DOCKER_HOST: &str = "0.0.0.0";
+
+/// Effective configuration, layered from lowest to highest precedence:
+/// defaults, TOML configuration file, environment variables and command line flags.
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub server: ServerConfig,
+    pub storage: StorageConfig,
+    pub limits: LimitsConfig,
+    pub logging: LoggingConfig,
+    pub tls: TlsConfig,
+    pub cors: CorsConfig,
+    pub features: FeaturesConfig,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ServerConfig {
+    /// The host where server will run (if server) or the host to connect to (if client):
+    /// an IPv4 or IPv6 address, a hostname or, for the client only, the URL of the server
+    pub host: String,
+    /// The port where server will run (if server) or the port to connect to (if client)
+    pub port: u16,
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        ServerConfig {
+            host: "127.0.0.1".to_string(),
+            port: 3030,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum StorageBackend {
+    /// Everything is kept in memory and lost when the server stops
+    Memory
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct StorageConfig {
+    pub backend: StorageBackend,
+}
+
+impl Default for StorageConfig {
+    fn default() -> Self {
+        StorageConfig { backend: StorageBackend::Memory }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LimitsConfig {
+    /// Longest message accepted in a room, in characters
+    pub max_message_length: usize,
+    /// How long in-flight requests and pending webhook deliveries are each given to complete on shutdown
+    pub shutdown_timeout_seconds: u64,
+    /// Attempts made to deliver a webhook event before giving up
+    pub webhook_max_attempts: u32,
+    /// Timeout of each webhook delivery attempt
+    pub webhook_request_timeout_seconds: u64,
+}
+
+impl Default for LimitsConfig {
+    fn default() -> Self {
+        LimitsConfig {
+            max_message_length: 4096,
+            shutdown_timeout_seconds: 10,
+            webhook_max_attempts: 5,
+            webhook_request_timeout_seconds: 10,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LoggingConfig {
+    pub format: LogFormat,
+    /// Filter of the logged events, in the RUST_LOG syntax, e.g. `info` or `debug,hyper=info`.
+    /// By default, the events of warp's own trace filter are left out since every request is already logged once completed.
+    pub level: String,
+}
+
+impl Default for LoggingConfig {
+    fn default() -> Self {
+        LoggingConfig {
+            format: LogFormat::Text,
+            level: "info,warp::filters::trace=off".to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TlsConfig {
+    /// Serve HTTPS (if server) or connect over HTTPS (if client)
+    pub enabled: bool,
+    /// PEM certificate chain served by the server
+    pub cert_path: Option<PathBuf>,
+    /// PEM private key of the served certificate
+    pub key_path: Option<PathBuf>,
+    /// PEM certificate of an additional authority trusted by the client, e.g. for self-signed internal certificates
+    pub ca_cert_path: Option<PathBuf>,
+}
+
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CorsConfig {
+    /// Origins allowed to call the API from a browser, e.g. `https://chat.example.com`, or `*` for any.
+    /// None by default, the bundled browser client being served from the same origin as the API
+    pub allowed_origins: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct FeaturesConfig {
+    /// Outgoing and incoming room webhooks
+    pub webhooks: bool,
+    /// Prometheus metrics at `GET /metrics`
+    pub metrics: bool,
+    /// Browser chat client at `GET /`
+    pub web_client: bool,
+}
+
+impl Default for FeaturesConfig {
+    fn default() -> Self {
+        FeaturesConfig {
+            webhooks: true,
+            metrics: true,
+            web_client: true,
+        }
+    }
+}
+
+/// Values given on the command line, overriding every other layer when set.
+#[derive(Debug, Clone, Default)]
+pub struct CliOverrides {
+    pub host: Option<String>,
+    pub port: Option<u16>,
+    pub log_format: Option<LogFormat>,
+    pub tls: bool,
+    pub tls_cert_path: Option<PathBuf>,
+    pub tls_key_path: Option<PathBuf>,
+    pub tls_ca_cert_path: Option<PathBuf>,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum ConfigError {
+    FileUnreadable { path: PathBuf, reason: String },
+    FileInvalid { path: PathBuf, reason: String },
+    InvalidValue { key: String, value: String, reason: String },
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::FileUnreadable { path, reason } => write!(f, "Cannot read configuration file {}: {}", path.display(), reason),
+            ConfigError::FileInvalid { path, reason } => write!(f, "Invalid configuration file {}: {}", path.display(), reason),
+            ConfigError::InvalidValue { key, value, reason } => write!(f, "Invalid value {:?} for {}: {}", value, key, reason),
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+/// Accepts an IPv4 or IPv6 address, brackets allowed, a hostname or an `http(s)://` URL.
+pub fn host_is_valid(s: &str) -> Result<String, String> {
+    if is_url(s) {
+        let url = reqwest::Url::parse(s).map_err(|error| format!("Invalid URL: {}", error))?;
+        return match url.host_str() {
+            Some(_) => Ok(s.to_string()),
+            None => Err("Invalid URL: missing host".to_string())
+        };
+    }
+    if parse_ip_address(s).is_some() {
+        return Ok(s.to_string());
+    }
+    // Rules out the malformed IPv4 addresses, e.g. 256.0.0.1, which are valid hostnames otherwise
+    if s.chars().all(|c| c.is_ascii_digit() || c == '.') {
+        return Err("Invalid IPv4 address".to_string());
+    }
+    if s.len() <= HOSTNAME_MAX_LENGTH && Regex::new(HOSTNAME_REGEX).unwrap().is_match(s) {
+        Ok(s.to_string())
+    } else {
+        Err("Invalid host: expected an IP address, a hostname or an http(s):// URL".to_string())
+    }
+}
+
+/// Accepts `*`, or an origin as sent by browsers: a scheme, a host and an optional port, without path nor trailing slash.
+fn origin_is_valid(origin: &str) -> Result<(), String> {
+    if origin == "*" {
+        return Ok(());
+    }
+    let url = reqwest::Url::parse(origin).map_err(|error| format!("Invalid origin: {}", error))?;
+    if !matches!(url.scheme(), "http" | "https") || url.origin().ascii_serialization() != origin {
+        return Err("Invalid origin: expected scheme://host[:port], e.g. https://chat.example.com".to_string());
+    }
+    Ok(())
+}
+
+fn is_url(host: &str) -> bool {
+    host.starts_with("http://") || host.starts_with("https://")
+}
+
+/// Parses an IP address, the IPv6 ones being possibly enclosed in brackets as in URLs.
+fn parse_ip_address(host: &str) -> Option<IpAddr> {
+    let unbracketed = host.strip_prefix('[').and_then(|host| host.strip_suffix(']'));
+    match unbracketed {
+        Some(host) => host.parse().ok().filter(IpAddr::is_ipv6),
+        None => host.parse().ok()
+    }
+}
+
+impl ServerConfig {
+    fn invalid_host(&self, reason: String) -> ConfigError {
+        ConfigError::InvalidValue {
+            key: "server.host".to_string(),
+            value: self.host.clone(),
+            reason,
+        }
+    }
+
+    /// Resolves the socket address the server binds to, e.g. `[::]:3030` to listen on every IPv4 and IPv6 interface.
+    pub fn bind_address(&self) -> Result<SocketAddr, ConfigError> {
+        if is_url(&self.host) {
+            return Err(self.invalid_host("the server binds to an IP address or a hostname, not a URL".to_string()));
+        }
+        if let Some(ip_address) = parse_ip_address(&self.host) {
+            return Ok(SocketAddr::new(ip_address, self.port));
+        }
+        (self.host.as_str(), self.port).to_socket_addrs()
+            .map_err(|error| self.invalid_host(format!("could not resolve host: {}", error)))?
+            .next()
+            .ok_or_else(|| self.invalid_host("could not resolve host: no address found".to_string()))
+    }
+
+    /// Base URL of the server the client connects to, without trailing slash, over HTTPS if `https`.
+    /// A URL host is taken as is, with its own scheme and port if any, rather than the configured ones.
+    pub fn endpoint(&self, https: bool) -> Result<String, ConfigError> {
+        if is_url(&self.host) {
+            let url = reqwest::Url::parse(&self.host).map_err(|error| self.invalid_host(error.to_string()))?;
+            return Ok(url.as_str().trim_end_matches('/').to_string());
+        }
+        let scheme = if https { "https" } else { "http" };
+        match parse_ip_address(&self.host) {
+            Some(IpAddr::V6(ip_address)) => Ok(format!("{}://[{}]:{}", scheme, ip_address, self.port)),
+            Some(IpAddr::V4(ip_address)) => Ok(format!("{}://{}:{}", scheme, ip_address, self.port)),
+            None => Ok(format!("{}://{}:{}", scheme, self.host, self.port))
+        }
+    }
+}
+
+fn parse_env_value<T: std::str::FromStr>(key: &str, value: &str) -> Result<T, ConfigError>
+where
+    T::Err: fmt::Display,
+{
+    value.parse().map_err(|error: T::Err| ConfigError::InvalidValue {
+        key: key.to_string(),
+        value: value.to_string(),
+        reason: error.to_string(),
+    })
+}
+
+fn parse_env_enum<T: clap::ValueEnum>(key: &str, value: &str) -> Result<T, ConfigError> {
+    T::from_str(value, true).map_err(|reason| ConfigError::InvalidValue {
+        key: key.to_string(),
+        value: value.to_string(),
+        reason,
+    })
+}
+
+impl Config {
+    /// Builds the effective configuration from the optional configuration file,
+    /// the environment variables looked up with `env` and the command line overrides.
+    pub fn load(path: Option<&Path>, env: impl Fn(&str) -> Option<String>, cli_overrides: CliOverrides) -> Result<Config, ConfigError> {
+        let mut config = match path {
+            Some(path) => Config::from_file(path)?,
+            None => Config::default(),
+        };
+        config.apply_env(env)?;
+        config.apply_cli_overrides(cli_overrides);
+        config.validate()?;
+        Ok(config)
+    }
+
+    pub fn from_file(path: &Path) -> Result<Config, ConfigError> {
+        let content = fs::read_to_string(path).map_err(|error| ConfigError::FileUnreadable {
+            path: path.to_path_buf(),
+            reason: error.to_string(),
+        })?;
+        toml::from_str(&content).map_err(|error| ConfigError::FileInvalid {
+            path: path.to_path_buf(),
+            reason: error.message().to_string(),
+        })
+    }
+
+    fn apply_env(&mut self, env: impl Fn(&str) -> Option<String>) -> Result<(), ConfigError> {
+        let var = |name: &str| env(&format!("{}{}", ENV_PREFIX, name)).map(|value| (format!("{}{}", ENV_PREFIX, name), value));
+
+        // Kept for the existing Docker images, which expose the server on every interface
+        if env("ENVIRONMENT").as_deref() == Some("docker") {
+            self.server.host = DOCKER_HOST.to_string();
+        }
+
+        if let Some((_, value)) = var("HOST") {
+            self.server.host = value;
+        }
+        if let Some((key, value)) = var("PORT") {
+            self.server.port = parse_env_value(&key, &value)?;
+        }
+        if let Some((key, value)) = var("STORAGE_BACKEND") {
+            self.storage.backend = parse_env_enum(&key, &value)?;
+        }
+        if let Some((key, value)) = var("MAX_MESSAGE_LENGTH") {
+            self.limits.max_message_length = parse_env_value(&key, &value)?;
+        }
+        if let Some((key, value)) = var("SHUTDOWN_TIMEOUT_SECONDS") {
+            self.limits.shutdown_timeout_seconds = parse_env_value(&key, &value)?;
+        }
+        if let Some((key, value)) = var("WEBHOOK_MAX_ATTEMPTS") {
+            self.limits.webhook_max_attempts = parse_env_value(&key, &value)?;
+        }
+        if let Some((key, value)) = var("WEBHOOK_REQUEST_TIMEOUT_SECONDS") {
+            self.limits.webhook_request_timeout_seconds = parse_env_value(&key, &value)?;
+        }
+        if let Some((key, value)) = var("LOG_FORMAT") {
+            self.logging.format = parse_env_enum(&key, &value)?;
+        }
+        if let Some(value) = var("LOG_LEVEL").map(|(_, value)| value).or_else(|| env("RUST_LOG")) {
+            self.logging.level = value;
+        }
+        if let Some((key, value)) = var("TLS_ENABLED") {
+            self.tls.enabled = parse_env_value(&key, &value)?;
+        }
+        if let Some((_, value)) = var("TLS_CERT_PATH") {
+            self.tls.cert_path = Some(PathBuf::from(value));
+        }
+        if let Some((_, value)) = var("TLS_KEY_PATH") {
+            self.tls.key_path = Some(PathBuf::from(value));
+        }
+        if let Some((_, value)) = var("TLS_CA_CERT_PATH") {
+            self.tls.ca_cert_path = Some(PathBuf::from(value));
+        }
+        if let Some((_, value)) = var("CORS_ALLOWED_ORIGINS") {
+            self.cors.allowed_origins = value.split(',').map(str::trim).filter(|origin| !origin.is_empty()).map(String::from).collect();
+        }
+        if let Some((key, value)) = var("FEATURE_WEBHOOKS") {
+            self.features.webhooks = parse_env_value(&key, &value)?;
+        }
+        if let Some((key, value)) = var("FEATURE_METRICS") {
+            self.features.metrics = parse_env_value(&key, &value)?;
+        }
+        if let Some((key, value)) = var("FEATURE_WEB_CLIENT") {
+            self.features.web_client = parse_env_value(&key, &value)?;
+        }
+        Ok(())
+    }
+
+    fn apply_cli_overrides(&mut self, cli_overrides: CliOverrides) {
+        if let Some(host) = cli_overrides.host {
+            self.server.host = host;
+        }
+        if let Some(port) = cli_overrides.port {
+            self.server.port = port;
+        }
+        if let Some(log_format) = cli_overrides.log_format {
+            self.logging.format = log_format;
+        }
+        if cli_overrides.tls {
+            self.tls.enabled = true;
+        }
+        if let Some(cert_path) = cli_overrides.tls_cert_path {
+            self.tls.cert_path = Some(cert_path);
+        }
+        if let Some(key_path) = cli_overrides.tls_key_path {
+            self.tls.key_path = Some(key_path);
+        }
+        if let Some(ca_cert_path) = cli_overrides.tls_ca_cert_path {
+            self.tls.ca_cert_path = Some(ca_cert_path);
+        }
+    }
+
+    fn validate(&self) -> Result<(), ConfigError> {
+        let invalid = |key: &str, value: String, reason: &str| ConfigError::InvalidValue {
+            key: key.to_string(),
+            value,
+            reason: reason.to_string(),
+        };
+
+        host_is_valid(&self.server.host).map_err(|reason| invalid("server.host", self.server.host.clone(), &reason))?;
+        if self.limits.max_message_length == 0 {
+            return Err(invalid("limits.max_message_length", "0".to_string(), "must be greater than 0"));
+        }
+        if self.limits.webhook_max_attempts == 0 {
+            return Err(invalid("limits.webhook_max_attempts", "0".to_string(), "must be greater than 0"));
+        }
+        if self.limits.webhook_request_timeout_seconds == 0 {
+            return Err(invalid("limits.webhook_request_timeout_seconds", "0".to_string(), "must be greater than 0"));
+        }
+        tracing_subscriber::EnvFilter::try_new(&self.logging.level)
+            .map_err(|error| invalid("logging.level", self.logging.level.clone(), &error.to_string()))?;
+        for origin in &self.cors.allowed_origins {
+            origin_is_valid(origin).map_err(|reason| invalid("cors.allowed_origins", origin.clone(), &reason))?;
+        }
+        if self.tls.cert_path.is_some() != self.tls.key_path.is_some() {
+            let (key, value) = match &self.tls.cert_path {
+                Some(cert_path) => ("tls.cert_path", cert_path),
+                None => ("tls.key_path", self.tls.key_path.as_ref().unwrap())
+            };
+            return Err(invalid(key, value.display().to_string(), "tls.cert_path and tls.key_path must be set together"));
+        }
+        Ok(())
+    }
+
+    /// Base URL of the server the client connects to.
+    pub fn server_endpoint(&self) -> Result<String, ConfigError> {
+        self.server.endpoint(self.tls.enabled)
+    }
+
+    pub fn to_toml(&self) -> String {
+        toml::to_string_pretty(self).expect("Configuration should serialize to TOML")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn env_from(vars: &[(&str, &str)]) -> impl Fn(&str) -> Option<String> {
+        let vars: HashMap<String, String> = vars.iter().map(|(key, value)| (key.to_string(), value.to_string())).collect();
+        move |key| vars.get(key).cloned()
+    }
+
+    fn write_config_file(content: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("rust-live-chat-{}.toml", uuid::Uuid::new_v4()));
+        fs::write(&path, content).unwrap();
+        path
+    }
+
+    #[test]
+    fn test_config_load_defaults() {
+        let config = Config::load(None, env_from(&[]), CliOverrides::default()).unwrap();
+        assert_eq!(config, Config::default());
+    }
+
+    #[test]
+    fn test_config_load_layers_precedence() {
+        let path = write_config_file("[server]\nhost = \"10.0.0.1\"\nport = 4000\n\n[limits]\nmax_message_length = 100\n");
+        let env = env_from(&[("CHAT_PORT", "5000"), ("CHAT_FEATURE_METRICS", "false")]);
+        let cli_overrides = CliOverrides { host: Some("127.0.0.2".to_string()), ..CliOverrides::default() };
+
+        let config = Config::load(Some(&path), env, cli_overrides).unwrap();
+        assert_eq!(config.server.host, "127.0.0.2");
+        assert_eq!(config.server.port, 5000);
+        assert_eq!(config.limits.max_message_length, 100);
+        assert!(!config.features.metrics);
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_config_load_docker_environment_does_not_override_cli() {
+        let env = env_from(&[("ENVIRONMENT", "docker")]);
+        assert_eq!(Config::load(None, &env, CliOverrides::default()).unwrap().server.host, "0.0.0.0");
+
+        let cli_overrides = CliOverrides { host: Some("127.0.0.1".to_string()), ..CliOverrides::default() };
+        assert_eq!(Config::load(None, &env, cli_overrides).unwrap().server.host, "127.0.0.1");
+    }
+
+    #[test]
+    fn test_config_load_invalid_env_value() {
+        let result = Config::load(None, env_from(&[("CHAT_PORT", "not-a-port")]), CliOverrides::default());
+        assert!(matches!(result, Err(ConfigError::InvalidValue { ref key, .. }) if key == "CHAT_PORT"));
+    }
+
+    #[test]
+    fn test_config_load_invalid_file() {
+        let path = write_config_file("[server]\nunknown_key = 1\n");
+        let result = Config::load(Some(&path), env_from(&[]), CliOverrides::default());
+        assert!(matches!(result, Err(ConfigError::FileInvalid { .. })));
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_config_load_invalid_value() {
+        let result = Config::load(None, env_from(&[("CHAT_MAX_MESSAGE_LENGTH", "0")]), CliOverrides::default());
+        assert_eq!(result.unwrap_err().to_string(), "Invalid value \"0\" for limits.max_message_length: must be greater than 0");
+    }
+
+    #[test]
+    fn test_config_load_tls() {
+        let env = env_from(&[("CHAT_TLS_CERT_PATH", "server.crt"), ("CHAT_TLS_KEY_PATH", "server.key")]);
+        let cli_overrides = CliOverrides { tls: true, tls_ca_cert_path: Some(PathBuf::from("ca.crt")), ..CliOverrides::default() };
+        let config = Config::load(None, env, cli_overrides).unwrap();
+        assert!(config.tls.enabled);
+        assert_eq!(config.tls.cert_path, Some(PathBuf::from("server.crt")));
+        assert_eq!(config.tls.ca_cert_path, Some(PathBuf::from("ca.crt")));
+        assert_eq!(config.server_endpoint().unwrap(), "https://127.0.0.1:3030");
+
+        let result = Config::load(None, env_from(&[("CHAT_TLS_CERT_PATH", "server.crt")]), CliOverrides::default());
+        assert!(matches!(result, Err(ConfigError::InvalidValue { ref key, .. }) if key == "tls.cert_path"));
+    }
+
+    #[test]
+    fn test_config_load_cors_allowed_origins() {
+        let env = env_from(&[("CHAT_CORS_ALLOWED_ORIGINS", "https://chat.example.com, http://localhost:8080")]);
+        let config = Config::load(None, env, CliOverrides::default()).unwrap();
+        assert_eq!(config.cors.allowed_origins, vec!["https://chat.example.com", "http://localhost:8080"]);
+
+        let result = Config::load(None, env_from(&[("CHAT_CORS_ALLOWED_ORIGINS", "https://chat.example.com/")]), CliOverrides::default());
+        assert!(matches!(result, Err(ConfigError::InvalidValue { ref key, .. }) if key == "cors.allowed_origins"));
+    }
+
+    #[test]
+    fn test_host_is_valid() {
+        for host in ["127.0.0.1", "::", "::1", "[::1]", "fe80::1", "localhost", "chat.internal", "http://chat.internal:8080", "https://[::1]/"] {
+            assert!(host_is_valid(host).is_ok(), "{}", host);
+        }
+        for host in ["256.0.0.1", "1.2.3", "[127.0.0.1]", "chat..internal", "-chat", "chat_internal", "ftp://chat.internal", "http://"] {
+            assert!(host_is_valid(host).is_err(), "{}", host);
+        }
+    }
+
+    #[test]
+    fn test_server_config_bind_address() {
+        let server_config = |host: &str| ServerConfig { host: host.to_string(), port: 3030 };
+        assert_eq!(server_config("0.0.0.0").bind_address().unwrap(), "0.0.0.0:3030".parse().unwrap());
+        assert_eq!(server_config("::").bind_address().unwrap(), "[::]:3030".parse().unwrap());
+        assert_eq!(server_config("[::1]").bind_address().unwrap(), "[::1]:3030".parse().unwrap());
+        assert!(server_config("localhost").bind_address().unwrap().ip().is_loopback());
+        assert!(matches!(server_config("http://localhost").bind_address(), Err(ConfigError::InvalidValue { .. })));
+    }
+
+    #[test]
+    fn test_server_config_endpoint() {
+        let server_config = |host: &str| ServerConfig { host: host.to_string(), port: 3030 };
+        assert_eq!(server_config("127.0.0.1").endpoint(false).unwrap(), "http://127.0.0.1:3030");
+        assert_eq!(server_config("::1").endpoint(false).unwrap(), "http://[::1]:3030");
+        assert_eq!(server_config("[::1]").endpoint(false).unwrap(), "http://[::1]:3030");
+        assert_eq!(server_config("chat.internal").endpoint(false).unwrap(), "http://chat.internal:3030");
+        assert_eq!(server_config("https://chat.example.com/").endpoint(false).unwrap(), "https://chat.example.com");
+        assert_eq!(server_config("http://chat.internal:8080").endpoint(false).unwrap(), "http://chat.internal:8080");
+        assert_eq!(server_config("chat.internal").endpoint(true).unwrap(), "https://chat.internal:3030");
+        assert_eq!(server_config("[::1]").endpoint(true).unwrap(), "https://[::1]:3030");
+    }
+
+    #[test]
+    fn test_config_to_toml_round_trip() {
+        let config = Config::default();
+        assert_eq!(toml::from_str::<Config>(&config.to_toml()).unwrap(), config);
+    }
+}
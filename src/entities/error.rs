@@ -0,0 +1,197 @@
+use std::fmt;
+use uuid::Uuid;
+
+/// Stable identifiers of the errors reported in `ErrorDetailsResponse`.
+/// Clients should match on these rather than on the human readable messages.
+pub mod error_ids {
+    pub const BAD_REQUEST: &str = "ERR__BAD_REQUEST";
+    pub const USER_NOT_FOUND: &str = "ERR__USER_NOT_FOUND";
+    pub const USER_ALREADY_EXISTS: &str = "ERR__USER_ALREADY_EXISTS";
+    pub const ROOM_NOT_FOUND: &str = "ERR__ROOM_NOT_FOUND";
+    pub const ROOM_ALREADY_EXISTS: &str = "ERR__ROOM_ALREADY_EXISTS";
+    pub const USER_ALREADY_IN_ROOM: &str = "ERR__USER_ALREADY_IN_ROOM";
+    pub const USER_NOT_IN_ROOM: &str = "ERR__USER_NOT_IN_ROOM";
+    pub const WEBHOOK_URL_INVALID: &str = "ERR__WEBHOOK_URL_INVALID";
+    pub const WEBHOOK_ALREADY_REGISTERED: &str = "ERR__WEBHOOK_ALREADY_REGISTERED";
+    pub const INCOMING_WEBHOOK_NOT_FOUND: &str = "ERR__INCOMING_WEBHOOK_NOT_FOUND";
+    pub const BOT_NAME_INVALID: &str = "ERR__BOT_NAME_INVALID";
+    pub const MESSAGE_TOO_LONG: &str = "ERR__MESSAGE_TOO_LONG";
+    pub const IDEMPOTENCY_KEY_REUSED: &str = "ERR__IDEMPOTENCY_KEY_REUSED";
+    pub const ROUTE_NOT_FOUND: &str = "ERR__ROUTE_NOT_FOUND";
+    pub const METHOD_NOT_ALLOWED: &str = "ERR__METHOD_NOT_ALLOWED";
+    pub const LENGTH_REQUIRED: &str = "ERR__LENGTH_REQUIRED";
+    pub const PAYLOAD_TOO_LARGE: &str = "ERR__PAYLOAD_TOO_LARGE";
+    pub const ORIGIN_NOT_ALLOWED: &str = "ERR__ORIGIN_NOT_ALLOWED";
+    pub const INTERNAL: &str = "ERR__INTERNAL";
+    pub const SERVER_UNAVAILABLE: &str = "ERR__SERVER_UNAVAILABLE";
+
+    /// Every error id answered by the server, as documented in the OpenAPI document.
+    pub const SERVER_ERROR_IDS: [&str; 20] = [
+        BAD_REQUEST,
+        USER_NOT_FOUND,
+        USER_ALREADY_EXISTS,
+        ROOM_NOT_FOUND,
+        ROOM_ALREADY_EXISTS,
+        USER_ALREADY_IN_ROOM,
+        USER_NOT_IN_ROOM,
+        WEBHOOK_URL_INVALID,
+        WEBHOOK_ALREADY_REGISTERED,
+        INCOMING_WEBHOOK_NOT_FOUND,
+        BOT_NAME_INVALID,
+        MESSAGE_TOO_LONG,
+        IDEMPOTENCY_KEY_REUSED,
+        ROUTE_NOT_FOUND,
+        METHOD_NOT_ALLOWED,
+        LENGTH_REQUIRED,
+        PAYLOAD_TOO_LARGE,
+        ORIGIN_NOT_ALLOWED,
+        INTERNAL,
+        SERVER_UNAVAILABLE
+    ];
+
+    // Raised by the client itself, never sent by the server
+    pub const CLIENT_FETCH_API: &str = "ERR__CLIENT_FETCH_API";
+    pub const CLIENT_USER_NOT_ADDED_TO_ROOM: &str = "ERR__USER_NOT_ADDED_TO_ROOM";
+    pub const CLIENT_DM_TO_SELF: &str = "ERR__DM_TO_SELF";
+    pub const CLIENT_INVALID_ENDPOINT: &str = "ERR__CLIENT_INVALID_ENDPOINT";
+    pub const CLIENT_INVALID_AUTH_TOKEN: &str = "ERR__CLIENT_INVALID_AUTH_TOKEN";
+    pub const CLIENT_INVALID_CA_CERTIFICATE: &str = "ERR__CLIENT_INVALID_CA_CERTIFICATE";
+    pub const CLIENT_BUILD_FAILED: &str = "ERR__CLIENT_BUILD_FAILED";
+    pub const CLIENT_MISSING_USER: &str = "ERR__CLIENT_MISSING_USER";
+    pub const CLIENT_OUTPUT_FAILED: &str = "ERR__CLIENT_OUTPUT_FAILED";
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ServerError {
+    BadRequest(String),
+    UserNotFound(String),
+    UserIdNotFound(Uuid),
+    UserAlreadyExists(String),
+    RoomNotFound(String),
+    RoomIdNotFound(Uuid),
+    RoomAlreadyExists(String),
+    UserAlreadyInRoom { username: String, room_name: String },
+    UserNotInRoom { username: String, room_name: String },
+    WebhookUrlInvalid(String),
+    WebhookAlreadyRegistered { url: String, room_name: String },
+    IncomingWebhookNotFound,
+    BotNameInvalid(String),
+    MessageTooLong(usize),
+    IdempotencyKeyReused(Uuid),
+    AuthorNotBot(String),
+    RouteNotFound,
+    MethodNotAllowed,
+    LengthRequired,
+    PayloadTooLarge,
+    OriginNotAllowed(String),
+    Internal(String),
+    Unavailable
+}
+
+impl ServerError {
+    pub fn error_id(&self) -> &'static str {
+        match self {
+            ServerError::BadRequest(_) => error_ids::BAD_REQUEST,
+            ServerError::UserNotFound(_) | ServerError::UserIdNotFound(_) => error_ids::USER_NOT_FOUND,
+            ServerError::UserAlreadyExists(_) => error_ids::USER_ALREADY_EXISTS,
+            ServerError::RoomNotFound(_) | ServerError::RoomIdNotFound(_) => error_ids::ROOM_NOT_FOUND,
+            ServerError::RoomAlreadyExists(_) => error_ids::ROOM_ALREADY_EXISTS,
+            ServerError::UserAlreadyInRoom { .. } => error_ids::USER_ALREADY_IN_ROOM,
+            ServerError::UserNotInRoom { .. } => error_ids::USER_NOT_IN_ROOM,
+            ServerError::WebhookUrlInvalid(_) => error_ids::WEBHOOK_URL_INVALID,
+            ServerError::WebhookAlreadyRegistered { .. } => error_ids::WEBHOOK_ALREADY_REGISTERED,
+            ServerError::IncomingWebhookNotFound => error_ids::INCOMING_WEBHOOK_NOT_FOUND,
+            ServerError::BotNameInvalid(_) => error_ids::BOT_NAME_INVALID,
+            ServerError::MessageTooLong(_) => error_ids::MESSAGE_TOO_LONG,
+            ServerError::IdempotencyKeyReused(_) => error_ids::IDEMPOTENCY_KEY_REUSED,
+            ServerError::AuthorNotBot(_) | ServerError::Internal(_) => error_ids::INTERNAL,
+            ServerError::RouteNotFound => error_ids::ROUTE_NOT_FOUND,
+            ServerError::MethodNotAllowed => error_ids::METHOD_NOT_ALLOWED,
+            ServerError::LengthRequired => error_ids::LENGTH_REQUIRED,
+            ServerError::PayloadTooLarge => error_ids::PAYLOAD_TOO_LARGE,
+            ServerError::OriginNotAllowed(_) => error_ids::ORIGIN_NOT_ALLOWED,
+            ServerError::Unavailable => error_ids::SERVER_UNAVAILABLE
+        }
+    }
+}
+
+impl fmt::Display for ServerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ServerError::BadRequest(reason) => write!(f, "{}", reason),
+            ServerError::UserNotFound(username) => write!(f, "User with username {} not found in server", username),
+            ServerError::UserIdNotFound(user_id) => write!(f, "User with id {} not found in server", user_id),
+            ServerError::UserAlreadyExists(username) => write!(f, "User with username {} already exists in server", username),
+            ServerError::RoomNotFound(room_name) => write!(f, "Room with name {} not found in server", room_name),
+            ServerError::RoomIdNotFound(room_id) => write!(f, "Room with id {} not found in server", room_id),
+            ServerError::RoomAlreadyExists(room_name) => write!(f, "Room with name {} already exists in server", room_name),
+            ServerError::UserAlreadyInRoom { username, room_name } => write!(f, "User with username {} is already in room with name {}", username, room_name),
+            ServerError::UserNotInRoom { username, room_name } => write!(f, "User with username {} is not in room with name {}", username, room_name),
+            ServerError::WebhookUrlInvalid(url) => write!(f, "Webhook URL {} is not a valid http(s) URL", url),
+            ServerError::WebhookAlreadyRegistered { url, room_name } => write!(f, "Webhook URL {} is already registered in room with name {}", url, room_name),
+            ServerError::IncomingWebhookNotFound => write!(f, "Incoming webhook not found in server"),
+            ServerError::BotNameInvalid(reason) => write!(f, "Invalid bot name: {}", reason),
+            ServerError::MessageTooLong(max_length) => write!(f, "Message cannot be longer than {} characters", max_length),
+            ServerError::IdempotencyKeyReused(client_message_id) => write!(f, "Client message id {} was already used for another message", client_message_id),
+            ServerError::AuthorNotBot(username) => write!(f, "Author with username {} is not a bot", username),
+            ServerError::RouteNotFound => write!(f, "No route matches the requested path"),
+            ServerError::MethodNotAllowed => write!(f, "The requested method is not allowed on this path"),
+            ServerError::LengthRequired => write!(f, "The request body must be sent with a Content-Length header"),
+            ServerError::PayloadTooLarge => write!(f, "The request body is too large"),
+            ServerError::OriginNotAllowed(reason) => write!(f, "Cross-origin request not allowed: {}", reason),
+            ServerError::Internal(reason) => write!(f, "Internal server error: {}", reason),
+            ServerError::Unavailable => write!(f, "The server is unavailable")
+        }
+    }
+}
+
+impl std::error::Error for ServerError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_server_error_id() {
+        assert_eq!(ServerError::RoomNotFound("room".to_string()).error_id(), "ERR__ROOM_NOT_FOUND");
+        assert_eq!(ServerError::UserAlreadyExists("user".to_string()).error_id(), "ERR__USER_ALREADY_EXISTS");
+    }
+
+    #[test]
+    fn test_server_error_ids_documented() {
+        let errors = [
+            ServerError::BadRequest(String::new()),
+            ServerError::UserNotFound(String::new()),
+            ServerError::UserIdNotFound(Uuid::nil()),
+            ServerError::UserAlreadyExists(String::new()),
+            ServerError::RoomNotFound(String::new()),
+            ServerError::RoomIdNotFound(Uuid::nil()),
+            ServerError::RoomAlreadyExists(String::new()),
+            ServerError::UserAlreadyInRoom { username: String::new(), room_name: String::new() },
+            ServerError::UserNotInRoom { username: String::new(), room_name: String::new() },
+            ServerError::WebhookUrlInvalid(String::new()),
+            ServerError::WebhookAlreadyRegistered { url: String::new(), room_name: String::new() },
+            ServerError::IncomingWebhookNotFound,
+            ServerError::BotNameInvalid(String::new()),
+            ServerError::MessageTooLong(0),
+            ServerError::IdempotencyKeyReused(Uuid::nil()),
+            ServerError::AuthorNotBot(String::new()),
+            ServerError::RouteNotFound,
+            ServerError::MethodNotAllowed,
+            ServerError::LengthRequired,
+            ServerError::PayloadTooLarge,
+            ServerError::OriginNotAllowed(String::new()),
+            ServerError::Internal(String::new()),
+            ServerError::Unavailable
+        ];
+        for error in errors {
+            assert!(error_ids::SERVER_ERROR_IDS.contains(&error.error_id()), "{} is not documented", error.error_id());
+        }
+    }
+
+    #[test]
+    fn test_server_error_display() {
+        let error = ServerError::UserNotInRoom { username: "user".to_string(), room_name: "room".to_string() };
+        assert_eq!(error.to_string(), "User with username user is not in room with name room");
+    }
+}
@@ -3,12 +3,22 @@ use super::user::User;
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
+/// How many client message ids each room remembers to recognize the messages sent again, the oldest ones being forgotten first.
+pub const MAX_CLIENT_MESSAGE_IDS_PER_ROOM: usize = 1000;
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Message {
     pub id: Uuid,
     pub author: Arc<User>,
     pub content: String,
-    pub timestamp: SystemTime
+    #[serde(with = "super::timestamp")]
+    pub timestamp: SystemTime,
+    /// Position of the message in its room, strictly increasing from 1 in the order the messages are posted, 0 until posted
+    #[serde(default)]
+    pub seq: u64,
+    /// Id generated by the client sending the message, so that a message sent again is posted once
+    #[serde(default)]
+    pub client_message_id: Option<Uuid>
 }
 
 impl Message {
@@ -17,9 +27,16 @@ impl Message {
             id: Uuid::new_v4(),
             author,
             content,
-            timestamp: SystemTime::now()
+            timestamp: SystemTime::now(),
+            seq: 0,
+            client_message_id: None
         }
     }
+
+    pub fn with_client_message_id(mut self, client_message_id: Option<Uuid>) -> Message {
+        self.client_message_id = client_message_id;
+        self
+    }
 }
 
 impl PartialEq for Message {
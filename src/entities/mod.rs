@@ -1,4 +1,7 @@
+pub mod error;
 pub mod user;
 pub mod message;
 pub mod room;
-pub mod server;
\ No newline at end of file
+pub mod server;
+pub mod timestamp;
+pub mod webhook;
\ No newline at end of file
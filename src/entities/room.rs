@@ -1,6 +1,7 @@
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
-use super::{message::Message, user::User};
+use super::{error::ServerError, message::{Message, MAX_CLIENT_MESSAGE_IDS_PER_ROOM}, user::User, webhook::{Webhook, WebhookDelivery, MAX_WEBHOOK_DELIVERIES_PER_ROOM}};
+use std::collections::{HashMap, VecDeque};
 use std::sync::Arc;
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -9,6 +10,16 @@ pub struct Room {
     pub name: String,
     pub users: Vec<Arc<User>>,
     pub messages: Vec<Arc<Message>>,
+    pub webhooks: Vec<Arc<Webhook>>,
+    pub webhook_deliveries: VecDeque<WebhookDelivery>,
+    /// Sequence number of the last message posted, 0 if none
+    pub last_seq: u64,
+    /// The recent messages posted with a client message id, by id
+    #[serde(skip)]
+    client_message_ids: HashMap<Uuid, Arc<Message>>,
+    /// The client message ids remembered, oldest first
+    #[serde(skip)]
+    client_message_ids_order: VecDeque<Uuid>,
 }
 
 impl Room {
@@ -18,31 +29,104 @@ impl Room {
             name,
             users: Vec::new(),
             messages: Vec::new(),
+            webhooks: Vec::new(),
+            webhook_deliveries: VecDeque::new(),
+            last_seq: 0,
+            client_message_ids: HashMap::new(),
+            client_message_ids_order: VecDeque::new(),
         }
     }
 
     pub fn is_user_in_room(&self, user: Arc<User>) -> bool {
-        self.users.iter().any(|u| u.to_owned() == user.to_owned())
+        self.users.contains(&user)
     }
 
-    pub fn add_user_to_room(&mut self, user: Arc<User>) -> Result<(), &'static str> {
+    pub fn add_user_to_room(&mut self, user: Arc<User>) -> Result<(), ServerError> {
         if self.is_user_in_room(user.clone()) {
-            return Err("User is already in the room");
+            return Err(ServerError::UserAlreadyInRoom { username: user.username.clone(), room_name: self.name.clone() });
         }
 
         self.users.push(user);
         Ok(())
     }
 
-    pub fn post_new_message(&mut self, message: Arc<Message>) -> Result<(), &'static str> {
+    pub fn remove_user_from_room(&mut self, user: Arc<User>) -> Result<(), ServerError> {
+        if !self.is_user_in_room(user.clone()) {
+            return Err(ServerError::UserNotInRoom { username: user.username.clone(), room_name: self.name.clone() });
+        }
+
+        self.users.retain(|u| *u != user);
+        Ok(())
+    }
+
+    pub fn post_new_message(&mut self, message: Message) -> Result<Arc<Message>, ServerError> {
         let author_user = message.author.clone();
         if !self.is_user_in_room(author_user) {
-            return Err("User is not in the room");
+            return Err(ServerError::UserNotInRoom { username: message.author.username.clone(), room_name: self.name.clone() });
         }
 
-        self.messages.push(message);
+        let message = self.push_message(message);
+        self.remember_client_message_id(&message);
+        Ok(message)
+    }
+
+    /// Appends the message with the next sequence number of the room, ordering the messages whatever their timestamps.
+    fn push_message(&mut self, mut message: Message) -> Arc<Message> {
+        self.last_seq += 1;
+        message.seq = self.last_seq;
+        let message = Arc::new(message);
+        self.messages.push(message.clone());
+        message
+    }
+
+    /// The messages posted after the given sequence number, oldest first, e.g. to catch up from the last message received.
+    pub fn messages_after(&self, seq: u64) -> &[Arc<Message>] {
+        let first_after = self.messages.partition_point(|message| message.seq <= seq);
+        &self.messages[first_after..]
+    }
+
+    /// The recent message posted with the client message id, if still remembered.
+    pub fn find_message_by_client_id(&self, client_message_id: &Uuid) -> Option<Arc<Message>> {
+        self.client_message_ids.get(client_message_id).cloned()
+    }
+
+    fn remember_client_message_id(&mut self, message: &Arc<Message>) {
+        let Some(client_message_id) = message.client_message_id else {
+            return;
+        };
+        if self.client_message_ids_order.len() >= MAX_CLIENT_MESSAGE_IDS_PER_ROOM {
+            if let Some(oldest_client_message_id) = self.client_message_ids_order.pop_front() {
+                self.client_message_ids.remove(&oldest_client_message_id);
+            }
+        }
+        self.client_message_ids_order.push_back(client_message_id);
+        self.client_message_ids.insert(client_message_id, message.clone());
+    }
+
+    pub fn post_new_bot_message(&mut self, message: Message) -> Result<Arc<Message>, ServerError> {
+        if !message.author.is_bot {
+            return Err(ServerError::AuthorNotBot(message.author.username.clone()));
+        }
+
+        Ok(self.push_message(message))
+    }
+
+    pub fn add_webhook(&mut self, webhook: Arc<Webhook>) -> Result<(), ServerError> {
+        if self.webhooks.iter().any(|w| w.url == webhook.url) {
+            return Err(ServerError::WebhookAlreadyRegistered { url: webhook.url.clone(), room_name: self.name.clone() });
+        }
+
+        self.webhooks.push(webhook);
         Ok(())
     }
+
+    pub fn log_webhook_delivery(&mut self, delivery: WebhookDelivery) {
+        if self.webhook_deliveries.len() >= MAX_WEBHOOK_DELIVERIES_PER_ROOM {
+            self.webhook_deliveries.pop_front();
+        }
+
+        self.webhook_deliveries.push_back(delivery);
+    }
 }
 
 #[cfg(test)]
@@ -61,7 +145,7 @@ mod tests {
         let user = Arc::new(User::new("test".to_string()));
         let mut room = Room::new("test".to_string());
         room.add_user_to_room(user.clone()).unwrap();
-        assert_eq!(room.is_user_in_room(user.clone()), true);
+        assert!(room.is_user_in_room(user.clone()));
     }
 
     #[test]
@@ -78,16 +162,123 @@ mod tests {
         let mut room = Room::new("test".to_string());
         room.add_user_to_room(user.clone()).unwrap();
         let result = room.add_user_to_room(user.clone());
-        assert_eq!(result, Err("User is already in the room"));
+        assert_eq!(result, Err(ServerError::UserAlreadyInRoom { username: "test".to_string(), room_name: "test".to_string() }));
+    }
+
+    #[test]
+    fn test_room_remove_user_from_room() {
+        let user = Arc::new(User::new("test".to_string()));
+        let mut room = Room::new("test".to_string());
+        room.add_user_to_room(user.clone()).unwrap();
+        room.remove_user_from_room(user.clone()).unwrap();
+        assert_eq!(room.users.len(), 0);
+    }
+
+    #[test]
+    fn test_room_remove_user_from_room_error() {
+        let user = Arc::new(User::new("test".to_string()));
+        let mut room = Room::new("test".to_string());
+        let result = room.remove_user_from_room(user.clone());
+        assert_eq!(result, Err(ServerError::UserNotInRoom { username: "test".to_string(), room_name: "test".to_string() }));
     }
 
     #[test]
     fn test_room_post_new_message() {
         let user = Arc::new(User::new("test".to_string()));
-        let message = Arc::new(Message::new(user.clone(), "test".to_string()));
+        let message = Message::new(user.clone(), "test".to_string());
         let mut room = Room::new("test".to_string());
         room.add_user_to_room(user.clone()).unwrap();
-        room.post_new_message(message.clone()).unwrap();
+        let message = room.post_new_message(message).unwrap();
+        assert_eq!(room.messages, vec![message]);
+    }
+
+    #[test]
+    fn test_room_messages_seq() {
+        let user = Arc::new(User::new("test".to_string()));
+        let bot = Arc::new(User::new_bot("bot".to_string()));
+        let mut room = Room::new("test".to_string());
+        room.add_user_to_room(user.clone()).unwrap();
+        room.post_new_message(Message::new(user.clone(), "first".to_string())).unwrap();
+        room.post_new_bot_message(Message::new(bot, "second".to_string())).unwrap();
+        assert!(room.post_new_bot_message(Message::new(user.clone(), "rejected".to_string())).is_err());
+        room.post_new_message(Message::new(user.clone(), "third".to_string())).unwrap();
+
+        let seqs: Vec<u64> = room.messages.iter().map(|message| message.seq).collect();
+        assert_eq!(seqs, vec![1, 2, 3]);
+        assert_eq!(room.last_seq, 3);
+        assert_eq!(room.messages_after(0).len(), 3);
+        assert_eq!(room.messages_after(1)[0].content, "second");
+        assert!(room.messages_after(3).is_empty());
+        assert!(room.messages_after(10).is_empty());
+    }
+
+    #[test]
+    fn test_room_post_new_bot_message() {
+        let bot = Arc::new(User::new_bot("bot".to_string()));
+        let message = Message::new(bot, "test".to_string());
+        let mut room = Room::new("test".to_string());
+        room.post_new_bot_message(message).unwrap();
         assert_eq!(room.messages.len(), 1);
     }
+
+    #[test]
+    fn test_room_post_new_bot_message_error() {
+        let user = Arc::new(User::new("test".to_string()));
+        let message = Message::new(user, "test".to_string());
+        let mut room = Room::new("test".to_string());
+        let result = room.post_new_bot_message(message);
+        assert_eq!(result, Err(ServerError::AuthorNotBot("test".to_string())));
+    }
+
+    #[test]
+    fn test_room_add_webhook() {
+        let webhook = Arc::new(Webhook::new("http://localhost/hook".to_string(), "secret".to_string()));
+        let mut room = Room::new("test".to_string());
+        room.add_webhook(webhook.clone()).unwrap();
+        assert_eq!(room.webhooks.len(), 1);
+    }
+
+    #[test]
+    fn test_room_add_webhook_error() {
+        let mut room = Room::new("test".to_string());
+        room.add_webhook(Arc::new(Webhook::new("http://localhost/hook".to_string(), "secret".to_string()))).unwrap();
+        let result = room.add_webhook(Arc::new(Webhook::new("http://localhost/hook".to_string(), "other".to_string())));
+        assert_eq!(result, Err(ServerError::WebhookAlreadyRegistered { url: "http://localhost/hook".to_string(), room_name: "test".to_string() }));
+    }
+
+    #[test]
+    fn test_room_find_message_by_client_id_is_bounded() {
+        let mut room = Room::new("test".to_string());
+        let user = Arc::new(User::new("test".to_string()));
+        room.add_user_to_room(user.clone()).unwrap();
+        let client_message_ids: Vec<Uuid> = (0..MAX_CLIENT_MESSAGE_IDS_PER_ROOM + 5).map(|_| Uuid::new_v4()).collect();
+        for client_message_id in &client_message_ids {
+            room.post_new_message(Message::new(user.clone(), "test".to_string()).with_client_message_id(Some(*client_message_id))).unwrap();
+        }
+        room.post_new_message(Message::new(user.clone(), "test".to_string())).unwrap();
+
+        assert!(room.find_message_by_client_id(&client_message_ids[4]).is_none());
+        let message = room.find_message_by_client_id(&client_message_ids[5]).unwrap();
+        assert_eq!(message, room.messages[5]);
+        assert_eq!(room.client_message_ids.len(), MAX_CLIENT_MESSAGE_IDS_PER_ROOM);
+    }
+
+    #[test]
+    fn test_room_log_webhook_delivery_is_bounded() {
+        let mut room = Room::new("test".to_string());
+        for attempt in 0..(MAX_WEBHOOK_DELIVERIES_PER_ROOM as u32 + 5) {
+            room.log_webhook_delivery(WebhookDelivery {
+                payload_id: uuid::Uuid::new_v4(),
+                webhook_id: uuid::Uuid::new_v4(),
+                event: "message_posted".to_string(),
+                attempt,
+                status_code: Some(200),
+                success: true,
+                error: None,
+                timestamp: std::time::SystemTime::now()
+            });
+        }
+        assert_eq!(room.webhook_deliveries.len(), MAX_WEBHOOK_DELIVERIES_PER_ROOM);
+        assert_eq!(room.webhook_deliveries[0].attempt, 5);
+    }
 }
\ No newline at end of file
@@ -1,109 +1,309 @@
-use super::{message::Message, room::Room, user::User};
-use std::sync::{Arc, Mutex};
+use super::{error::ServerError, message::Message, room::Room, user::User, webhook::{IncomingWebhook, Webhook, WebhookDelivery, WebhookDispatch, WebhookDispatchSender, WebhookEvent, WebhookPayload}};
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+use uuid::Uuid;
+
+/// Server state as shared between request handlers.
+/// Operations scoped to a single room only need the read lock on the server,
+/// since each room is locked on its own: this way traffic in a room does not block the others.
+pub type SharedServer = Arc<RwLock<Server>>;
+
+/// Author of the notices posted by the server itself.
+pub const SERVER_BOT_NAME: &str = "server";
+
+pub const DEFAULT_MAX_MESSAGE_LENGTH: usize = 4096;
+
+/// Message answered to a post: posted now, or posted by an earlier post with the same client message id.
+#[derive(Debug)]
+pub enum PostedMessage {
+    New(Arc<Message>),
+    Replayed(Arc<Message>)
+}
+
+impl PostedMessage {
+    pub fn into_message(self) -> Arc<Message> {
+        match self {
+            PostedMessage::New(message) | PostedMessage::Replayed(message) => message
+        }
+    }
+}
 
 #[derive(Debug)]
 pub struct Server {
-    pub users: Vec<Arc<User>>,
-    pub rooms: Vec<Arc<Mutex<Room>>>,
+    pub users: HashMap<String, Arc<User>>,
+    pub users_by_id: HashMap<Uuid, Arc<User>>,
+    pub rooms: HashMap<String, Arc<RwLock<Room>>>,
+    pub rooms_by_id: HashMap<Uuid, Arc<RwLock<Room>>>,
+    pub incoming_webhooks: HashMap<String, Arc<IncomingWebhook>>,
+    /// Longest message accepted in a room, in characters
+    pub max_message_length: usize,
+    webhook_dispatcher: Option<WebhookDispatchSender>,
+    shutting_down: bool,
+}
+
+impl Default for Server {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Server {
     pub fn new() -> Self {
         Self {
+            users: HashMap::new(),
+            users_by_id: HashMap::new(),
+            rooms: HashMap::new(),
+            rooms_by_id: HashMap::new(),
+            incoming_webhooks: HashMap::new(),
+            max_message_length: DEFAULT_MAX_MESSAGE_LENGTH,
+            webhook_dispatcher: None,
+            shutting_down: false,
+        }
+    }
+
+    pub fn new_shared() -> SharedServer {
+        Arc::new(RwLock::new(Server::new()))
+    }
 
-            users: Vec::new(),
-            rooms: Vec::new(),
+    pub fn set_webhook_dispatcher(&mut self, webhook_dispatcher: WebhookDispatchSender) {
+        self.webhook_dispatcher = Some(webhook_dispatcher);
+    }
+
+    /// Stops emitting webhook events, so that the dispatcher can flush the pending deliveries and stop.
+    pub fn take_webhook_dispatcher(&mut self) -> Option<WebhookDispatchSender> {
+        self.webhook_dispatcher.take()
+    }
+
+    pub fn is_shutting_down(&self) -> bool {
+        self.shutting_down
+    }
+
+    /// Marks the server as shutting down and notifies the users of every room with a message
+    /// from the server itself.
+    pub fn begin_shutdown(&mut self, notice: &str) {
+        self.shutting_down = true;
+
+        let server_bot = Arc::new(User::new_bot(SERVER_BOT_NAME.to_string()));
+        for room_arc in self.rooms.values() {
+            let mut room = room_arc.write().unwrap();
+            let message = Message::new(server_bot.clone(), notice.to_string());
+            if let Ok(message) = room.post_new_bot_message(message) {
+                self.emit_webhook_event(room_arc, &room, WebhookEvent::MessagePosted(message));
+            }
+        }
+    }
+
+    /// Queues the event for delivery to every webhook registered in the room.
+    /// Events are dropped when no dispatcher is set, e.g. in tests.
+    fn emit_webhook_event(&self, room_arc: &Arc<RwLock<Room>>, room: &Room, event: WebhookEvent) {
+        let Some(webhook_dispatcher) = &self.webhook_dispatcher else {
+            return;
+        };
+
+        for webhook in &room.webhooks {
+            let _ = webhook_dispatcher.send(WebhookDispatch {
+                room: room_arc.clone(),
+                webhook: webhook.clone(),
+                payload: WebhookPayload::new(room.name.clone(), event.clone())
+            });
         }
     }
 
     pub fn is_username_already_registered(&self, username: &str) -> bool {
-        self.users.iter().any(|user| user.username == username)
+        self.users.contains_key(username)
     }
 
     pub fn get_user_by_username(&self, username: &str) -> Option<Arc<User>> {
-        self.users.iter().find(|user| user.username == username).map(|user| user.clone())
+        self.users.get(username).cloned()
+    }
+
+    pub fn get_user_by_id(&self, user_id: &Uuid) -> Option<Arc<User>> {
+        self.users_by_id.get(user_id).cloned()
     }
 
     pub fn is_room_name_already_registered(&self, room_name: &str) -> bool {
-        self.rooms.iter().any(|room| room.lock().unwrap().name == room_name)
+        self.rooms.contains_key(room_name)
     }
 
-    pub fn get_room_by_name(&self, room_name: &str) -> Option<Arc<Mutex<Room>>> {
-        self.rooms.iter().find(|room| room.lock().unwrap().name == room_name).map(|room| room.clone())
+    pub fn get_room_by_name(&self, room_name: &str) -> Option<Arc<RwLock<Room>>> {
+        self.rooms.get(room_name).cloned()
     }
 
-    pub fn register_user(&mut self, username: &str) -> Result<(), &'static str> {
+    pub fn get_room_by_id(&self, room_id: &Uuid) -> Option<Arc<RwLock<Room>>> {
+        self.rooms_by_id.get(room_id).cloned()
+    }
+
+    /// Messages posted across all the rooms.
+    pub fn messages_count(&self) -> usize {
+        self.rooms.values().map(|room| room.read().unwrap().messages.len()).sum()
+    }
+
+    /// Rooms sorted by name, so that listings are stable.
+    pub fn get_rooms(&self) -> Vec<Arc<RwLock<Room>>> {
+        let mut rooms_names: Vec<&String> = self.rooms.keys().collect();
+        rooms_names.sort();
+        rooms_names.into_iter().map(|room_name| self.rooms[room_name].clone()).collect()
+    }
+
+    fn find_user(&self, username: &str) -> Result<Arc<User>, ServerError> {
+        self.get_user_by_username(username).ok_or_else(|| ServerError::UserNotFound(username.to_string()))
+    }
+
+    fn find_room(&self, room_name: &str) -> Result<Arc<RwLock<Room>>, ServerError> {
+        self.get_room_by_name(room_name).ok_or_else(|| ServerError::RoomNotFound(room_name.to_string()))
+    }
+
+    pub fn register_user(&mut self, username: &str) -> Result<(), ServerError> {
         if self.is_username_already_registered(username) {
-            return Err("Username already registered");
+            return Err(ServerError::UserAlreadyExists(username.to_string()));
         }
 
         let user = Arc::new(User::new(username.to_string()));
-        self.users.push(user);
+        self.users_by_id.insert(user.id, user.clone());
+        self.users.insert(user.username.clone(), user);
         Ok(())
     }
 
-    pub fn create_room(&mut self, room_name: &str, creator_username: &str) -> Result<(), &'static str> {
-        if self.is_room_name_already_registered(&room_name) {
-            return Err("Room name already registered");
+    pub fn create_room(&mut self, room_name: &str, creator_username: &str) -> Result<(), ServerError> {
+        if self.is_room_name_already_registered(room_name) {
+            return Err(ServerError::RoomAlreadyExists(room_name.to_string()));
         }
 
-        if !self.is_username_already_registered(&creator_username) {
-            return Err("Creator user not registered");
-        }
+        let creator = self.find_user(creator_username)?;
+        let mut room = Room::new(room_name.to_string());
+        room.add_user_to_room(creator)?;
+        let room_id = room.id;
+        let room = Arc::new(RwLock::new(room));
+        self.rooms_by_id.insert(room_id, room.clone());
+        self.rooms.insert(room_name.to_string(), room);
+        Ok(())
+    }
 
-        let room = Arc::new(Mutex::new(Room::new(room_name.to_string())));
-        self.rooms.push(room);
-        match self.add_user_to_room(&room_name.to_owned(), creator_username) {
-            Ok(_) => Ok(()),
-            Err(_) => Err("Failed to add creator user to room")
-        }
+    pub fn add_user_to_room(&self, room_name: &str, username: &str) -> Result<(), ServerError> {
+        let room_arc = self.find_room(room_name)?;
+        let user = self.find_user(username)?;
+        let mut room = room_arc.write().unwrap();
+        room.add_user_to_room(user.clone())?;
+        self.emit_webhook_event(&room_arc, &room, WebhookEvent::UserJoined(user));
+        Ok(())
     }
 
-    pub fn add_user_to_room(&mut self, room_name: &str, username: &str) -> Result<(), &'static str> {
-        if !self.is_room_name_already_registered(&room_name) {
-            return Err("Room name not registered");
-        }
+    pub fn remove_user_from_room(&self, room_name: &str, username: &str) -> Result<(), ServerError> {
+        let room_arc = self.find_room(room_name)?;
+        let user = self.find_user(username)?;
+        let mut room = room_arc.write().unwrap();
+        room.remove_user_from_room(user.clone())?;
+        self.emit_webhook_event(&room_arc, &room, WebhookEvent::UserLeft(user));
+        Ok(())
+    }
 
-        if !self.is_username_already_registered(&username) {
-            return Err("Username not registered");
+    fn check_message_length(&self, message: &str) -> Result<(), ServerError> {
+        if message.chars().count() > self.max_message_length {
+            return Err(ServerError::MessageTooLong(self.max_message_length));
         }
+        Ok(())
+    }
 
-        let room_arc = self.get_room_by_name(&room_name).unwrap();
-        let mut room = room_arc.lock().unwrap();
-        let user = self.get_user_by_username(&username).unwrap();
-        match room.add_user_to_room(user) {
-            Ok(_) => Ok(()),
-            Err(_) => Err("Failed to add user to room")
-        }
+    pub fn post_message_to_room(&self, room_name: &str, username: &str, message: &str) -> Result<Arc<Message>, ServerError> {
+        self.post_client_message_to_room(room_name, username, message, None).map(PostedMessage::into_message)
     }
 
-    pub fn post_message_to_room(&mut self, room_name: &str, username: &str, message: &str) -> Result<Arc<Message>, &'static str> {
-        if !self.is_room_name_already_registered(&room_name) {
-            return Err("Room name not registered");
+    /// Posts a message sent with an id generated by the client, the idempotency key of the post.
+    /// The same message sent again with the same id while the room still remembers it is a retry, answered with the message posted the first time;
+    /// another message sent with that id fails with `IdempotencyKeyReused`.
+    pub fn post_client_message_to_room(&self, room_name: &str, username: &str, message: &str, client_message_id: Option<Uuid>) -> Result<PostedMessage, ServerError> {
+        self.check_message_length(message)?;
+        let room_arc = self.find_room(room_name)?;
+        let user = self.find_user(username)?;
+        let mut room = room_arc.write().unwrap();
+        if let Some(posted_message) = client_message_id.and_then(|client_message_id| room.find_message_by_client_id(&client_message_id)) {
+            if posted_message.author.id != user.id || posted_message.content != message {
+                return Err(ServerError::IdempotencyKeyReused(posted_message.client_message_id.unwrap()));
+            }
+            return Ok(PostedMessage::Replayed(posted_message));
         }
+        let message = room.post_new_message(Message::new(user, message.to_string()).with_client_message_id(client_message_id))?;
+        self.emit_webhook_event(&room_arc, &room, WebhookEvent::MessagePosted(message.clone()));
+        Ok(PostedMessage::New(message))
+    }
+
+    pub fn get_room_messages(&self, room_name: &str) -> Result<Vec<Arc<Message>>, ServerError> {
+        self.get_room_messages_after(room_name, 0)
+    }
 
-        if !self.is_username_already_registered(&username) {
-            return Err("Username not registered");
+    /// The messages of the room posted after the given sequence number, for the clients to catch up from the last message they received.
+    pub fn get_room_messages_after(&self, room_name: &str, seq: u64) -> Result<Vec<Arc<Message>>, ServerError> {
+        let room_arc = self.find_room(room_name)?;
+        let room = room_arc.read().unwrap();
+        Ok(room.messages_after(seq).to_vec())
+    }
+
+    pub fn register_webhook_to_room(&self, room_name: &str, url: &str, secret: Option<&str>) -> Result<Arc<Webhook>, ServerError> {
+        let room_arc = self.find_room(room_name)?;
+
+        let is_url_valid = reqwest::Url::parse(url)
+            .map(|url| url.scheme() == "http" || url.scheme() == "https")
+            .unwrap_or(false);
+        if !is_url_valid {
+            return Err(ServerError::WebhookUrlInvalid(url.to_string()));
         }
 
-        let room_arc = self.get_room_by_name(&room_name).unwrap();
-        let mut room = room_arc.lock().unwrap();
-        let user = self.get_user_by_username(&username).unwrap();
-        let message = Arc::new(Message::new(user.clone(), message.to_string()));
-        match room.post_new_message(message.clone()) {
-            Ok(_) => Ok(message.clone()),
-            Err(_) => Err("Failed to post message to room")
+        let secret = match secret {
+            Some(secret) => secret.to_string(),
+            None => Uuid::new_v4().simple().to_string()
+        };
+        let webhook = Arc::new(Webhook::new(url.to_string(), secret));
+        room_arc.write().unwrap().add_webhook(webhook.clone())?;
+        Ok(webhook)
+    }
+
+    pub fn get_room_webhooks(&self, room_name: &str) -> Result<Vec<Arc<Webhook>>, ServerError> {
+        let room_arc = self.find_room(room_name)?;
+        let room = room_arc.read().unwrap();
+        Ok(room.webhooks.clone())
+    }
+
+    pub fn get_room_webhook_deliveries(&self, room_name: &str) -> Result<Vec<WebhookDelivery>, ServerError> {
+        let room_arc = self.find_room(room_name)?;
+        let room = room_arc.read().unwrap();
+        Ok(room.webhook_deliveries.iter().cloned().collect())
+    }
+
+    pub fn get_incoming_webhook_by_token(&self, token: &str) -> Option<Arc<IncomingWebhook>> {
+        self.incoming_webhooks.get(token).cloned()
+    }
+
+    pub fn create_incoming_webhook(&mut self, room_name: &str, bot_name: &str) -> Result<Arc<IncomingWebhook>, ServerError> {
+        self.find_room(room_name)?;
+
+        if bot_name.trim().is_empty() {
+            return Err(ServerError::BotNameInvalid("it cannot be empty".to_string()));
         }
+        // The bot would otherwise post as the user, only its bot flag telling them apart
+        if self.is_username_already_registered(bot_name) {
+            return Err(ServerError::BotNameInvalid(format!("{} is the username of a registered user", bot_name)));
+        }
+
+        let incoming_webhook = Arc::new(IncomingWebhook::new(room_name.to_string(), bot_name.to_string()));
+        self.incoming_webhooks.insert(incoming_webhook.token.clone(), incoming_webhook.clone());
+        Ok(incoming_webhook)
     }
 
-    pub fn get_room_messages(&self, room_name: &str) -> Result<Vec<Arc<Message>>, &'static str> {
-        if !self.is_room_name_already_registered(&room_name) {
-            return Err("Room name not registered");
+    pub fn revoke_incoming_webhook(&mut self, token: &str) -> Result<(), ServerError> {
+        match self.incoming_webhooks.remove(token) {
+            Some(_) => Ok(()),
+            None => Err(ServerError::IncomingWebhookNotFound)
         }
+    }
 
-        let room_arc = self.get_room_by_name(&room_name).unwrap();
-        let room = room_arc.lock().unwrap();
-        Ok(room.messages.clone())
+    pub fn post_incoming_webhook_message(&self, token: &str, message: &str) -> Result<Arc<Message>, ServerError> {
+        let incoming_webhook = self.get_incoming_webhook_by_token(token).ok_or(ServerError::IncomingWebhookNotFound)?;
+        self.check_message_length(message)?;
+        let room_arc = self.find_room(&incoming_webhook.room_name)?;
+        let mut room = room_arc.write().unwrap();
+        let message = room.post_new_bot_message(Message::new(incoming_webhook.bot.clone(), message.to_string()))?;
+        self.emit_webhook_event(&room_arc, &room, WebhookEvent::MessagePosted(message.clone()));
+        Ok(message)
     }
 }
 
@@ -118,17 +318,31 @@ mod tests {
         assert_eq!(server.users.len(), 0);
     }
 
+    #[test]
+    fn test_server_begin_shutdown() {
+        let mut server = Server::new();
+        server.register_user("test").unwrap();
+        server.create_room("test", "test").unwrap();
+        server.begin_shutdown("The server is shutting down");
+        assert!(server.is_shutting_down());
+
+        let messages = server.get_room_messages("test").unwrap();
+        assert_eq!(messages.len(), 1);
+        assert_eq!(messages[0].author.username, SERVER_BOT_NAME);
+        assert!(messages[0].author.is_bot);
+    }
+
     #[test]
     fn test_server_is_username_already_registered() {
         let mut server = Server::new();
         server.register_user("test").unwrap();
-        assert_eq!(server.is_username_already_registered("test"), true);
+        assert!(server.is_username_already_registered("test"));
     }
 
     #[test]
     fn test_server_is_username_already_registered_false() {
         let server = Server::new();
-        assert_eq!(server.is_username_already_registered("test"), false);
+        assert!(!server.is_username_already_registered("test"));
     }
 
     #[test]
@@ -149,13 +363,13 @@ mod tests {
         let mut server = Server::new();
         server.register_user("test").unwrap();
         server.create_room("test", "test").unwrap();
-        assert_eq!(server.is_room_name_already_registered("test"), true);
+        assert!(server.is_room_name_already_registered("test"));
     }
 
     #[test]
     fn test_server_is_room_name_already_registered_false() {
         let server = Server::new();
-        assert_eq!(server.is_room_name_already_registered("test"), false);
+        assert!(!server.is_room_name_already_registered("test"));
     }
 
     #[test]
@@ -163,7 +377,7 @@ mod tests {
         let mut server = Server::new();
         server.register_user("test").unwrap();
         server.create_room("test", "test").unwrap();
-        assert_eq!(server.get_room_by_name("test").unwrap().lock().unwrap().name, "test");
+        assert_eq!(server.get_room_by_name("test").unwrap().read().unwrap().name, "test");
     }
 
     #[test]
@@ -184,7 +398,7 @@ mod tests {
         let mut server = Server::new();
         server.register_user("test").unwrap();
         let result = server.register_user("test");
-        assert_eq!(result, Err("Username already registered"));
+        assert_eq!(result, Err(ServerError::UserAlreadyExists("test".to_string())));
     }
 
     #[test]
@@ -195,20 +409,51 @@ mod tests {
         assert_eq!(server.rooms.len(), 1);
     }
 
+    #[test]
+    fn test_server_get_room_by_id() {
+        let mut server = Server::new();
+        server.register_user("test").unwrap();
+        server.create_room("test", "test").unwrap();
+        let room_id = server.get_room_by_name("test").unwrap().read().unwrap().id;
+        assert_eq!(server.get_room_by_id(&room_id).unwrap().read().unwrap().name, "test");
+        assert!(server.get_room_by_id(&Uuid::new_v4()).is_none());
+    }
+
+    #[test]
+    fn test_server_get_user_by_id() {
+        let mut server = Server::new();
+        server.register_user("test").unwrap();
+        let user_id = server.get_user_by_username("test").unwrap().id;
+        assert_eq!(server.get_user_by_id(&user_id).unwrap().username, "test");
+        assert!(server.get_user_by_id(&Uuid::new_v4()).is_none());
+    }
+
+    #[test]
+    fn test_server_get_rooms_sorted_by_name() {
+        let mut server = Server::new();
+        server.register_user("test").unwrap();
+        for room_name in ["charlie", "alpha", "bravo"] {
+            server.create_room(room_name, "test").unwrap();
+        }
+        let rooms_names: Vec<String> = server.get_rooms().iter().map(|room| room.read().unwrap().name.clone()).collect();
+        assert_eq!(rooms_names, vec!["alpha", "bravo", "charlie"]);
+    }
+
     #[test]
     fn test_server_create_room_error_room() {
         let mut server = Server::new();
         server.register_user("test").unwrap();
         server.create_room("test", "test").unwrap();
         let result = server.create_room("test", "test");
-        assert_eq!(result, Err("Room name already registered"));
+        assert_eq!(result, Err(ServerError::RoomAlreadyExists("test".to_string())));
     }
 
     #[test]
     fn test_server_create_room_error_creator() {
         let mut server = Server::new();
         let result = server.create_room("test", "test");
-        assert_eq!(result, Err("Creator user not registered"));
+        assert_eq!(result, Err(ServerError::UserNotFound("test".to_string())));
+        assert_eq!(server.rooms.len(), 0);
     }
 
     #[test]
@@ -218,7 +463,7 @@ mod tests {
         server.create_room("test", "test").unwrap();
         server.register_user("test2").unwrap();
         server.add_user_to_room("test", "test2").unwrap();
-        assert_eq!(server.get_room_by_name("test").unwrap().lock().unwrap().users.len(), 2);
+        assert_eq!(server.get_room_by_name("test").unwrap().read().unwrap().users.len(), 2);
     }
 
     #[test]
@@ -227,7 +472,7 @@ mod tests {
         server.register_user("test").unwrap();
         server.create_room("test", "test").unwrap();
         let result = server.add_user_to_room("test", "test2");
-        assert_eq!(result, Err("Username not registered"));
+        assert_eq!(result, Err(ServerError::UserNotFound("test2".to_string())));
     }
 
     #[test]
@@ -236,7 +481,26 @@ mod tests {
         server.register_user("test").unwrap();
         server.create_room("test", "test").unwrap();
         let result = server.add_user_to_room("test", "test");
-        assert_eq!(result, Err("Failed to add user to room"));
+        assert_eq!(result, Err(ServerError::UserAlreadyInRoom { username: "test".to_string(), room_name: "test".to_string() }));
+    }
+
+    #[test]
+    fn test_server_remove_user_from_room() {
+        let mut server = Server::new();
+        server.register_user("test").unwrap();
+        server.create_room("test", "test").unwrap();
+        server.remove_user_from_room("test", "test").unwrap();
+        assert_eq!(server.get_room_by_name("test").unwrap().read().unwrap().users.len(), 0);
+    }
+
+    #[test]
+    fn test_server_remove_user_from_room_error_not_in_room() {
+        let mut server = Server::new();
+        server.register_user("test").unwrap();
+        server.register_user("test2").unwrap();
+        server.create_room("test", "test").unwrap();
+        let result = server.remove_user_from_room("test", "test2");
+        assert_eq!(result, Err(ServerError::UserNotInRoom { username: "test2".to_string(), room_name: "test".to_string() }));
     }
 
     #[test]
@@ -250,13 +514,52 @@ mod tests {
         assert_eq!(server.get_room_messages("test").unwrap().len(), 1);
     }
 
+    #[test]
+    fn test_server_post_client_message_to_room_once() {
+        let mut server = Server::new();
+        server.register_user("test").unwrap();
+        server.create_room("test", "test").unwrap();
+        let client_message_id = Uuid::new_v4();
+
+        let PostedMessage::New(message) = server.post_client_message_to_room("test", "test", "test", Some(client_message_id)).unwrap() else {
+            panic!("expected a new message");
+        };
+        let PostedMessage::Replayed(replayed_message) = server.post_client_message_to_room("test", "test", "test", Some(client_message_id)).unwrap() else {
+            panic!("expected a replayed message");
+        };
+        assert_eq!(replayed_message.id, message.id);
+        assert_eq!(message.client_message_id, Some(client_message_id));
+        assert_eq!(server.get_room_messages("test").unwrap().len(), 1);
+
+        server.post_client_message_to_room("test", "test", "test", None).unwrap();
+        server.post_client_message_to_room("test", "test", "test", None).unwrap();
+        assert_eq!(server.get_room_messages("test").unwrap().len(), 3);
+    }
+
+    #[test]
+    fn test_server_post_client_message_to_room_error_id_reused() {
+        let mut server = Server::new();
+        server.register_user("test").unwrap();
+        server.register_user("test2").unwrap();
+        server.create_room("test", "test").unwrap();
+        server.add_user_to_room("test", "test2").unwrap();
+        let client_message_id = Uuid::new_v4();
+        server.post_client_message_to_room("test", "test", "test", Some(client_message_id)).unwrap();
+
+        let result = server.post_client_message_to_room("test", "test", "other", Some(client_message_id)).map(PostedMessage::into_message);
+        assert_eq!(result, Err(ServerError::IdempotencyKeyReused(client_message_id)));
+        let result = server.post_client_message_to_room("test", "test2", "test", Some(client_message_id)).map(PostedMessage::into_message);
+        assert_eq!(result, Err(ServerError::IdempotencyKeyReused(client_message_id)));
+        assert_eq!(server.get_room_messages("test").unwrap().len(), 1);
+    }
+
     #[test]
     fn test_server_post_message_to_room_error_room() {
         let mut server = Server::new();
         server.register_user("test").unwrap();
         server.create_room("test", "test").unwrap();
         let result = server.post_message_to_room("test2", "test", "test");
-        assert_eq!(result, Err("Room name not registered"));
+        assert_eq!(result, Err(ServerError::RoomNotFound("test2".to_string())));
     }
 
     #[test]
@@ -265,7 +568,18 @@ mod tests {
         server.register_user("test").unwrap();
         server.create_room("test", "test").unwrap();
         let result = server.post_message_to_room("test", "test2", "test");
-        assert_eq!(result, Err("Username not registered"));
+        assert_eq!(result, Err(ServerError::UserNotFound("test2".to_string())));
+    }
+
+    #[test]
+    fn test_server_post_message_to_room_error_too_long() {
+        let mut server = Server::new();
+        server.max_message_length = 5;
+        server.register_user("test").unwrap();
+        server.create_room("test", "test").unwrap();
+        assert!(server.post_message_to_room("test", "test", "hello").is_ok());
+        let result = server.post_message_to_room("test", "test", "hello!");
+        assert_eq!(result, Err(ServerError::MessageTooLong(5)));
     }
 
     #[test]
@@ -277,6 +591,21 @@ mod tests {
         assert_eq!(server.get_room_messages("test").unwrap().len(), 1);
     }
 
+    #[test]
+    fn test_server_get_room_messages_after() {
+        let mut server = Server::new();
+        server.register_user("test").unwrap();
+        server.create_room("test", "test").unwrap();
+        for content in ["first", "second", "third"] {
+            server.post_message_to_room("test", "test", content).unwrap();
+        }
+        let messages = server.get_room_messages_after("test", 1).unwrap();
+        let contents: Vec<&str> = messages.iter().map(|message| message.content.as_str()).collect();
+        assert_eq!(contents, vec!["second", "third"]);
+        assert_eq!(messages[1].seq, 3);
+        assert!(server.get_room_messages_after("test", 3).unwrap().is_empty());
+    }
+
     #[test]
     fn test_server_get_room_messages_no_messages() {
         let mut server = Server::new();
@@ -292,6 +621,120 @@ mod tests {
         server.register_user("test").unwrap();
         server.create_room("test", "test").unwrap();
         let result = server.get_room_messages("test2");
-        assert_eq!(result, Err("Room name not registered"));
+        assert_eq!(result, Err(ServerError::RoomNotFound("test2".to_string())));
+    }
+
+    #[test]
+    fn test_server_register_webhook_to_room() {
+        let mut server = Server::new();
+        server.register_user("test").unwrap();
+        server.create_room("test", "test").unwrap();
+        let webhook = server.register_webhook_to_room("test", "http://localhost/hook", Some("secret")).unwrap();
+        assert_eq!(webhook.secret, "secret");
+        assert_eq!(server.get_room_webhooks("test").unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_server_register_webhook_to_room_generates_secret() {
+        let mut server = Server::new();
+        server.register_user("test").unwrap();
+        server.create_room("test", "test").unwrap();
+        let webhook = server.register_webhook_to_room("test", "http://localhost/hook", None).unwrap();
+        assert!(!webhook.secret.is_empty());
+    }
+
+    #[test]
+    fn test_server_register_webhook_to_room_error_room() {
+        let server = Server::new();
+        let result = server.register_webhook_to_room("test", "http://localhost/hook", None);
+        assert_eq!(result, Err(ServerError::RoomNotFound("test".to_string())));
+    }
+
+    #[test]
+    fn test_server_register_webhook_to_room_error_url() {
+        let mut server = Server::new();
+        server.register_user("test").unwrap();
+        server.create_room("test", "test").unwrap();
+        let result = server.register_webhook_to_room("test", "ftp://localhost/hook", None);
+        assert_eq!(result, Err(ServerError::WebhookUrlInvalid("ftp://localhost/hook".to_string())));
+    }
+
+    #[test]
+    fn test_server_emits_webhook_events() {
+        let (sender, mut receiver) = tokio::sync::mpsc::unbounded_channel();
+        let mut server = Server::new();
+        server.set_webhook_dispatcher(sender);
+        server.register_user("test").unwrap();
+        server.register_user("test2").unwrap();
+        server.create_room("test", "test").unwrap();
+        server.register_webhook_to_room("test", "http://localhost/hook", None).unwrap();
+
+        server.add_user_to_room("test", "test2").unwrap();
+        server.post_message_to_room("test", "test2", "hello").unwrap();
+
+        let user_joined = receiver.try_recv().unwrap();
+        assert_eq!(user_joined.payload.event.name(), "user_joined");
+        assert_eq!(user_joined.payload.room, "test");
+        let message_posted = receiver.try_recv().unwrap();
+        assert_eq!(message_posted.payload.event.name(), "message_posted");
+        assert!(receiver.try_recv().is_err());
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_server_create_incoming_webhook() {
+        let mut server = Server::new();
+        server.register_user("test").unwrap();
+        server.create_room("test", "test").unwrap();
+        let incoming_webhook = server.create_incoming_webhook("test", "ci").unwrap();
+        assert_eq!(incoming_webhook.room_name, "test");
+        assert_eq!(server.get_incoming_webhook_by_token(&incoming_webhook.token).unwrap().bot.username, "ci");
+        assert!(!server.is_username_already_registered("ci"));
+    }
+
+    #[test]
+    fn test_server_create_incoming_webhook_error_bot_name() {
+        let mut server = Server::new();
+        server.register_user("test").unwrap();
+        server.create_room("test", "test").unwrap();
+        assert_eq!(server.create_incoming_webhook("test", " ").unwrap_err(), ServerError::BotNameInvalid("it cannot be empty".to_string()));
+        let result = server.create_incoming_webhook("test", "test");
+        assert_eq!(result.unwrap_err(), ServerError::BotNameInvalid("test is the username of a registered user".to_string()));
+    }
+
+    #[test]
+    fn test_server_create_incoming_webhook_error_room() {
+        let mut server = Server::new();
+        let result = server.create_incoming_webhook("test", "ci");
+        assert_eq!(result.unwrap_err(), ServerError::RoomNotFound("test".to_string()));
+    }
+
+    #[test]
+    fn test_server_revoke_incoming_webhook() {
+        let mut server = Server::new();
+        server.register_user("test").unwrap();
+        server.create_room("test", "test").unwrap();
+        let incoming_webhook = server.create_incoming_webhook("test", "ci").unwrap();
+        server.revoke_incoming_webhook(&incoming_webhook.token).unwrap();
+        assert!(server.get_incoming_webhook_by_token(&incoming_webhook.token).is_none());
+        assert_eq!(server.revoke_incoming_webhook(&incoming_webhook.token), Err(ServerError::IncomingWebhookNotFound));
+    }
+
+    #[test]
+    fn test_server_post_incoming_webhook_message() {
+        let mut server = Server::new();
+        server.register_user("test").unwrap();
+        server.create_room("test", "test").unwrap();
+        let incoming_webhook = server.create_incoming_webhook("test", "ci").unwrap();
+        let message = server.post_incoming_webhook_message(&incoming_webhook.token, "build passed").unwrap();
+        assert_eq!(message.author.username, "ci");
+        assert!(message.author.is_bot);
+        assert_eq!(server.get_room_messages("test").unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_server_post_incoming_webhook_message_error_token() {
+        let server = Server::new();
+        let result = server.post_incoming_webhook_message("unknown", "build passed");
+        assert_eq!(result, Err(ServerError::IncomingWebhookNotFound));
+    }
+}
@@ -0,0 +1,77 @@
+//! Timestamps on the wire, as RFC 3339 strings in UTC, e.g. `2023-11-14T22:13:20.000000123Z`.
+//! The `{"secs_since_epoch", "nanos_since_epoch"}` objects sent before are still read, for the clients and servers not upgraded yet.
+//! Used with `#[serde(with = "crate::entities::timestamp")]` on the `SystemTime` fields.
+
+use chrono::{DateTime, SecondsFormat, Utc};
+use serde::{de::Error, Deserialize, Deserializer, Serializer};
+use std::time::{Duration, SystemTime};
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum WireTimestamp {
+    Rfc3339(String),
+    SinceEpoch { secs_since_epoch: u64, nanos_since_epoch: u32 }
+}
+
+/// Formats the timestamp as sent on the wire, e.g. `2023-11-14T22:13:20.5Z`.
+pub fn to_rfc3339(timestamp: SystemTime) -> String {
+    let datetime: DateTime<Utc> = timestamp.into();
+    datetime.to_rfc3339_opts(SecondsFormat::AutoSi, true)
+}
+
+/// Parses an RFC 3339 timestamp in any timezone, e.g. `2023-11-15T00:13:20+02:00`.
+pub fn parse_rfc3339(timestamp: &str) -> Result<SystemTime, String> {
+    DateTime::parse_from_rfc3339(timestamp)
+        .map(SystemTime::from)
+        .map_err(|error| format!("invalid RFC 3339 timestamp {}: {}", timestamp, error))
+}
+
+pub fn serialize<S: Serializer>(timestamp: &SystemTime, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(&to_rfc3339(*timestamp))
+}
+
+pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<SystemTime, D::Error> {
+    match WireTimestamp::deserialize(deserializer)? {
+        WireTimestamp::Rfc3339(timestamp) => parse_rfc3339(&timestamp).map_err(D::Error::custom),
+        WireTimestamp::SinceEpoch { secs_since_epoch, nanos_since_epoch } => SystemTime::UNIX_EPOCH
+            .checked_add(Duration::new(secs_since_epoch, nanos_since_epoch))
+            .ok_or_else(|| D::Error::custom("timestamp out of range"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::{Deserialize, Serialize};
+    use serde_json::json;
+    use std::time::{Duration, SystemTime};
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Timestamped {
+        #[serde(with = "super")]
+        timestamp: SystemTime
+    }
+
+    #[test]
+    fn test_timestamp_serialize_rfc3339() {
+        let timestamped = |duration| Timestamped { timestamp: SystemTime::UNIX_EPOCH + duration };
+
+        assert_eq!(serde_json::to_value(timestamped(Duration::new(1700000000, 0))).unwrap(), json!({"timestamp": "2023-11-14T22:13:20Z"}));
+        assert_eq!(serde_json::to_value(timestamped(Duration::new(1700000000, 123))).unwrap(), json!({"timestamp": "2023-11-14T22:13:20.000000123Z"}));
+        assert_eq!(serde_json::to_value(timestamped(Duration::from_millis(1700000000500))).unwrap(), json!({"timestamp": "2023-11-14T22:13:20.500Z"}));
+    }
+
+    #[test]
+    fn test_timestamp_deserialize() {
+        let expected = Timestamped { timestamp: SystemTime::UNIX_EPOCH + Duration::new(1700000000, 123) };
+
+        for timestamp in [
+            json!("2023-11-14T22:13:20.000000123Z"),
+            json!("2023-11-15T00:13:20.000000123+02:00"),
+            json!({"secs_since_epoch": 1700000000, "nanos_since_epoch": 123})
+        ] {
+            assert_eq!(serde_json::from_value::<Timestamped>(json!({"timestamp": timestamp})).unwrap(), expected);
+        }
+        assert!(serde_json::from_value::<Timestamped>(json!({"timestamp": "yesterday"})).is_err());
+        assert!(serde_json::from_value::<Timestamped>(json!({"timestamp": 1700000000})).is_err());
+    }
+}
@@ -4,14 +4,26 @@ use serde::{Serialize, Deserialize};
 #[derive(Debug, Serialize, Deserialize)]
 pub struct User {
     pub id: Uuid,
-    pub username: String
+    pub username: String,
+    #[serde(default)]
+    pub is_bot: bool
 }
 
 impl User {
     pub fn new(username: String) -> User {
         User {
             id: Uuid::new_v4(),
-            username
+            username,
+            is_bot: false
+        }
+    }
+
+    /// Creates an identity for automated posters, which is not registered in the server users.
+    pub fn new_bot(username: String) -> User {
+        User {
+            id: Uuid::new_v4(),
+            username,
+            is_bot: true
         }
     }
 }
@@ -31,6 +43,15 @@ mod tests {
         let user = User::new("user".to_string());
 
         assert_eq!(user.username, "user");
+        assert!(!user.is_bot);
+    }
+
+    #[test]
+    fn test_user_new_bot() {
+        let user = User::new_bot("bot".to_string());
+
+        assert_eq!(user.username, "bot");
+        assert!(user.is_bot);
     }
 
     #[test]
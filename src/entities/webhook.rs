@@ -0,0 +1,152 @@
+use std::{sync::{Arc, RwLock}, time::SystemTime};
+use super::{message::Message, room::Room, user::User};
+use serde::{Deserialize, Serialize};
+use tokio::sync::mpsc::UnboundedSender;
+use uuid::Uuid;
+
+pub const MAX_WEBHOOK_DELIVERIES_PER_ROOM: usize = 100;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Webhook {
+    pub id: Uuid,
+    pub url: String,
+    #[serde(skip_serializing, default)]
+    pub secret: String
+}
+
+impl Webhook {
+    pub fn new(url: String, secret: String) -> Webhook {
+        Webhook {
+            id: Uuid::new_v4(),
+            url,
+            secret
+        }
+    }
+}
+
+impl PartialEq for Webhook {
+    fn eq(&self, other: &Self) -> bool {
+        self.id == other.id
+    }
+}
+
+/// Token-authenticated entry point letting external scripts post into a room as a bot.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct IncomingWebhook {
+    pub id: Uuid,
+    pub token: String,
+    pub room_name: String,
+    pub bot: Arc<User>
+}
+
+impl IncomingWebhook {
+    pub fn new(room_name: String, bot_name: String) -> IncomingWebhook {
+        IncomingWebhook {
+            id: Uuid::new_v4(),
+            token: Uuid::new_v4().simple().to_string(),
+            room_name,
+            bot: Arc::new(User::new_bot(bot_name))
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "event", content = "data", rename_all = "snake_case")]
+pub enum WebhookEvent {
+    MessagePosted(Arc<Message>),
+    UserJoined(Arc<User>),
+    UserLeft(Arc<User>)
+}
+
+impl WebhookEvent {
+    pub fn name(&self) -> &'static str {
+        match self {
+            WebhookEvent::MessagePosted(_) => "message_posted",
+            WebhookEvent::UserJoined(_) => "user_joined",
+            WebhookEvent::UserLeft(_) => "user_left"
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WebhookPayload {
+    pub id: Uuid,
+    pub room: String,
+    #[serde(with = "super::timestamp")]
+    pub timestamp: SystemTime,
+    #[serde(flatten)]
+    pub event: WebhookEvent
+}
+
+impl WebhookPayload {
+    pub fn new(room: String, event: WebhookEvent) -> WebhookPayload {
+        WebhookPayload {
+            id: Uuid::new_v4(),
+            room,
+            timestamp: SystemTime::now(),
+            event
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WebhookDelivery {
+    pub payload_id: Uuid,
+    pub webhook_id: Uuid,
+    pub event: String,
+    pub attempt: u32,
+    pub status_code: Option<u16>,
+    pub success: bool,
+    pub error: Option<String>,
+    #[serde(with = "super::timestamp")]
+    pub timestamp: SystemTime
+}
+
+/// A single webhook delivery job, handed by the `Server` to the delivery worker.
+/// Carries the room so that the worker can append to its delivery log once done.
+#[derive(Debug)]
+pub struct WebhookDispatch {
+    pub room: Arc<RwLock<Room>>,
+    pub webhook: Arc<Webhook>,
+    pub payload: WebhookPayload
+}
+
+pub type WebhookDispatchSender = UnboundedSender<WebhookDispatch>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_webhook_new() {
+        let webhook = Webhook::new("http://localhost/hook".to_string(), "secret".to_string());
+        assert_eq!(webhook.url, "http://localhost/hook");
+        assert_eq!(webhook.secret, "secret");
+    }
+
+    #[test]
+    fn test_webhook_serialize_hides_secret() {
+        let webhook = Webhook::new("http://localhost/hook".to_string(), "secret".to_string());
+        let serialized = serde_json::to_string(&webhook).unwrap();
+        assert!(!serialized.contains("secret"));
+    }
+
+    #[test]
+    fn test_incoming_webhook_new() {
+        let incoming_webhook = IncomingWebhook::new("room".to_string(), "bot".to_string());
+        assert_eq!(incoming_webhook.room_name, "room");
+        assert_eq!(incoming_webhook.bot.username, "bot");
+        assert!(incoming_webhook.bot.is_bot);
+        assert!(!incoming_webhook.token.is_empty());
+    }
+
+    #[test]
+    fn test_webhook_payload_serialize() {
+        let user = Arc::new(User::new("test".to_string()));
+        let payload = WebhookPayload::new("room".to_string(), WebhookEvent::UserJoined(user));
+        let value = serde_json::to_value(&payload).unwrap();
+        assert_eq!(value["event"], "user_joined");
+        assert_eq!(value["room"], "room");
+        assert_eq!(value["data"]["username"], "test");
+    }
+}
@@ -0,0 +1,9 @@
+//! Live chat server, along with its command line client and the `ChatClient` SDK to talk to it from other crates.
+
+pub mod config;
+pub mod entities;
+pub mod web_server;
+pub mod cli_client;
+pub mod chat_client;
+
+pub use chat_client::{ChatClient, ChatClientOptions};
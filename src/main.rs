@@ -1,11 +1,7 @@
-mod entities;
-mod web_server;
-mod cli_client;
-
-use std::env;
+use std::{env, path::PathBuf, process::exit};
 
 use clap::{Parser, ValueEnum};
-use regex::Regex;
+use rust_live_chat::{cli_client::{self, commands::{ClientCommand, OutputFormat}, outbox::{self, Outbox}, profiles::{self, ActiveProfile}}, config::{self, CliOverrides, Config}, web_server};
 
 #[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, ValueEnum)]
 enum RunType {
@@ -15,29 +11,72 @@ enum RunType {
     Client
 }
 
-const DEFAULT_HOST: &str = "127.0.0.1";
-const DEFAULT_HOST_DOCKER: &str = "0.0.0.0";
-const DEFAULT_PORT: u16 = 3030;
-
 #[derive(Parser)]
+#[command(subcommand_negates_reqs = true)]
 struct Cli {
-    /// Whether to run the server or the client
-    #[arg(long)]
-    run: RunType,
-    /// The host where server will run (if server) or the host to connect to (if client)
-    #[arg(long, value_parser = host_is_valid, default_value_t = DEFAULT_HOST.to_string())]
-    host: String,
+    /// Whether to run the server or the interactive client
+    #[arg(long, required_unless_present = "print_config")]
+    run: Option<RunType>,
+    /// Run a client command instead of the interactive client, e.g. from a script
+    #[command(subcommand)]
+    command: Option<ClientCommand>,
+    /// The format of the client commands output
+    #[arg(long, value_enum, global = true, default_value_t = OutputFormat::Text)]
+    output: OutputFormat,
+    /// The TOML configuration file, overridden by the CHAT_* environment variables and the flags below
+    #[arg(long, env = "CHAT_CONFIG", global = true)]
+    config: Option<PathBuf>,
+    /// The host where server will run (if server) or the host to connect to (if client):
+    /// an IPv4 or IPv6 address, a hostname or, for the client only, the URL of the server
+    #[arg(long, value_parser = config::host_is_valid, global = true)]
+    host: Option<String>,
     /// The port where server will run (if server) or the port to connect to (if client)
-    #[arg(long, default_value_t = DEFAULT_PORT)]
-    port: u16,
+    #[arg(long, global = true)]
+    port: Option<u16>,
+    /// The format of the server logs
+    #[arg(long, value_enum)]
+    log_format: Option<web_server::logging::LogFormat>,
+    /// Serve HTTPS (if server) or connect over HTTPS (if client)
+    #[arg(long, global = true)]
+    tls: bool,
+    /// The PEM certificate chain served over HTTPS
+    #[arg(long)]
+    tls_cert: Option<PathBuf>,
+    /// The PEM private key of the certificate served over HTTPS
+    #[arg(long)]
+    tls_key: Option<PathBuf>,
+    /// A PEM certificate authority trusted by the client, e.g. for self-signed internal certificates
+    #[arg(long, global = true)]
+    tls_ca_cert: Option<PathBuf>,
+    /// The client profile remembering the server, user and last room, created on first use
+    #[arg(long, env = "CHAT_PROFILE", global = true)]
+    profile: Option<String>,
+    /// The client profiles file, ~/.config/rust-live-chat/profiles.toml by default
+    #[arg(long, env = "CHAT_PROFILES_FILE", global = true)]
+    profiles_file: Option<PathBuf>,
+    /// The client outbox file, keeping the messages typed while offline, ~/.local/state/rust-live-chat/outbox.json by default
+    #[arg(long, env = "CHAT_OUTBOX_FILE")]
+    outbox_file: Option<PathBuf>,
+    /// Print the effective configuration and exit
+    #[arg(long)]
+    print_config: bool,
+}
+
+fn load_active_profile(name: &str, profiles_file: Option<PathBuf>) -> Result<ActiveProfile, String> {
+    let path = profiles_file
+        .or_else(|| profiles::default_profiles_path(|key| env::var(key).ok()))
+        .ok_or("Could not locate the profiles file, set it with --profiles-file")?;
+    ActiveProfile::load(&path, name)
 }
 
-const HOST_REGEX: &str = r"^(\d{1,3}\.){3}\d{1,3}$";
-fn host_is_valid(s: &str) -> Result<String, String> {
-    if Regex::new(HOST_REGEX).unwrap().is_match(s) {
-        Ok(s.to_string())
-    } else {
-        Err("Invalid host".to_string())
+/// Loads the outbox file, keeping the messages in memory only if it cannot be located.
+fn load_outbox(outbox_file: Option<PathBuf>) -> Result<Outbox, String> {
+    match outbox_file.or_else(|| outbox::default_outbox_path(|key| env::var(key).ok())) {
+        Some(path) => Outbox::load(&path),
+        None => {
+            println!("Warning: could not locate the outbox file, set it with --outbox-file; messages typed while offline are lost on exit");
+            Ok(Outbox::in_memory())
+        }
     }
 }
 
@@ -45,34 +84,65 @@ fn host_is_valid(s: &str) -> Result<String, String> {
 async fn main() {
     let cli = Cli::parse();
 
-    let mut host = cli.host;
-    let port = cli.port;
+    let active_profile = cli.profile.as_deref().map(|name| load_active_profile(name, cli.profiles_file.clone()).unwrap_or_else(|error| {
+        eprintln!("{}", error);
+        exit(2);
+    }));
+    let profile = active_profile.as_ref().map(|active_profile| active_profile.profile.clone()).unwrap_or_default();
 
-    match env::var("ENVIRONMENT") {
-        Ok(env) => {
-            match env.as_str() {
-                "docker" => {
-                    println!("Running in Docker environment, using default host 0.0.0.0 to expose the server to the outside");
-                    host = DEFAULT_HOST_DOCKER.to_string();
-                },
-                _ => {
-                    println!("Running in unknown environment, using provided host and port");
-                }
-            }
-        },
-        Err(_) => { 
-            print!("No ENVIRONMENT variable set, using provided host and port");
+    let cli_overrides = CliOverrides {
+        host: cli.host.or_else(|| profile.server.clone()),
+        port: cli.port,
+        log_format: cli.log_format,
+        tls: cli.tls,
+        tls_cert_path: cli.tls_cert,
+        tls_key_path: cli.tls_key,
+        tls_ca_cert_path: cli.tls_ca_cert
+    };
+    let config = match Config::load(cli.config.as_deref(), |key| env::var(key).ok(), cli_overrides) {
+        Ok(config) => config,
+        Err(error) => {
+            eprintln!("{}", error);
+            exit(2);
         }
+    };
+
+    if cli.print_config {
+        print!("{}", config.to_toml());
+        return;
+    }
+
+    if let Some(command) = cli.command {
+        exit(cli_client::commands::run_command(&config, &profile, command, cli.output).await);
     }
 
     match cli.run {
-        RunType::Server => {
-            let web_server = web_server::WebServer::new(&host, port);
-            web_server.run().await;
+        Some(RunType::Server) => {
+            web_server::logging::init_logging(config.logging.format, &config.logging.level);
+            let web_server = web_server::WebServer::new(&config).unwrap_or_else(|error| {
+                eprintln!("{}", error);
+                exit(2);
+            });
+            if let Err(error) = web_server.run().await {
+                eprintln!("Could not start the server: {}", error);
+                exit(1);
+            }
         },
-        RunType::Client => {
-            let mut cli_client = cli_client::CliClient::new(host, port);
-            cli_client.run().await;
-        }
+        Some(RunType::Client) => {
+            let chat_client = cli_client::chat_client_from_config(&config, &profile).unwrap_or_else(|error| {
+                eprintln!("{}", error);
+                exit(2);
+            });
+            let outbox = load_outbox(cli.outbox_file).unwrap_or_else(|error| {
+                eprintln!("{}", error);
+                exit(2);
+            });
+            let mut cli_client = cli_client::CliClient::new(chat_client, active_profile, outbox);
+            if let Err(error) = cli_client.run().await {
+                eprintln!("{}", error);
+                exit(1);
+            }
+        },
+        None => unreachable!("clap requires --run unless --print-config or a command is given")
     }
 }
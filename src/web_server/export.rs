@@ -0,0 +1,242 @@
+//! Room history exports, rendered one message at a time so that a large room is streamed rather than held in memory twice.
+
+use crate::entities::{error::ServerError, timestamp};
+use crate::web_server::handlers::views::MessageView;
+use std::collections::HashMap;
+use std::time::SystemTime;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum ExportFormat {
+    /// One JSON message per line
+    #[value(name = "jsonl")]
+    JsonLines,
+    Csv,
+    /// Transcript readable as is, or rendered by any Markdown viewer
+    Markdown,
+    /// Standalone page, without any external stylesheet or script
+    Html,
+}
+
+impl ExportFormat {
+    pub fn name(&self) -> &'static str {
+        match self {
+            ExportFormat::JsonLines => "jsonl",
+            ExportFormat::Csv => "csv",
+            ExportFormat::Markdown => "markdown",
+            ExportFormat::Html => "html",
+        }
+    }
+
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            ExportFormat::JsonLines => "application/x-ndjson",
+            ExportFormat::Csv => "text/csv; charset=utf-8",
+            ExportFormat::Markdown => "text/markdown; charset=utf-8",
+            ExportFormat::Html => "text/html; charset=utf-8",
+        }
+    }
+
+    pub fn file_extension(&self) -> &'static str {
+        match self {
+            ExportFormat::JsonLines => "jsonl",
+            ExportFormat::Csv => "csv",
+            ExportFormat::Markdown => "md",
+            ExportFormat::Html => "html",
+        }
+    }
+}
+
+/// What to export from a room: its messages posted in `[since, until)`, in the given format.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ExportQuery {
+    pub format: ExportFormat,
+    pub since: Option<SystemTime>,
+    pub until: Option<SystemTime>,
+}
+
+impl ExportQuery {
+    /// Reads the `format`, `since` and `until` query parameters, the format being JSON lines by default.
+    pub fn from_query_params(query_params: &HashMap<String, String>) -> Result<ExportQuery, ServerError> {
+        let format = match query_params.get("format") {
+            Some(format) => <ExportFormat as clap::ValueEnum>::from_str(format, true)
+                .map_err(|_| ServerError::BadRequest(format!("Invalid format query parameter {}, expected jsonl, csv, markdown or html", format)))?,
+            None => ExportFormat::JsonLines,
+        };
+        let bound = |name: &str| query_params.get(name)
+            .map(|bound| timestamp::parse_rfc3339(bound).map_err(|reason| ServerError::BadRequest(format!("Invalid {} query parameter: {}", name, reason))))
+            .transpose();
+        Ok(ExportQuery { format, since: bound("since")?, until: bound("until")? })
+    }
+
+    pub fn to_query_params(&self) -> Vec<(&'static str, String)> {
+        let mut query_params = vec![("format", self.format.name().to_string())];
+        if let Some(since) = self.since {
+            query_params.push(("since", timestamp::to_rfc3339(since)));
+        }
+        if let Some(until) = self.until {
+            query_params.push(("until", timestamp::to_rfc3339(until)));
+        }
+        query_params
+    }
+
+    pub fn includes(&self, message: &MessageView) -> bool {
+        self.since.is_none_or(|since| message.timestamp >= since) && self.until.is_none_or(|until| message.timestamp < until)
+    }
+}
+
+/// Renders the beginning of the export, before its first message.
+pub fn render_header(format: ExportFormat, room_name: &str) -> String {
+    match format {
+        ExportFormat::JsonLines => String::new(),
+        ExportFormat::Csv => "seq,timestamp,author,is_bot,content\n".to_string(),
+        ExportFormat::Markdown => format!("# {}\n\n", escape_markdown(room_name)),
+        ExportFormat::Html => format!(concat!(
+            "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{room}</title>\n",
+            "<style>body {{ font-family: sans-serif; }} li {{ white-space: pre-wrap; }} time {{ color: #666; }}</style>\n",
+            "</head>\n<body>\n<h1>{room}</h1>\n<ol>\n"
+        ), room = escape_html(room_name)),
+    }
+}
+
+pub fn render_message(format: ExportFormat, message: &MessageView) -> String {
+    let timestamp = timestamp::to_rfc3339(message.timestamp);
+    let bot_marker = if message.author.is_bot { " (bot)" } else { "" };
+    match format {
+        ExportFormat::JsonLines => format!("{}\n", serde_json::to_string(message).expect("messages are serializable")),
+        ExportFormat::Csv => format!("{},{},{},{},{}\n", message.seq, timestamp, escape_csv(&message.author.username), message.author.is_bot, escape_csv(&message.content)),
+        ExportFormat::Markdown => format!(
+            "- **{}**{} ({}): {}\n",
+            escape_markdown(&message.author.username), bot_marker, timestamp, escape_markdown(&message.content).replace('\n', "  \n  ")
+        ),
+        ExportFormat::Html => format!(
+            "<li><time datetime=\"{timestamp}\">{timestamp}</time> <strong>{}</strong>{}: {}</li>\n",
+            escape_html(&message.author.username), bot_marker, escape_html(&message.content)
+        ),
+    }
+}
+
+/// Renders the end of the export, after its last message.
+pub fn render_footer(format: ExportFormat) -> String {
+    match format {
+        ExportFormat::Html => "</ol>\n</body>\n</html>\n".to_string(),
+        _ => String::new(),
+    }
+}
+
+/// Quotes the CSV field if it contains a separator, a quote or a line break, doubling its quotes.
+/// A field starting like a formula is prefixed with `'`, for spreadsheets to show it as text rather than evaluate it.
+fn escape_csv(field: &str) -> String {
+    let field = if field.starts_with(['=', '+', '-', '@', '\t', '\r']) {
+        format!("'{}", field)
+    } else {
+        field.to_string()
+    };
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field
+    }
+}
+
+/// Escapes the characters Markdown would read as formatting, so that messages are rendered as typed.
+fn escape_markdown(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for character in text.chars() {
+        if matches!(character, '\\' | '`' | '*' | '_' | '[' | ']' | '<' | '>' | '#' | '|' | '~') {
+            escaped.push('\\');
+        }
+        escaped.push(character);
+    }
+    escaped
+}
+
+fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for character in text.chars() {
+        match character {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            _ => escaped.push(character),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::web_server::handlers::views::UserView;
+    use std::time::Duration;
+    use uuid::Uuid;
+
+    fn message(content: &str, is_bot: bool) -> MessageView {
+        MessageView {
+            id: Uuid::nil(),
+            author: UserView { id: Uuid::nil(), username: "alice".to_string(), is_bot },
+            content: content.to_string(),
+            timestamp: SystemTime::UNIX_EPOCH + Duration::from_secs(1700000000),
+            seq: 7,
+            client_message_id: None
+        }
+    }
+
+    #[test]
+    fn test_export_query_from_query_params() {
+        let query_params = |pairs: &[(&str, &str)]| pairs.iter().map(|(name, value)| (name.to_string(), value.to_string())).collect::<HashMap<_, _>>();
+
+        let query = ExportQuery::from_query_params(&query_params(&[])).unwrap();
+        assert_eq!(query, ExportQuery { format: ExportFormat::JsonLines, since: None, until: None });
+        let query = ExportQuery::from_query_params(&query_params(&[("format", "HTML"), ("since", "2023-11-14T00:00:00Z")])).unwrap();
+        assert_eq!(query.format, ExportFormat::Html);
+        assert_eq!(query.since, Some(SystemTime::UNIX_EPOCH + Duration::from_secs(1699920000)));
+        assert_eq!(ExportQuery::from_query_params(&query.to_query_params().into_iter().map(|(name, value)| (name.to_string(), value)).collect()).unwrap(), query);
+
+        assert!(ExportQuery::from_query_params(&query_params(&[("format", "pdf")])).is_err());
+        assert!(ExportQuery::from_query_params(&query_params(&[("until", "yesterday")])).is_err());
+    }
+
+    #[test]
+    fn test_export_query_includes() {
+        let timestamp = message("", false).timestamp;
+        let query = ExportQuery { format: ExportFormat::Csv, since: Some(timestamp), until: Some(timestamp + Duration::from_secs(60)) };
+        assert!(query.includes(&message("", false)));
+        assert!(!ExportQuery { since: Some(timestamp + Duration::from_secs(1)), ..query }.includes(&message("", false)));
+        assert!(!ExportQuery { until: Some(timestamp), ..query }.includes(&message("", false)));
+    }
+
+    #[test]
+    fn test_render_csv() {
+        assert_eq!(render_header(ExportFormat::Csv, "general"), "seq,timestamp,author,is_bot,content\n");
+        assert_eq!(render_message(ExportFormat::Csv, &message("Hello", false)), "7,2023-11-14T22:13:20Z,alice,false,Hello\n");
+        assert_eq!(render_message(ExportFormat::Csv, &message("Hello, \"world\"\nbye", true)), "7,2023-11-14T22:13:20Z,alice,true,\"Hello, \"\"world\"\"\nbye\"\n");
+        assert_eq!(render_message(ExportFormat::Csv, &message("=HYPERLINK(\"http://evil\")", false)), "7,2023-11-14T22:13:20Z,alice,false,\"'=HYPERLINK(\"\"http://evil\"\")\"\n");
+        for formula in ["+1", "-1", "@SUM(A1)", "\t=1"] {
+            assert_eq!(render_message(ExportFormat::Csv, &message(formula, false)), format!("7,2023-11-14T22:13:20Z,alice,false,'{}\n", formula));
+        }
+    }
+
+    #[test]
+    fn test_render_json_lines() {
+        let line = render_message(ExportFormat::JsonLines, &message("Hello\nworld", false));
+        assert_eq!(line.matches('\n').count(), 1);
+        assert_eq!(serde_json::from_str::<MessageView>(&line).unwrap(), message("Hello\nworld", false));
+    }
+
+    #[test]
+    fn test_render_markdown() {
+        assert_eq!(render_header(ExportFormat::Markdown, "general"), "# general\n\n");
+        assert_eq!(render_message(ExportFormat::Markdown, &message("*Hello*\nworld", true)), "- **alice** (bot) (2023-11-14T22:13:20Z): \\*Hello\\*  \n  world\n");
+    }
+
+    #[test]
+    fn test_render_html() {
+        let html = render_header(ExportFormat::Html, "<general>") + &render_message(ExportFormat::Html, &message("<b>Hello</b> & 'bye'", false)) + &render_footer(ExportFormat::Html);
+        assert!(html.starts_with("<!DOCTYPE html>"));
+        assert!(html.contains("<title>&lt;general&gt;</title>"));
+        assert!(html.contains("<li><time datetime=\"2023-11-14T22:13:20Z\">2023-11-14T22:13:20Z</time> <strong>alice</strong>: &lt;b&gt;Hello&lt;/b&gt; &amp; &#39;bye&#39;</li>"));
+        assert!(html.ends_with("</html>\n"));
+    }
+}
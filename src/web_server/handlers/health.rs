@@ -0,0 +1,149 @@
+use crate::entities::server::SharedServer;
+use crate::web_server::routes::API_VERSION;
+use serde::{Deserialize, Serialize};
+use std::sync::{LazyLock, TryLockError};
+use std::time::{Duration, Instant};
+use warp::http::StatusCode;
+use std::convert::Infallible;
+
+pub const SERVER_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// Waiting longer than this for the server state is reported as a degraded lock.
+const LOCK_CONTENTION_THRESHOLD: Duration = Duration::from_millis(100);
+
+/// The server is reported as not ready if its state cannot be read within this time, rather than waiting for it.
+const LOCK_TIMEOUT: Duration = Duration::from_millis(500);
+
+const LOCK_RETRY_INTERVAL: Duration = Duration::from_millis(5);
+
+static STARTED_AT: LazyLock<Instant> = LazyLock::new(Instant::now);
+
+/// Starts counting the uptime, to be called as the server starts.
+pub fn mark_server_started() {
+    LazyLock::force(&STARTED_AT);
+}
+
+fn uptime_seconds() -> u64 {
+    STARTED_AT.elapsed().as_secs()
+}
+
+/// Whether a client can talk to a server, following semantic versioning:
+/// the major versions must match, as well as the minor versions while still in 0.x.
+pub fn is_version_compatible(client_version: &str, server_version: &str) -> bool {
+    let major_minor = |version: &str| -> Option<(u64, u64)> {
+        let mut parts = version.split('.').map(|part| part.parse::<u64>().ok());
+        Some((parts.next()??, parts.next()??))
+    };
+
+    match (major_minor(client_version), major_minor(server_version)) {
+        (Some((0, client_minor)), Some((0, server_minor))) => client_minor == server_minor,
+        (Some((client_major, _)), Some((server_major, _))) => client_major == server_major,
+        _ => false
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct LivenessResponse {
+    pub status: String,
+    pub version: String,
+    pub uptime_seconds: u64,
+    /// Versions of the REST API served, none for the servers predating the versioned paths
+    #[serde(default)]
+    pub api_versions: Vec<String>
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ReadinessCheck {
+    pub status: String,
+    #[serde(flatten)]
+    pub details: serde_json::Value
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ReadinessResponse {
+    pub status: String,
+    pub version: String,
+    pub uptime_seconds: u64,
+    pub storage: ReadinessCheck,
+    pub lock: ReadinessCheck
+}
+
+/// The process is up and answering: the server state is not touched on purpose,
+/// so that a stuck lock does not get the server restarted while it may still recover.
+pub async fn get_liveness() -> Result<impl warp::Reply, Infallible> {
+    let liveness = LivenessResponse {
+        status: "OK__LIVE".to_string(),
+        version: SERVER_VERSION.to_string(),
+        uptime_seconds: uptime_seconds(),
+        api_versions: vec![API_VERSION.to_string()]
+    };
+    Ok(warp::reply::with_status(warp::reply::json(&liveness), StatusCode::OK))
+}
+
+/// The server can serve requests: the in-memory storage is usable and the server is not shutting down.
+/// The time to acquire the storage is reported so that contention shows up before requests start timing out.
+/// The lock is polled rather than waited for, so that a stuck writer neither hangs the probe nor blocks a worker thread.
+pub async fn get_readiness(server: SharedServer) -> Result<impl warp::Reply, Infallible> {
+    let lock_started_at = Instant::now();
+    let mut contended = false;
+    let server = loop {
+        match server.try_read() {
+            Err(TryLockError::WouldBlock) if lock_started_at.elapsed() < LOCK_TIMEOUT => contended = true,
+            result => break result
+        }
+        tokio::time::sleep(LOCK_RETRY_INTERVAL).await;
+    };
+    let lock_wait = lock_started_at.elapsed();
+
+    let storage = match &server {
+        Ok(server) => ReadinessCheck {
+            status: "up".to_string(),
+            details: serde_json::json!({"users": server.users.len(), "rooms": server.rooms.len()})
+        },
+        Err(TryLockError::WouldBlock) => ReadinessCheck {
+            status: "down".to_string(),
+            details: serde_json::json!({"reason": format!("server state lock not acquired within {} ms", LOCK_TIMEOUT.as_millis())})
+        },
+        Err(TryLockError::Poisoned(_)) => ReadinessCheck {
+            status: "down".to_string(),
+            details: serde_json::json!({"reason": "server state lock is poisoned"})
+        }
+    };
+    let lock_status = if lock_wait > LOCK_CONTENTION_THRESHOLD { "degraded" } else { "up" };
+    let lock = ReadinessCheck {
+        status: lock_status.to_string(),
+        details: serde_json::json!({"contended": contended, "wait_ms": lock_wait.as_secs_f64() * 1000.0})
+    };
+
+    let is_shutting_down = server.as_ref().is_ok_and(|server| server.is_shutting_down());
+    let is_ready = server.is_ok() && !is_shutting_down;
+    drop(server);
+    let status = match (is_ready, is_shutting_down) {
+        (true, _) => "OK__READY",
+        (false, true) => "ERR__SHUTTING_DOWN",
+        (false, false) => "ERR__NOT_READY"
+    };
+    let readiness = ReadinessResponse {
+        status: status.to_string(),
+        version: SERVER_VERSION.to_string(),
+        uptime_seconds: uptime_seconds(),
+        storage,
+        lock
+    };
+    let status_code = if is_ready { StatusCode::OK } else { StatusCode::SERVICE_UNAVAILABLE };
+    Ok(warp::reply::with_status(warp::reply::json(&readiness), status_code))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_version_compatible() {
+        assert!(is_version_compatible("0.1.0", "0.1.7"));
+        assert!(!is_version_compatible("0.1.0", "0.2.0"));
+        assert!(is_version_compatible("1.2.0", "1.0.3"));
+        assert!(!is_version_compatible("1.2.0", "2.0.0"));
+        assert!(!is_version_compatible("1.2.0", "unknown"));
+    }
+}
@@ -0,0 +1,41 @@
+use crate::entities::{error::ServerError, server::SharedServer};
+use super::{error_reply, views::MessageView};
+use serde::{Deserialize, Serialize};
+use warp::http::StatusCode;
+use std::convert::Infallible;
+
+/// Body accepted by incoming webhooks.
+/// Besides the native message field, the Slack-compatible text field is accepted so that
+/// existing Slack integrations can be pointed to the server without changes.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct IncomingWebhookMessageRequest {
+    pub message: Option<String>,
+    pub text: Option<String>
+}
+
+pub async fn post_incoming_webhook_message(token: String, body: IncomingWebhookMessageRequest, server: SharedServer) -> Result<impl warp::Reply, Infallible> {
+    let message = body.message.or(body.text).filter(|message| !message.trim().is_empty());
+    let Some(message) = message else {
+        return Ok(error_reply(ServerError::BadRequest("Missing message or text in request body".to_string())));
+    };
+
+    let server = server.read().unwrap();
+    match server.post_incoming_webhook_message(&token, &message) {
+        Ok(message) => {
+            let json_response = warp::reply::json(&MessageView::from(message.as_ref()));
+            Ok(warp::reply::with_status(json_response, StatusCode::CREATED))
+        },
+        Err(error) => Ok(error_reply(error))
+    }
+}
+
+pub async fn revoke_incoming_webhook(token: String, server: SharedServer) -> Result<impl warp::Reply, Infallible> {
+    let mut server = server.write().unwrap();
+    match server.revoke_incoming_webhook(&token) {
+        Ok(_) => {
+            let json_response = warp::reply::json(&serde_json::json!({"status": "OK__INCOMING_WEBHOOK_REVOKED"}));
+            Ok(warp::reply::with_status(json_response, StatusCode::OK))
+        },
+        Err(error) => Ok(error_reply(error))
+    }
+}
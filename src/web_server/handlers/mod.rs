@@ -1,27 +1,175 @@
 pub mod users;
 pub mod rooms;
+pub mod hooks;
+pub mod health;
+pub mod web_client;
+pub mod views;
 
 use serde::{Serialize, Deserialize};
 use warp::http::StatusCode;
+use warp::reply::{Json, WithStatus};
 use std::convert::Infallible;
-use std::sync::{Arc, Mutex};
-use crate::entities::server::Server;
+use std::fmt;
+use crate::entities::{error::ServerError, server::SharedServer};
+use super::metrics::Metrics;
+use std::sync::Arc;
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct ErrorDetailsResponse {
     pub error_id: String,
-    pub error_message: String
+    pub error_message: String,
+    /// Id of the request that failed, to be quoted when reporting an issue.
+    /// Set by the server on every error response, absent on errors raised by the client itself.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub request_id: Option<String>
 }
 
-pub async fn is_server_reachable(server: Arc<Mutex<Server>>) -> Result<impl warp::Reply, Infallible> {
-    let server = server.lock();
+impl fmt::Display for ErrorDetailsResponse {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.request_id {
+            Some(request_id) => write!(f, "{} (request id: {})", self.error_message, request_id),
+            None => write!(f, "{}", self.error_message)
+        }
+    }
+}
+
+impl From<ServerError> for ErrorDetailsResponse {
+    fn from(error: ServerError) -> Self {
+        ErrorDetailsResponse {
+            error_id: error.error_id().to_string(),
+            error_message: error.to_string(),
+            request_id: None
+        }
+    }
+}
+
+/// HTTP status answered for each server error, the same whatever the route.
+pub fn error_status_code(error: &ServerError) -> StatusCode {
+    match error {
+        ServerError::BadRequest(_)
+        | ServerError::WebhookUrlInvalid(_)
+        | ServerError::BotNameInvalid(_)
+        | ServerError::MessageTooLong(_) => StatusCode::BAD_REQUEST,
+        ServerError::UserNotFound(_)
+        | ServerError::UserIdNotFound(_)
+        | ServerError::RoomNotFound(_)
+        | ServerError::RoomIdNotFound(_)
+        | ServerError::IncomingWebhookNotFound
+        | ServerError::RouteNotFound => StatusCode::NOT_FOUND,
+        ServerError::MethodNotAllowed => StatusCode::METHOD_NOT_ALLOWED,
+        ServerError::LengthRequired => StatusCode::LENGTH_REQUIRED,
+        ServerError::PayloadTooLarge => StatusCode::PAYLOAD_TOO_LARGE,
+        ServerError::UserAlreadyExists(_)
+        | ServerError::RoomAlreadyExists(_)
+        | ServerError::UserAlreadyInRoom { .. }
+        | ServerError::WebhookAlreadyRegistered { .. }
+        | ServerError::IdempotencyKeyReused(_) => StatusCode::CONFLICT,
+        ServerError::UserNotInRoom { .. }
+        | ServerError::OriginNotAllowed(_) => StatusCode::FORBIDDEN,
+        ServerError::AuthorNotBot(_)
+        | ServerError::Internal(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        ServerError::Unavailable => StatusCode::SERVICE_UNAVAILABLE
+    }
+}
+
+pub fn error_reply(error: ServerError) -> WithStatus<Json> {
+    let status_code = error_status_code(&error);
+    warp::reply::with_status(warp::reply::json(&ErrorDetailsResponse::from(error)), status_code)
+}
+
+/// Reads a JSON request body into its typed request, answering 400 BAD REQUEST with the missing or invalid fields otherwise.
+pub fn parse_request_body<T: serde::de::DeserializeOwned>(body: serde_json::Value) -> Result<T, ServerError> {
+    serde_json::from_value(body).map_err(|error| ServerError::BadRequest(format!("Invalid request body: {}", error)))
+}
+
+/// Answers the requests rejected by every route with the same error details as the handlers,
+/// so that clients always get an `ErrorDetailsResponse` back. The details of unexpected rejections are only logged.
+pub async fn handle_rejection(rejection: warp::Rejection) -> Result<impl warp::Reply, Infallible> {
+    let error = if rejection.is_not_found() {
+        ServerError::RouteNotFound
+    } else if rejection.find::<warp::reject::MethodNotAllowed>().is_some() {
+        ServerError::MethodNotAllowed
+    } else if let Some(body_error) = rejection.find::<warp::filters::body::BodyDeserializeError>() {
+        ServerError::BadRequest(body_error.to_string())
+    } else if let Some(query_error) = rejection.find::<warp::reject::InvalidQuery>() {
+        ServerError::BadRequest(query_error.to_string())
+    } else if let Some(media_type_error) = rejection.find::<warp::reject::UnsupportedMediaType>() {
+        ServerError::BadRequest(media_type_error.to_string())
+    } else if let Some(header_error) = rejection.find::<warp::reject::MissingHeader>() {
+        ServerError::BadRequest(format!("Missing request header {}", header_error.name()))
+    } else if let Some(header_error) = rejection.find::<warp::reject::InvalidHeader>() {
+        ServerError::BadRequest(format!("Invalid request header {}", header_error.name()))
+    } else if rejection.find::<warp::reject::LengthRequired>().is_some() {
+        ServerError::LengthRequired
+    } else if rejection.find::<warp::reject::PayloadTooLarge>().is_some() {
+        ServerError::PayloadTooLarge
+    } else if let Some(cors_error) = rejection.find::<warp::filters::cors::CorsForbidden>() {
+        ServerError::OriginNotAllowed(cors_error.to_string())
+    } else {
+        tracing::error!(?rejection, "unexpected rejection");
+        ServerError::Internal("the request could not be handled".to_string())
+    };
+
+    Ok(error_reply(error))
+}
+
+pub async fn is_server_reachable(server: SharedServer) -> Result<impl warp::Reply, Infallible> {
+    let server = server.read();
     if server.is_err() {
-        let error_object = &ErrorDetailsResponse {
-            error_id: "SERVER_UNREACHABLE".to_string(),
-            error_message: "The server is unreachable".to_string()
-        };
-        return Ok(warp::reply::with_status(warp::reply::json(&serde_json::json!(error_object)), StatusCode::INTERNAL_SERVER_ERROR));
+        return Ok(error_reply(ServerError::Unavailable));
     }
 
     Ok(warp::reply::with_status(warp::reply::json(&serde_json::json!({"status": "OK__SERVER_REACHABLE"})), StatusCode::OK))
-}
\ No newline at end of file
+}
+
+pub async fn get_openapi_document() -> Result<impl warp::Reply, Infallible> {
+    Ok(warp::reply::with_status(warp::reply::json(&super::openapi::openapi_document()), StatusCode::OK))
+}
+
+pub async fn get_metrics(metrics: Arc<Metrics>, server: SharedServer) -> Result<impl warp::Reply, Infallible> {
+    let server = server.read().unwrap();
+    let metrics_text = metrics.render(&server);
+    Ok(warp::reply::with_header(metrics_text, "Content-Type", "text/plain; version=0.0.4"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_error_details_response_from_server_error() {
+        let error_details = ErrorDetailsResponse::from(ServerError::RoomNotFound("test_room".to_string()));
+        assert_eq!(error_details.error_id, "ERR__ROOM_NOT_FOUND");
+        assert_eq!(error_details.error_message, "Room with name test_room not found in server");
+    }
+
+    #[test]
+    fn test_error_status_code() {
+        assert_eq!(error_status_code(&ServerError::RoomNotFound("test_room".to_string())), StatusCode::NOT_FOUND);
+        assert_eq!(error_status_code(&ServerError::UserAlreadyExists("test_user".to_string())), StatusCode::CONFLICT);
+        assert_eq!(error_status_code(&ServerError::BadRequest("Missing field".to_string())), StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn test_handle_rejection_client_errors() {
+        use warp::Filter;
+        let routes = warp::header::<String>("idempotency-key")
+            .and(warp::body::content_length_limit(16))
+            .and(warp::body::bytes())
+            .map(|_, _| warp::reply())
+            .recover(handle_rejection);
+        let request = || warp::test::request().method("POST").path("/");
+
+        let cases = [
+            (request().body("{}"), StatusCode::BAD_REQUEST, "Missing request header idempotency-key"),
+            (request().header("idempotency-key", warp::http::HeaderValue::from_bytes(b"\xff").unwrap()).body("{}"), StatusCode::BAD_REQUEST, "Invalid request header idempotency-key"),
+            (request().header("idempotency-key", "key").body("{\"message\": \"too long\"}"), StatusCode::PAYLOAD_TOO_LARGE, "The request body is too large")
+        ];
+        for (request, status, error_message) in cases {
+            let response = request.reply(&routes).await;
+            assert_eq!(response.status(), status);
+            let error_details: ErrorDetailsResponse = serde_json::from_slice(response.body()).unwrap();
+            assert_eq!(error_details.error_message, error_message);
+        }
+    }
+}
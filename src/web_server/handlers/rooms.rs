@@ -1,175 +1,297 @@
-use crate::entities::server::Server;
-use super::ErrorDetailsResponse;
-use std::{collections::HashMap, sync::{Arc, Mutex}};
-use warp::http::StatusCode;
+use crate::entities::{error::ServerError, server::{PostedMessage, Server, SharedServer}};
+use super::{error_reply, parse_request_body, views::{MessageView, RoomSummary, UserView}, ErrorDetailsResponse};
+use crate::web_server::export::{self, ExportQuery};
+use crate::web_server::logging::record_user;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use warp::http::{header, Response, StatusCode};
+use warp::hyper::Body;
+use warp::Reply;
 use std::convert::Infallible;
+use uuid::Uuid;
 
-pub async fn get_room_by_name(room_name: String, server: Arc<Mutex<Server>>) -> Result<impl warp::Reply, Infallible> {
-    let server = server.lock().unwrap();
-    let room = server.get_room_by_name(&room_name);
-    match room {
-        Some(room_arc) => {
-            let room = room_arc.lock().unwrap();
-            let room_summary = serde_json::json!({
-                "id": room.id,
-                "name": room.name,
-                "users": room.users
-            });
-            let json_response = warp::reply::json(&room_summary);
-            Ok(warp::reply::with_status(json_response, StatusCode::OK))
-        }
-        None => {
-            let json_response = warp::reply::json(&ErrorDetailsResponse {
-                error_id: "ERR__ROOM_NOT_FOUND".to_string(),
-                error_message: format!("Room with name {} not found in server", room_name)
-            });
-            Ok(warp::reply::with_status(json_response, StatusCode::NOT_FOUND))
-        }
+const DEFAULT_INCOMING_WEBHOOK_BOT_NAME: &str = "incoming-webhook";
+
+/// Size from which the rendered messages of an export are sent to the client.
+const EXPORT_CHUNK_SIZE: usize = 64 * 1024;
+
+/// Longest `Idempotency-Key` header accepted, in bytes.
+pub const IDEMPOTENCY_KEY_MAX_LENGTH: usize = 255;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PostMessageRequest {
+    pub username: String,
+    pub message: String,
+    /// Id generated by the client, so that sending the message again does not post it twice, also accepted as the `Idempotency-Key` header
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub client_message_id: Option<Uuid>
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RegisterWebhookRequest {
+    pub url: String,
+    /// Key of the HMAC-SHA256 signature sent with every delivery, generated if missing
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub secret: Option<String>
+}
+
+/// Outgoing webhook as answered on registration, the only time its secret is disclosed.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RegisteredWebhook {
+    pub id: Uuid,
+    pub url: String,
+    pub secret: String
+}
+
+fn room_summary_reply(server: &Server, room_name: &str, status_code: StatusCode) -> warp::reply::WithStatus<warp::reply::Json> {
+    let room = server.get_room_by_name(room_name).unwrap();
+    let room = room.read().unwrap();
+    warp::reply::with_status(warp::reply::json(&RoomSummary::from(&*room)), status_code)
+}
+
+pub async fn get_rooms(server: SharedServer) -> Result<impl warp::Reply, Infallible> {
+    let server = server.read().unwrap();
+    let rooms_summaries: Vec<RoomSummary> = server.get_rooms().iter()
+        .map(|room_arc| RoomSummary::from(&*room_arc.read().unwrap()))
+        .collect();
+    let json_response = warp::reply::json(&rooms_summaries);
+    Ok(warp::reply::with_status(json_response, StatusCode::OK))
+}
+
+pub async fn get_room_by_name(room_name: String, server: SharedServer) -> Result<impl warp::Reply, Infallible> {
+    let server = server.read().unwrap();
+    if server.get_room_by_name(&room_name).is_none() {
+        return Ok(error_reply(ServerError::RoomNotFound(room_name)));
     }
+
+    Ok(room_summary_reply(&server, &room_name, StatusCode::OK))
 }
 
-pub async fn get_user_in_room_by_username(room_name: String, username: String, server: Arc<Mutex<Server>>) -> Result<impl warp::Reply, Infallible> {
-    let server = server.lock().unwrap();
-    let room = server.get_room_by_name(&room_name);
-    match room {
+pub async fn get_room_by_id(room_id: Uuid, server: SharedServer) -> Result<impl warp::Reply, Infallible> {
+    let server = server.read().unwrap();
+    match server.get_room_by_id(&room_id) {
         Some(room_arc) => {
-            match server.get_user_by_username(&username) {
-                Some(user_arc) => {
-                    let user = user_arc.clone();
-                    let room = room_arc.lock().unwrap();
-                    match room.is_user_in_room(user.clone()) {
-                        true => {
-                            let json_response = warp::reply::json(&*user);
-                            Ok(warp::reply::with_status(json_response, StatusCode::OK))
-                        },
-                        false => {
-                            let json_response = warp::reply::json(&ErrorDetailsResponse {
-                                error_id: "ERR__USER_NOT_IN_ROOM".to_string(),
-                                error_message: format!("User with username {} is not in room with name {}", username, room_name)
-                            });
-                            Ok(warp::reply::with_status(json_response, StatusCode::NOT_FOUND))
-                        }
-                    }
-                }
-                None => {
-                    let json_response = warp::reply::json(&ErrorDetailsResponse {
-                        error_id: "ERR__USER_NOT_FOUND".to_string(),
-                        error_message: format!("User with username {} not found in server", username)
-                    });
-                    Ok(warp::reply::with_status(json_response, StatusCode::NOT_FOUND))
-                }
-            }
-        }
-        None => {
-            let json_response = warp::reply::json(&ErrorDetailsResponse {
-                error_id: "ERR__ROOM_NOT_FOUND".to_string(),
-                error_message: format!("Room with name {} not found in server", room_name)
-            });
-            Ok(warp::reply::with_status(json_response, StatusCode::NOT_FOUND))
-        }
+            let json_response = warp::reply::json(&RoomSummary::from(&*room_arc.read().unwrap()));
+            Ok(warp::reply::with_status(json_response, StatusCode::OK))
+        },
+        None => Ok(error_reply(ServerError::RoomIdNotFound(room_id)))
     }
 }
 
-pub async fn create_room(room_name: String, query_params: HashMap<String, String>, server: Arc<Mutex<Server>>) -> Result<impl warp::Reply, Infallible> {
-    let creator_username = query_params.get("creator_username");
-    if creator_username.is_none() {
-        let json_response = warp::reply::json(&ErrorDetailsResponse {
-            error_id: "ERR__ROOM_CREATE_BAD_REQUEST".to_string(),
-            error_message: "Missing creator_username query parameter".to_string()
-        });
-        return Ok(warp::reply::with_status(json_response, StatusCode::BAD_REQUEST));
+pub async fn get_user_in_room_by_username(room_name: String, username: String, server: SharedServer) -> Result<impl warp::Reply, Infallible> {
+    record_user(&username);
+    let server = server.read().unwrap();
+    let Some(room_arc) = server.get_room_by_name(&room_name) else {
+        return Ok(error_reply(ServerError::RoomNotFound(room_name)));
+    };
+    let Some(user) = server.get_user_by_username(&username) else {
+        return Ok(error_reply(ServerError::UserNotFound(username)));
+    };
+
+    let room = room_arc.read().unwrap();
+    if !room.is_user_in_room(user.clone()) {
+        // A membership lookup, where a user absent from the room is not found rather than forbidden as when acting on it
+        let error_response = ErrorDetailsResponse::from(ServerError::UserNotInRoom { username, room_name });
+        return Ok(warp::reply::with_status(warp::reply::json(&error_response), StatusCode::NOT_FOUND));
     }
-    let creator_username = creator_username.unwrap();
-
-    let mut server = server.lock().unwrap();
-    match server.create_room(&room_name, &creator_username) {
-        Ok(_) => {
-            let room = server.get_room_by_name(&room_name).unwrap();
-            let room = room.lock().unwrap();
-            let room_summary = serde_json::json!({
-                "id": room.id,
-                "name": room.name,
-                "users": room.users
-            });
-            let json_response = warp::reply::json(&room_summary);
-            Ok(warp::reply::with_status(json_response, StatusCode::CREATED))
-        },
-        Err(err_message) => {
-            let json_response = warp::reply::json(&ErrorDetailsResponse {
-                error_id: "ERR__ROOM_CREATE_CONFLICT".to_string(),
-                error_message: format!("Cannot create room {}: {}", creator_username, err_message)
-            });
-            Ok(warp::reply::with_status(json_response, StatusCode::CONFLICT))
-        }
+
+    let json_response = warp::reply::json(&UserView::from(user.as_ref()));
+    Ok(warp::reply::with_status(json_response, StatusCode::OK))
+}
+
+pub async fn create_room(room_name: String, query_params: HashMap<String, String>, server: SharedServer) -> Result<impl warp::Reply, Infallible> {
+    let Some(creator_username) = query_params.get("creator_username") else {
+        return Ok(error_reply(ServerError::BadRequest("Missing creator_username query parameter".to_string())));
+    };
+    record_user(creator_username);
+
+    let mut server = server.write().unwrap();
+    match server.create_room(&room_name, creator_username) {
+        Ok(_) => Ok(room_summary_reply(&server, &room_name, StatusCode::CREATED)),
+        Err(error) => Ok(error_reply(error))
     }
 }
 
-pub async fn add_user_to_room(room_name: String, username: String, server: Arc<Mutex<Server>>) -> Result<impl warp::Reply, Infallible> {
-    let mut server = server.lock().unwrap();
+pub async fn add_user_to_room(room_name: String, username: String, server: SharedServer) -> Result<impl warp::Reply, Infallible> {
+    record_user(&username);
+    let server = server.read().unwrap();
     match server.add_user_to_room(&room_name, &username) {
-        Ok(_) => {
-            let room = server.get_room_by_name(&room_name).unwrap();
-            let room = room.lock().unwrap();
-            let room_summary = serde_json::json!({
-                "id": room.id,
-                "name": room.name,
-                "users": room.users
-            });
-            let json_response = warp::reply::json(&room_summary);
-            Ok(warp::reply::with_status(json_response, StatusCode::CREATED))
-        },
-        Err(err_message) => {
-            let json_response = warp::reply::json(&ErrorDetailsResponse {
-                error_id: "ERR__USER_ADD_TO_ROOM_CONFLICT".to_string(),
-                error_message: format!("Cannot add user {} to room {}: {}", username, room_name, err_message)
-            });
-            Ok(warp::reply::with_status(json_response, StatusCode::CONFLICT))
-        }
+        Ok(_) => Ok(room_summary_reply(&server, &room_name, StatusCode::CREATED)),
+        Err(error) => Ok(error_reply(error))
+    }
+}
+
+pub async fn remove_user_from_room(room_name: String, username: String, server: SharedServer) -> Result<impl warp::Reply, Infallible> {
+    record_user(&username);
+    let server = server.read().unwrap();
+    match server.remove_user_from_room(&room_name, &username) {
+        Ok(_) => Ok(room_summary_reply(&server, &room_name, StatusCode::OK)),
+        Err(error) => Ok(error_reply(error))
     }
 }
 
-pub async fn get_room_messages(room_name: String, server: Arc<Mutex<Server>>) -> Result<impl warp::Reply, Infallible> {
-    let server = server.lock().unwrap();
-    match server.get_room_messages(&room_name) {
+pub async fn get_room_messages(room_name: String, query_params: HashMap<String, String>, server: SharedServer) -> Result<impl warp::Reply, Infallible> {
+    let after_seq = match query_params.get("after_seq").map(|after_seq| after_seq.parse::<u64>()) {
+        None => 0,
+        Some(Ok(after_seq)) => after_seq,
+        Some(Err(_)) => return Ok(error_reply(ServerError::BadRequest("Invalid after_seq query parameter, expected a sequence number".to_string())))
+    };
+
+    let server = server.read().unwrap();
+    match server.get_room_messages_after(&room_name, after_seq) {
         Ok(messages) => {
+            let messages: Vec<MessageView> = messages.iter().map(|message| MessageView::from(message.as_ref())).collect();
             let json_response = warp::reply::json(&messages);
             Ok(warp::reply::with_status(json_response, StatusCode::OK))
         },
-        Err(err_message) => {
-            let json_response = warp::reply::json(&ErrorDetailsResponse {
-                error_id: "ERR__ROOM_MESSAGES_CONFLICT".to_string(),
-                error_message: format!("Cannot get messages for room {}: {}", room_name, err_message)
-            });
-            Ok(warp::reply::with_status(json_response, StatusCode::CONFLICT))
+        Err(error) => Ok(error_reply(error))
+    }
+}
+
+/// Client message id of an `Idempotency-Key` header: the UUID it holds, or for any other key a UUID derived from its SHA-256 hash,
+/// so that opaque keys share the per-room key space of the `client_message_id` field.
+fn idempotency_key_message_id(idempotency_key: &str) -> Result<Uuid, ServerError> {
+    let idempotency_key = idempotency_key.trim();
+    if idempotency_key.is_empty() || idempotency_key.len() > IDEMPOTENCY_KEY_MAX_LENGTH {
+        return Err(ServerError::BadRequest(format!("Invalid Idempotency-Key header, expected 1 to {} bytes", IDEMPOTENCY_KEY_MAX_LENGTH)));
+    }
+    if let Ok(client_message_id) = idempotency_key.parse() {
+        return Ok(client_message_id);
+    }
+    let hash = Sha256::digest(idempotency_key.as_bytes());
+    Ok(Uuid::from_bytes(hash[..16].try_into().expect("SHA-256 hashes are 32 bytes")))
+}
+
+/// Reads the client message id of a post from the `Idempotency-Key` header or the request body, which must agree if both are given.
+fn client_message_id(idempotency_key: Option<String>, body: &PostMessageRequest) -> Result<Option<Uuid>, ServerError> {
+    let Some(idempotency_key) = idempotency_key else {
+        return Ok(body.client_message_id);
+    };
+    let idempotency_key = idempotency_key_message_id(&idempotency_key)?;
+    match body.client_message_id {
+        Some(client_message_id) if client_message_id != idempotency_key => {
+            Err(ServerError::BadRequest("The Idempotency-Key header and the client_message_id field differ".to_string()))
+        },
+        _ => Ok(Some(idempotency_key))
+    }
+}
+
+pub async fn post_message_to_room(room_name: String, idempotency_key: Option<String>, body: serde_json::Value, server: SharedServer) -> Result<impl warp::Reply, Infallible> {
+    let body: PostMessageRequest = match parse_request_body(body) {
+        Ok(body) => body,
+        Err(error) => return Ok(error_reply(error))
+    };
+    record_user(&body.username);
+    let client_message_id = match client_message_id(idempotency_key, &body) {
+        Ok(client_message_id) => client_message_id,
+        Err(error) => return Ok(error_reply(error))
+    };
+
+    let server = server.read().unwrap();
+    match server.post_client_message_to_room(&room_name, &body.username, &body.message, client_message_id) {
+        Ok(posted_message) => {
+            let status_code = match posted_message {
+                PostedMessage::New(_) => StatusCode::CREATED,
+                PostedMessage::Replayed(_) => StatusCode::OK
+            };
+            let json_response = warp::reply::json(&MessageView::from(posted_message.into_message().as_ref()));
+            Ok(warp::reply::with_status(json_response, status_code))
+        },
+        Err(error) => Ok(error_reply(error))
+    }
+}
+
+/// Streams the messages of the room in the export format, rendered as they are sent:
+/// only the list of the messages is copied, not their content, and the room is not locked meanwhile.
+pub async fn export_room_messages(room_name: String, query_params: HashMap<String, String>, server: SharedServer) -> Result<warp::reply::Response, Infallible> {
+    let query = match ExportQuery::from_query_params(&query_params) {
+        Ok(query) => query,
+        Err(error) => return Ok(error_reply(error).into_response())
+    };
+    let messages = match server.read().unwrap().get_room_messages(&room_name) {
+        Ok(messages) => messages,
+        Err(error) => return Ok(error_reply(error).into_response())
+    };
+
+    let (mut sender, body) = Body::channel();
+    let file_name: String = room_name.chars().map(|character| if character.is_alphanumeric() || character == '-' { character } else { '_' }).collect();
+    tokio::spawn(async move {
+        let mut chunk = export::render_header(query.format, &room_name);
+        for message in messages {
+            let message = MessageView::from(message.as_ref());
+            if !query.includes(&message) {
+                continue;
+            }
+            chunk.push_str(&export::render_message(query.format, &message));
+            // Stops rendering if the client went away
+            if chunk.len() >= EXPORT_CHUNK_SIZE && sender.send_data(std::mem::take(&mut chunk).into()).await.is_err() {
+                return;
+            }
         }
+        chunk.push_str(&export::render_footer(query.format));
+        let _ = sender.send_data(chunk.into()).await;
+    });
+
+    Ok(Response::builder()
+        .status(StatusCode::OK)
+        .header(header::CONTENT_TYPE, query.format.content_type())
+        .header(header::CONTENT_DISPOSITION, format!("attachment; filename=\"{}.{}\"", file_name, query.format.file_extension()))
+        .body(body)
+        .unwrap())
+}
+
+pub async fn register_webhook_to_room(room_name: String, body: serde_json::Value, server: SharedServer) -> Result<impl warp::Reply, Infallible> {
+    let body: RegisterWebhookRequest = match parse_request_body(body) {
+        Ok(body) => body,
+        Err(error) => return Ok(error_reply(error))
+    };
+    let server = server.read().unwrap();
+    match server.register_webhook_to_room(&room_name, &body.url, body.secret.as_deref()) {
+        Ok(webhook) => {
+            let registered_webhook = RegisteredWebhook {
+                id: webhook.id,
+                url: webhook.url.clone(),
+                secret: webhook.secret.clone()
+            };
+            let json_response = warp::reply::json(&registered_webhook);
+            Ok(warp::reply::with_status(json_response, StatusCode::CREATED))
+        },
+        Err(error) => Ok(error_reply(error))
+    }
+}
+
+pub async fn get_room_webhooks(room_name: String, server: SharedServer) -> Result<impl warp::Reply, Infallible> {
+    let server = server.read().unwrap();
+    match server.get_room_webhooks(&room_name) {
+        Ok(webhooks) => {
+            let json_response = warp::reply::json(&webhooks);
+            Ok(warp::reply::with_status(json_response, StatusCode::OK))
+        },
+        Err(error) => Ok(error_reply(error))
     }
 }
 
-pub async fn post_message_to_room(room_name: String, body: HashMap<String, String>, server: Arc<Mutex<Server>>) -> Result<impl warp::Reply, Infallible> {
-    let username = body.get("username");
-    let message = body.get("message");
-    if username.is_none() || message.is_none() {
-        let json_response = warp::reply::json(&ErrorDetailsResponse {
-            error_id: "ERR__MESSAGE_POST_TO_ROOM_BAD_REQUEST".to_string(),
-            error_message: "Missing username or message in request body".to_string()
-        });
-        return Ok(warp::reply::with_status(json_response, StatusCode::BAD_REQUEST));
+pub async fn get_room_webhook_deliveries(room_name: String, server: SharedServer) -> Result<impl warp::Reply, Infallible> {
+    let server = server.read().unwrap();
+    match server.get_room_webhook_deliveries(&room_name) {
+        Ok(deliveries) => {
+            let json_response = warp::reply::json(&deliveries);
+            Ok(warp::reply::with_status(json_response, StatusCode::OK))
+        },
+        Err(error) => Ok(error_reply(error))
     }
-    let username = username.unwrap();
-    let message = message.unwrap();
+}
+
+pub async fn create_incoming_webhook(room_name: String, query_params: HashMap<String, String>, server: SharedServer) -> Result<impl warp::Reply, Infallible> {
+    let bot_name = query_params.get("bot_name").map(|bot_name| bot_name.as_str()).unwrap_or(DEFAULT_INCOMING_WEBHOOK_BOT_NAME);
 
-    let mut server = server.lock().unwrap();
-    match server.post_message_to_room(&room_name, &username, &message) {
-        Ok(message) => {
-            let json_response = warp::reply::json(&message);
+    let mut server = server.write().unwrap();
+    match server.create_incoming_webhook(&room_name, bot_name) {
+        Ok(incoming_webhook) => {
+            let json_response = warp::reply::json(&*incoming_webhook);
             Ok(warp::reply::with_status(json_response, StatusCode::CREATED))
         },
-        Err(err_message) => {
-            let json_response = warp::reply::json(&ErrorDetailsResponse {
-                error_id: "ERR__MESSAGE_POST_TO_ROOM_CONFLICT".to_string(),
-                error_message: format!("Cannot post message to room {}: {}", room_name, err_message)
-            });
-            Ok(warp::reply::with_status(json_response, StatusCode::CONFLICT))
-        }
+        Err(error) => Ok(error_reply(error))
     }
-}
\ No newline at end of file
+}
@@ -1,42 +1,42 @@
-use crate::entities::server::Server;
-use super::ErrorDetailsResponse;
-use std::sync::{Arc, Mutex};
+use crate::entities::{error::ServerError, server::SharedServer};
+use super::{error_reply, views::UserView};
+use crate::web_server::logging::record_user;
 use warp::http::StatusCode;
 use std::convert::Infallible;
+use uuid::Uuid;
 
-pub async fn get_user_in_server_by_username(username: String, server: Arc<Mutex<Server>>) -> Result<impl warp::Reply, Infallible> {
-    let server = server.lock().unwrap();
-    let user = server.get_user_by_username(&username);
-    match user {
-        Some(user_arc) => {
-            let user = user_arc.clone();
-            let json_response = warp::reply::json(&*user);
+pub async fn get_user_in_server_by_username(username: String, server: SharedServer) -> Result<impl warp::Reply, Infallible> {
+    record_user(&username);
+    let server = server.read().unwrap();
+    match server.get_user_by_username(&username) {
+        Some(user) => {
+            let json_response = warp::reply::json(&UserView::from(user.as_ref()));
             Ok(warp::reply::with_status(json_response, StatusCode::OK))
         },
-        None => {
-            let json_response = warp::reply::json(&ErrorDetailsResponse {
-                error_id: "ERR__USER_NOT_FOUND".to_string(),
-                error_message: format!("User with username {} not found in server", username)
-            });
-            Ok(warp::reply::with_status(json_response, StatusCode::NOT_FOUND))
-        }
+        None => Ok(error_reply(ServerError::UserNotFound(username)))
     }
 }
 
-pub async fn register_user_to_server(username: String, server: Arc<Mutex<Server>>) -> Result<impl warp::Reply, Infallible> {
-    let mut server = server.lock().unwrap();
+pub async fn get_user_in_server_by_id(user_id: Uuid, server: SharedServer) -> Result<impl warp::Reply, Infallible> {
+    let server = server.read().unwrap();
+    match server.get_user_by_id(&user_id) {
+        Some(user) => {
+            let json_response = warp::reply::json(&UserView::from(user.as_ref()));
+            Ok(warp::reply::with_status(json_response, StatusCode::OK))
+        },
+        None => Ok(error_reply(ServerError::UserIdNotFound(user_id)))
+    }
+}
+
+pub async fn register_user_to_server(username: String, server: SharedServer) -> Result<impl warp::Reply, Infallible> {
+    record_user(&username);
+    let mut server = server.write().unwrap();
     match server.register_user(&username) {
         Ok(_) => {
             let user = server.get_user_by_username(&username).unwrap();
-            let json_response = warp::reply::json(&*user);
+            let json_response = warp::reply::json(&UserView::from(user.as_ref()));
             Ok(warp::reply::with_status(json_response, StatusCode::CREATED))
         },
-        Err(_) => {
-            let json_response = warp::reply::json(&ErrorDetailsResponse {
-                error_id: "ERR__USER_ALREADY_EXISTS".to_string(),
-                error_message: format!("User with username {} already exists in server", username)
-            });
-            Ok(warp::reply::with_status(json_response, StatusCode::CONFLICT))
-        }
+        Err(error) => Ok(error_reply(error))
     }
-}
\ No newline at end of file
+}
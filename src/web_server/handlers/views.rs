@@ -0,0 +1,166 @@
+//! Views of the entities as answered by the handlers and read by the clients.
+//! They decouple the wire format from the entities, so that the entities can change without breaking the clients.
+
+use crate::entities::{message::Message, room::Room, user::User};
+use serde::{Deserialize, Serialize};
+use std::time::SystemTime;
+use uuid::Uuid;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct UserView {
+    pub id: Uuid,
+    pub username: String,
+    #[serde(default)]
+    pub is_bot: bool
+}
+
+impl From<&User> for UserView {
+    fn from(user: &User) -> Self {
+        UserView {
+            id: user.id,
+            username: user.username.clone(),
+            is_bot: user.is_bot
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MessageView {
+    pub id: Uuid,
+    pub author: UserView,
+    pub content: String,
+    #[serde(with = "crate::entities::timestamp")]
+    pub timestamp: SystemTime,
+    /// Position of the message in its room, strictly increasing, for the clients to detect the messages they missed
+    #[serde(default)]
+    pub seq: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub client_message_id: Option<Uuid>
+}
+
+impl From<&Message> for MessageView {
+    fn from(message: &Message) -> Self {
+        MessageView {
+            id: message.id,
+            author: UserView::from(message.author.as_ref()),
+            content: message.content.clone(),
+            timestamp: message.timestamp,
+            seq: message.seq,
+            client_message_id: message.client_message_id
+        }
+    }
+}
+
+/// Room as answered by the rooms routes, without its messages and webhooks.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RoomSummary {
+    pub id: Uuid,
+    pub name: String,
+    pub users: Vec<UserView>
+}
+
+impl From<&Room> for RoomSummary {
+    fn from(room: &Room) -> Self {
+        RoomSummary {
+            id: room.id,
+            name: room.name.clone(),
+            users: room.users.iter().map(|user| UserView::from(user.as_ref())).collect()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use std::time::Duration;
+
+    const USER_ID: &str = "0b7e8a52-0d5b-4c4e-9c43-3c1c0a1f6c11";
+    const ROOM_ID: &str = "5f1d9c3e-8a2b-4d6f-9e0a-1b2c3d4e5f60";
+    const MESSAGE_ID: &str = "9a8b7c6d-5e4f-4a3b-8c2d-1e0f9a8b7c6d";
+
+    fn user_view() -> UserView {
+        UserView { id: USER_ID.parse().unwrap(), username: "test_user".to_string(), is_bot: false }
+    }
+
+    fn user_view_json() -> serde_json::Value {
+        json!({"id": USER_ID, "username": "test_user", "is_bot": false})
+    }
+
+    /// Serializes the view to the expected JSON, and reads it back unchanged.
+    fn assert_round_trip<T: Serialize + serde::de::DeserializeOwned + PartialEq + std::fmt::Debug>(view: T, expected_json: serde_json::Value) {
+        assert_eq!(serde_json::to_value(&view).unwrap(), expected_json);
+        assert_eq!(serde_json::from_value::<T>(expected_json).unwrap(), view);
+    }
+
+    #[test]
+    fn test_user_view_round_trip() {
+        assert_round_trip(user_view(), user_view_json());
+    }
+
+    #[test]
+    fn test_user_view_is_bot_defaults_to_false() {
+        let user_view: UserView = serde_json::from_value(json!({"id": USER_ID, "username": "test_user"})).unwrap();
+        assert!(!user_view.is_bot);
+    }
+
+    #[test]
+    fn test_message_view_round_trip() {
+        let message_view = MessageView {
+            id: MESSAGE_ID.parse().unwrap(),
+            author: user_view(),
+            content: "Hello".to_string(),
+            timestamp: SystemTime::UNIX_EPOCH + Duration::new(1700000000, 123),
+            seq: 42,
+            client_message_id: None
+        };
+        assert_round_trip(message_view, json!({
+            "id": MESSAGE_ID,
+            "author": user_view_json(),
+            "content": "Hello",
+            "timestamp": "2023-11-14T22:13:20.000000123Z",
+            "seq": 42
+        }));
+    }
+
+    #[test]
+    fn test_message_view_reads_timestamp_since_epoch() {
+        let message_view: MessageView = serde_json::from_value(json!({
+            "id": MESSAGE_ID,
+            "author": user_view_json(),
+            "content": "Hello",
+            "timestamp": {"secs_since_epoch": 1700000000, "nanos_since_epoch": 123}
+        })).unwrap();
+        assert_eq!(message_view.timestamp, SystemTime::UNIX_EPOCH + Duration::new(1700000000, 123));
+    }
+
+    #[test]
+    fn test_room_summary_round_trip() {
+        let room_summary = RoomSummary {
+            id: ROOM_ID.parse().unwrap(),
+            name: "test_room".to_string(),
+            users: vec![user_view()]
+        };
+        assert_round_trip(room_summary, json!({
+            "id": ROOM_ID,
+            "name": "test_room",
+            "users": [user_view_json()]
+        }));
+    }
+
+    #[test]
+    fn test_views_from_entities() {
+        let mut room = Room::new("test_room".to_string());
+        let user = std::sync::Arc::new(User::new_bot("test_bot".to_string()));
+        room.users.push(user.clone());
+        let message = Message::new(user.clone(), "Hello".to_string());
+
+        let room_summary = RoomSummary::from(&room);
+        assert_eq!(room_summary.id, room.id);
+        assert_eq!(room_summary.users, vec![UserView::from(user.as_ref())]);
+        let message_view = MessageView::from(&message);
+        assert_eq!(message_view.id, message.id);
+        assert!(message_view.author.is_bot);
+        assert_eq!(message_view.timestamp, message.timestamp);
+    }
+}
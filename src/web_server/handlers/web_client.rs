@@ -0,0 +1,51 @@
+use crate::entities::error::ServerError;
+use super::error_reply;
+use std::convert::Infallible;
+use warp::http::{header, Response, StatusCode};
+use warp::Reply;
+
+/// File of the browser chat client, bundled in the binary so that the server is deployed alone.
+struct WebClientAsset {
+    file_name: &'static str,
+    content_type: &'static str,
+    content: &'static str
+}
+
+const INDEX: WebClientAsset = WebClientAsset {
+    file_name: "index.html",
+    content_type: "text/html; charset=utf-8",
+    content: include_str!("../web_client/index.html")
+};
+
+const ASSETS: [WebClientAsset; 2] = [
+    WebClientAsset {
+        file_name: "app.js",
+        content_type: "text/javascript; charset=utf-8",
+        content: include_str!("../web_client/app.js")
+    },
+    WebClientAsset {
+        file_name: "style.css",
+        content_type: "text/css; charset=utf-8",
+        content: include_str!("../web_client/style.css")
+    }
+];
+
+fn asset_reply(asset: &WebClientAsset) -> warp::reply::Response {
+    Response::builder()
+        .status(StatusCode::OK)
+        .header(header::CONTENT_TYPE, asset.content_type)
+        .header(header::CACHE_CONTROL, "no-cache")
+        .body(asset.content.into())
+        .unwrap()
+}
+
+pub async fn get_web_client_index() -> Result<impl warp::Reply, Infallible> {
+    Ok(asset_reply(&INDEX))
+}
+
+pub async fn get_web_client_asset(file_name: String) -> Result<impl warp::Reply, Infallible> {
+    match ASSETS.iter().find(|asset| asset.file_name == file_name) {
+        Some(asset) => Ok(asset_reply(asset)),
+        None => Ok(error_reply(ServerError::RouteNotFound).into_response())
+    }
+}
@@ -0,0 +1,125 @@
+use super::handlers::ErrorDetailsResponse;
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+use std::time::Instant;
+use tracing::field::Empty;
+use tracing_subscriber::EnvFilter;
+use uuid::Uuid;
+use warp::http::HeaderValue;
+use warp::hyper::{body, Body, Response};
+
+/// Header carrying the request id, generated by the server unless the client already provides one.
+pub const REQUEST_ID_HEADER: &str = "X-Request-Id";
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogFormat {
+    /// Human readable lines
+    Text,
+    /// One JSON object per line, for log collectors
+    Json
+}
+
+/// Installs the global logger, keeping only the events matching the filter.
+pub fn init_logging(log_format: LogFormat, filter: &str) {
+    let env_filter = EnvFilter::new(filter);
+    let subscriber = tracing_subscriber::fmt().with_env_filter(env_filter);
+    let result = match log_format {
+        LogFormat::Text => subscriber.try_init(),
+        LogFormat::Json => subscriber.json().with_current_span(true).with_span_list(false).try_init()
+    };
+    if let Err(error) = result {
+        eprintln!("Could not initialize logging: {}", error);
+    }
+}
+
+/// Span wrapping the whole processing of a request.
+/// The request id and the user are recorded later on, once known.
+pub fn request_span(info: warp::trace::Info) -> tracing::Span {
+    tracing::info_span!(
+        "request",
+        request_id = Empty,
+        method = %info.method(),
+        path = %info.path(),
+        user = Empty
+    )
+}
+
+pub fn request_id_from_header(request_id: Option<String>) -> String {
+    let request_id = request_id
+        .filter(|request_id| !request_id.is_empty() && HeaderValue::from_str(request_id).is_ok())
+        .unwrap_or_else(|| Uuid::new_v4().to_string());
+    tracing::Span::current().record("request_id", request_id.as_str());
+    request_id
+}
+
+/// Records the user acting in the current request, to be reported in the request log.
+pub fn record_user(username: &str) {
+    tracing::Span::current().record("user", username);
+}
+
+/// Echoes the request id in the response, both as a header and in the error details if any,
+/// then logs the outcome of the request.
+pub async fn finalize_response(request_id: String, started_at: Instant, response: Response<Body>) -> Response<Body> {
+    let (mut parts, mut response_body) = response.into_parts();
+
+    if parts.status.is_client_error() || parts.status.is_server_error() {
+        let bytes = body::to_bytes(response_body).await.unwrap_or_default();
+        response_body = match serde_json::from_slice::<ErrorDetailsResponse>(&bytes) {
+            Ok(mut error_details) => {
+                error_details.request_id = Some(request_id.clone());
+                Body::from(serde_json::to_vec(&error_details).unwrap())
+            },
+            Err(_) => Body::from(bytes)
+        };
+        parts.headers.remove(warp::http::header::CONTENT_LENGTH);
+    }
+    parts.headers.insert(REQUEST_ID_HEADER, HeaderValue::from_str(&request_id).unwrap());
+
+    let status = parts.status.as_u16();
+    let latency_ms = started_at.elapsed().as_secs_f64() * 1000.0;
+    if parts.status.is_server_error() {
+        tracing::error!(status, latency_ms, "request failed");
+    } else {
+        tracing::info!(status, latency_ms, "request completed");
+    }
+
+    Response::from_parts(parts, response_body)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::entities::error::ServerError;
+    use crate::web_server::handlers::error_reply;
+    use warp::Reply;
+
+    #[test]
+    fn test_request_id_from_header() {
+        assert_eq!(request_id_from_header(Some("abc-123".to_string())), "abc-123");
+        assert!(Uuid::parse_str(&request_id_from_header(None)).is_ok());
+        assert!(Uuid::parse_str(&request_id_from_header(Some("bad\nid".to_string()))).is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_finalize_response_error() {
+        let response = error_reply(ServerError::RoomNotFound("test_room".to_string())).into_response();
+        let response = finalize_response("abc-123".to_string(), Instant::now(), response).await;
+
+        assert_eq!(response.headers()[REQUEST_ID_HEADER], "abc-123");
+        let bytes = body::to_bytes(response.into_body()).await.unwrap();
+        let error_details: ErrorDetailsResponse = serde_json::from_slice(&bytes).unwrap();
+        assert_eq!(error_details.error_id, "ERR__ROOM_NOT_FOUND");
+        assert_eq!(error_details.request_id, Some("abc-123".to_string()));
+    }
+
+    #[tokio::test]
+    async fn test_finalize_response_success() {
+        let response = warp::reply::json(&serde_json::json!({"status": "OK"})).into_response();
+        let response = finalize_response("abc-123".to_string(), Instant::now(), response).await;
+
+        assert_eq!(response.headers()[REQUEST_ID_HEADER], "abc-123");
+        let bytes = body::to_bytes(response.into_body()).await.unwrap();
+        assert_eq!(&bytes[..], br#"{"status":"OK"}"#);
+    }
+}
@@ -0,0 +1,158 @@
+use crate::entities::server::Server;
+use super::routes::{API_VERSION, ROUTES};
+use prometheus::{Encoder, HistogramOpts, HistogramVec, IntCounterVec, IntGauge, Opts, Registry, TextEncoder};
+use std::time::Duration;
+
+/// Prometheus metrics of the web server.
+/// Request metrics are recorded as requests complete, while the domain gauges are sampled from the
+/// server state every time the metrics are scraped.
+pub struct Metrics {
+    registry: Registry,
+    http_requests_total: IntCounterVec,
+    http_request_duration_seconds: HistogramVec,
+    http_requests_in_flight: IntGauge,
+    registered_users: IntGauge,
+    rooms: IntGauge,
+    messages: IntGauge,
+}
+
+/// Keeps the in-flight requests gauge incremented until dropped,
+/// so that requests rejected or cancelled halfway are accounted for too.
+pub struct InFlightRequest {
+    gauge: IntGauge
+}
+
+impl Drop for InFlightRequest {
+    fn drop(&mut self) {
+        self.gauge.dec();
+    }
+}
+
+impl Default for Metrics {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Metrics {
+    pub fn new() -> Self {
+        let http_requests_total = IntCounterVec::new(
+            Opts::new("http_requests_total", "HTTP requests served, by route, method and status code"),
+            &["route", "method", "status"]
+        ).unwrap();
+        let http_request_duration_seconds = HistogramVec::new(
+            HistogramOpts::new("http_request_duration_seconds", "Latency of the HTTP requests served, by route and method"),
+            &["route", "method"]
+        ).unwrap();
+        let http_requests_in_flight = IntGauge::new("http_requests_in_flight", "HTTP requests currently being served").unwrap();
+        let registered_users = IntGauge::new("chat_registered_users", "Users registered in the server").unwrap();
+        let rooms = IntGauge::new("chat_rooms", "Rooms created in the server").unwrap();
+        let messages = IntGauge::new("chat_messages", "Messages posted across all the rooms of the server").unwrap();
+
+        let registry = Registry::new();
+        registry.register(Box::new(http_requests_total.clone())).unwrap();
+        registry.register(Box::new(http_request_duration_seconds.clone())).unwrap();
+        registry.register(Box::new(http_requests_in_flight.clone())).unwrap();
+        registry.register(Box::new(registered_users.clone())).unwrap();
+        registry.register(Box::new(rooms.clone())).unwrap();
+        registry.register(Box::new(messages.clone())).unwrap();
+
+        Metrics {
+            registry,
+            http_requests_total,
+            http_request_duration_seconds,
+            http_requests_in_flight,
+            registered_users,
+            rooms,
+            messages,
+        }
+    }
+
+    pub fn track_in_flight_request(&self) -> InFlightRequest {
+        self.http_requests_in_flight.inc();
+        InFlightRequest { gauge: self.http_requests_in_flight.clone() }
+    }
+
+    pub fn observe_request(&self, path: &str, method: &str, status: u16, elapsed: Duration) {
+        let route = route_label(path);
+        self.http_requests_total.with_label_values(&[route, method, &status.to_string()]).inc();
+        self.http_request_duration_seconds.with_label_values(&[route, method]).observe(elapsed.as_secs_f64());
+    }
+
+    /// Renders all the metrics in the Prometheus text exposition format.
+    pub fn render(&self, server: &Server) -> String {
+        self.registered_users.set(server.users.len() as i64);
+        self.rooms.set(server.rooms.len() as i64);
+        self.messages.set(server.messages_count() as i64);
+
+        let mut buffer = Vec::new();
+        TextEncoder::new().encode(&self.registry.gather(), &mut buffer).unwrap();
+        String::from_utf8(buffer).unwrap()
+    }
+}
+
+/// Maps a request path to the route serving it, as listed in `web_server::routes::ROUTES`,
+/// so that the metrics are labelled by route rather than by the unbounded set of paths.
+/// The versioned paths of the REST API share the label of their unversioned alias.
+pub fn route_label(path: &str) -> &'static str {
+    let segments: Vec<&str> = unversioned_path(path).trim_matches('/').split('/').collect();
+    ROUTES.iter()
+        .map(|(_, route_path)| unversioned_path(route_path))
+        .find(|route_path| {
+            let route_segments: Vec<&str> = route_path.trim_matches('/').split('/').collect();
+            route_segments.len() == segments.len()
+                && route_segments.iter().zip(&segments).all(|(route_segment, segment)| route_segment.starts_with(':') || route_segment == segment)
+        })
+        .unwrap_or("unmatched")
+}
+
+/// The path without its API version prefix, e.g. `/rooms` for `/v1/rooms`.
+fn unversioned_path(path: &str) -> &str {
+    path.strip_prefix('/')
+        .and_then(|path| path.strip_prefix(API_VERSION))
+        .filter(|path| path.len() > 1 && path.starts_with('/'))
+        .unwrap_or(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_route_label() {
+        assert_eq!(route_label("/"), "/");
+        assert_eq!(route_label("/web/app.js"), "/web/:file_name");
+        assert_eq!(route_label("/rooms"), "/rooms");
+        assert_eq!(route_label("/rooms/general"), "/rooms/:room_name");
+        assert_eq!(route_label("/v1/rooms/general"), "/rooms/:room_name");
+        assert_eq!(route_label("/rooms/general/users/alice"), "/rooms/:room_name/users/:username");
+        assert_eq!(route_label("/rooms/by-id/1b4e28ba-2fa1-11d2-883f-0016d3cca427"), "/rooms/by-id/:room_id");
+        assert_eq!(route_label("/hooks/abc"), "/hooks/:token");
+        assert_eq!(route_label("/unknown/path/here"), "unmatched");
+    }
+
+    #[test]
+    fn test_metrics_render() {
+        let metrics = Metrics::new();
+        let mut server = Server::new();
+        server.register_user("test_user").unwrap();
+        server.create_room("test_room", "test_user").unwrap();
+        server.post_message_to_room("test_room", "test_user", "hello").unwrap();
+        metrics.observe_request("/rooms/test_room", "GET", 200, Duration::from_millis(5));
+
+        let rendered = metrics.render(&server);
+        assert!(rendered.contains(r#"http_requests_total{method="GET",route="/rooms/:room_name",status="200"} 1"#));
+        assert!(rendered.contains("chat_registered_users 1"));
+        assert!(rendered.contains("chat_rooms 1"));
+        assert!(rendered.contains("chat_messages 1"));
+    }
+
+    #[test]
+    fn test_metrics_in_flight_request() {
+        let metrics = Metrics::new();
+        let in_flight_request = metrics.track_in_flight_request();
+        assert!(metrics.render(&Server::new()).contains("http_requests_in_flight 1"));
+        drop(in_flight_request);
+        assert!(metrics.render(&Server::new()).contains("http_requests_in_flight 0"));
+    }
+}
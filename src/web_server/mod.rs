@@ -1,32 +1,244 @@
+pub mod export;
 pub mod handlers;
+pub mod logging;
+pub mod metrics;
+pub mod openapi;
 pub mod routes;
+pub mod shutdown;
+pub mod tls;
+pub mod webhooks;
 
-use crate::entities::server::Server;
-use std::sync::{Arc, Mutex};
+use crate::config::{Config, ConfigError, CorsConfig, FeaturesConfig};
+use crate::entities::server::{Server, SharedServer};
+use metrics::Metrics;
+use shutdown::ShutdownPolicy;
+use std::future::Future;
+use std::net::SocketAddr;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::time::Duration;
+use tls::ServerTls;
+use tokio::sync::Notify;
+use webhooks::WebhookDeliveryPolicy;
 
 pub struct WebServer {
-    host: [u8; 4],
-    port: u16,
-    pub server: Arc<Mutex<Server>>
+    address: SocketAddr,
+    pub server: SharedServer,
+    pub metrics: Arc<Metrics>,
+    pub features: FeaturesConfig,
+    pub cors: CorsConfig,
+    pub tls: Option<ServerTls>,
+    pub shutdown_policy: ShutdownPolicy,
+    pub webhook_delivery_policy: WebhookDeliveryPolicy
 }
 
 impl WebServer {
-    pub fn new(host: &str, port: u16) -> WebServer {
-        let host_vec = host.split('.').map(|x| x.parse().unwrap()).collect::<Vec<u8>>();
-        let host_array: [u8; 4] = host_vec.try_into().expect("Host should have 4 octets");
-
-        WebServer {
-            host: host_array,
-            port,
-            server: Arc::new(Mutex::new(Server::new()))
+    pub fn new(config: &Config) -> Result<WebServer, ConfigError> {
+        let address = config.server.bind_address()?;
+
+        let server = Server::new_shared();
+        server.write().unwrap().max_message_length = config.limits.max_message_length;
+        let shutdown_timeout = Duration::from_secs(config.limits.shutdown_timeout_seconds);
+
+        Ok(WebServer {
+            address,
+            server,
+            metrics: Arc::new(Metrics::new()),
+            features: config.features.clone(),
+            cors: config.cors.clone(),
+            tls: ServerTls::from_config(&config.tls)?,
+            shutdown_policy: ShutdownPolicy {
+                drain_timeout: shutdown_timeout,
+                flush_timeout: shutdown_timeout
+            },
+            webhook_delivery_policy: WebhookDeliveryPolicy {
+                max_attempts: config.limits.webhook_max_attempts,
+                request_timeout: Duration::from_secs(config.limits.webhook_request_timeout_seconds),
+                ..WebhookDeliveryPolicy::default()
+            }
+        })
+    }
+
+    /// Runs the server until SIGINT or SIGTERM, then shuts it down gracefully.
+    pub async fn run(&self) -> Result<(), warp::Error> {
+        let (address, serving) = self.bind_with_graceful_shutdown(shutdown::shutdown_signal())?;
+        let scheme = if self.tls.is_some() { "https" } else { "http" };
+        tracing::info!("Starting server at {}://{}/", scheme, address);
+        serving.await;
+        Ok(())
+    }
+
+    /// Binds the server and returns the bound address, along with the future serving requests.
+    /// Once the shutdown signal completes, the server stops accepting connections, notifies the rooms,
+    /// drains the in-flight requests and flushes the pending webhook deliveries, each within the shutdown policy timeouts.
+    /// Fails if the address cannot be bound, or if the TLS certificate or key is invalid.
+    pub fn bind_with_graceful_shutdown(&self, shutdown_signal: impl Future<Output = ()> + Send + 'static) -> Result<(SocketAddr, impl Future<Output = ()>), warp::Error> {
+        let draining = Arc::new(Notify::new());
+        let signal_server = self.server.clone();
+        let signal_draining = draining.clone();
+        let signal = async move {
+            shutdown_signal.await;
+            tracing::info!("Shutting down, draining in-flight requests");
+            if let Ok(mut server) = signal_server.write() {
+                server.begin_shutdown(shutdown::SHUTDOWN_NOTICE);
+            }
+            signal_draining.notify_one();
+        };
+        let routes = routes::routes(self.server.clone(), self.metrics.clone(), &self.features, &self.cors);
+        let (address, serving): (SocketAddr, Pin<Box<dyn Future<Output = ()> + Send>>) = match &self.tls {
+            Some(tls) => {
+                let (address, serving) = warp::serve(routes).tls()
+                    .cert(&tls.cert_pem)
+                    .key(&tls.key_pem)
+                    .try_bind_with_graceful_shutdown(self.address, signal)?;
+                (address, Box::pin(serving))
+            },
+            None => {
+                let (address, serving) = warp::serve(routes).try_bind_with_graceful_shutdown(self.address, signal)?;
+                (address, Box::pin(serving))
+            }
+        };
+        // The uptime counts from the bind, which may still fail above
+        handlers::health::mark_server_started();
+
+        let webhook_dispatcher_handle = self.features.webhooks.then(|| {
+            let (webhook_dispatcher, webhook_dispatcher_handle) = webhooks::spawn_webhook_dispatcher(self.webhook_delivery_policy);
+            self.server.write().unwrap().set_webhook_dispatcher(webhook_dispatcher);
+            webhook_dispatcher_handle
+        });
+
+        let server = self.server.clone();
+        let shutdown_policy = self.shutdown_policy;
+        let serving = async move {
+            tokio::select! {
+                _ = serving => tracing::info!("In-flight requests drained"),
+                _ = async {
+                    draining.notified().await;
+                    tokio::time::sleep(shutdown_policy.drain_timeout).await;
+                } => tracing::warn!("In-flight requests not drained within {:?}, dropping them", shutdown_policy.drain_timeout),
+            }
+
+            // Dropping the last sender lets the dispatcher deliver the queued events and stop
+            drop(server.write().ok().and_then(|mut server| server.take_webhook_dispatcher()));
+            if let Some(webhook_dispatcher_handle) = webhook_dispatcher_handle {
+                match tokio::time::timeout(shutdown_policy.flush_timeout, webhook_dispatcher_handle).await {
+                    Ok(_) => tracing::info!("Pending webhook deliveries flushed"),
+                    Err(_) => tracing::warn!("Pending webhook deliveries not flushed within {:?}, dropping them", shutdown_policy.flush_timeout)
+                }
+            }
+            tracing::info!("Server stopped");
+        };
+
+        Ok((address, serving))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::entities::{message::Message, server::SERVER_BOT_NAME};
+    use std::collections::HashSet;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::Duration;
+    use tokio::sync::oneshot;
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn test_graceful_shutdown_loses_no_accepted_message() {
+        let mut config = Config::default();
+        config.server.port = 0;
+        let mut web_server = WebServer::new(&config).unwrap();
+        web_server.shutdown_policy.drain_timeout = Duration::from_secs(2);
+        web_server.server.write().unwrap().register_user("test_user").unwrap();
+        web_server.server.write().unwrap().create_room("test_room", "test_user").unwrap();
+
+        let (shutdown_sender, shutdown_receiver) = oneshot::channel::<()>();
+        let (address, serving) = web_server.bind_with_graceful_shutdown(async {
+            let _ = shutdown_receiver.await;
+        }).unwrap();
+        let serving = tokio::spawn(serving);
+
+        let client = reqwest::Client::new();
+        let responded = Arc::new(AtomicUsize::new(0));
+        let posts: Vec<_> = (0..200).map(|index| {
+            let client = client.clone();
+            let responded = responded.clone();
+            tokio::spawn(async move {
+                let response = client.post(format!("http://{}/v1/rooms/test_room/messages", address))
+                    .json(&serde_json::json!({"username": "test_user", "message": format!("message {}", index)}))
+                    .send()
+                    .await;
+                responded.fetch_add(1, Ordering::SeqCst);
+                match response {
+                    Ok(response) if response.status() == reqwest::StatusCode::CREATED => Some(response.json::<Message>().await.unwrap().id),
+                    _ => None
+                }
+            })
+        }).collect();
+
+        // Shut down while posts are still in flight
+        while responded.load(Ordering::SeqCst) < 20 {
+            tokio::time::sleep(Duration::from_millis(1)).await;
         }
+        shutdown_sender.send(()).unwrap();
+
+        let mut accepted_messages_ids = HashSet::new();
+        for post in posts {
+            if let Some(message_id) = post.await.unwrap() {
+                accepted_messages_ids.insert(message_id);
+            }
+        }
+        // Closes the connections opened but left unused by the client pool, which would otherwise wait for the drain timeout
+        drop(client);
+        tokio::time::timeout(Duration::from_secs(30), serving).await.unwrap().unwrap();
+
+        let stored_messages_ids: HashSet<_> = web_server.server.read().unwrap().get_room_messages("test_room").unwrap().iter()
+            .filter(|message| message.author.username != SERVER_BOT_NAME)
+            .map(|message| message.id)
+            .collect();
+        assert!(accepted_messages_ids.len() >= 20);
+        assert_eq!(accepted_messages_ids, stored_messages_ids);
+        assert!(web_server.server.read().unwrap().is_shutting_down());
     }
 
-    pub async fn run(&self) {
-        println!("{}", format!("Starting server at http://{}.{}.{}.{}:{}/", self.host[0], self.host[1], self.host[2], self.host[3], self.port));
+    #[tokio::test]
+    async fn test_serve_over_tls() {
+        let certificates = tls::TestCertificates::generate();
+        let mut config = Config::default();
+        config.server.port = 0;
+        let mut web_server = WebServer::new(&config).unwrap();
+        web_server.tls = Some(certificates.server_tls());
+
+        let (shutdown_sender, shutdown_receiver) = oneshot::channel::<()>();
+        let (address, serving) = web_server.bind_with_graceful_shutdown(async {
+            let _ = shutdown_receiver.await;
+        }).unwrap();
+        let serving = tokio::spawn(serving);
 
-        warp::serve(routes::routes(self.server.clone()))
-            .run((self.host, self.port))
-            .await;
+        let client = reqwest::Client::builder()
+            .add_root_certificate(reqwest::Certificate::from_pem(certificates.ca_cert_pem.as_bytes()).unwrap())
+            .build()
+            .unwrap();
+        let response = client.get(format!("https://localhost:{}/v1/status", address.port())).send().await.unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::OK);
+
+        // Neither the default trust store nor plaintext HTTP are accepted
+        assert!(reqwest::get(format!("https://localhost:{}/status", address.port())).await.is_err());
+        assert!(reqwest::get(format!("http://localhost:{}/status", address.port())).await.is_err());
+
+        drop(client);
+        shutdown_sender.send(()).unwrap();
+        tokio::time::timeout(Duration::from_secs(30), serving).await.unwrap().unwrap();
+    }
+
+    #[test]
+    fn test_bind_invalid_tls_certificate() {
+        let mut config = Config::default();
+        config.server.port = 0;
+        let mut web_server = WebServer::new(&config).unwrap();
+        web_server.tls = Some(ServerTls { cert_pem: b"not a certificate".to_vec(), key_pem: b"not a key".to_vec() });
+
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let _guard = runtime.enter();
+        assert!(web_server.bind_with_graceful_shutdown(async {}).is_err());
     }
-}
\ No newline at end of file
+}
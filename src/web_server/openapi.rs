@@ -0,0 +1,591 @@
+use crate::entities::error::error_ids;
+use super::handlers::health::SERVER_VERSION;
+use super::handlers::rooms::IDEMPOTENCY_KEY_MAX_LENGTH;
+use super::routes::API_VERSION;
+use serde_json::{json, Map, Value};
+
+/// Error ids answered by any route, on top of the ones specific to each operation.
+const COMMON_ERROR_IDS: [&str; 7] = [
+    error_ids::BAD_REQUEST,
+    error_ids::ROUTE_NOT_FOUND,
+    error_ids::METHOD_NOT_ALLOWED,
+    error_ids::LENGTH_REQUIRED,
+    error_ids::PAYLOAD_TOO_LARGE,
+    error_ids::ORIGIN_NOT_ALLOWED,
+    error_ids::INTERNAL
+];
+
+fn schema_ref(name: &str) -> Value {
+    json!({"$ref": format!("#/components/schemas/{}", name)})
+}
+
+fn json_content(schema: Value) -> Value {
+    json!({"application/json": {"schema": schema}})
+}
+
+fn success_response(description: &str, schema: Value) -> Value {
+    json!({"description": description, "content": json_content(schema)})
+}
+
+/// Error response of an operation, listing the error ids it can carry.
+fn error_response(description: &str, error_ids: &[&str]) -> Value {
+    json!({
+        "description": format!("{} Error ids: {}.", description, error_ids.join(", ")),
+        "content": json_content(schema_ref("ErrorDetailsResponse"))
+    })
+}
+
+fn path_parameter(name: &str, description: &str) -> Value {
+    let schema = if name.ends_with("_id") {
+        json!({"type": "string", "format": "uuid"})
+    } else {
+        json!({"type": "string"})
+    };
+    json!({"name": name, "in": "path", "required": true, "description": description, "schema": schema})
+}
+
+fn query_parameter(name: &str, description: &str, required: bool) -> Value {
+    json!({"name": name, "in": "query", "required": required, "description": description, "schema": {"type": "string"}})
+}
+
+fn request_body(schema_name: &str) -> Value {
+    json!({"required": true, "content": json_content(schema_ref(schema_name))})
+}
+
+/// Describes an operation: its summary, parameters, request body if any, and responses by status code.
+fn operation(operation_id: &str, tag: &str, summary: &str, parameters: Vec<Value>, body: Option<Value>, responses: Vec<(&str, Value)>) -> Value {
+    let mut operation = json!({
+        "operationId": operation_id,
+        "tags": [tag],
+        "summary": summary,
+        "responses": responses.into_iter().map(|(status, response)| (status.to_string(), response)).collect::<Map<String, Value>>()
+    });
+    if !parameters.is_empty() {
+        operation["parameters"] = Value::Array(parameters);
+    }
+    if let Some(body) = body {
+        operation["requestBody"] = body;
+    }
+    operation
+}
+
+fn paths() -> Value {
+    let room_name = || path_parameter("room_name", "Name of the room");
+    let username = || path_parameter("username", "Username of the user");
+    let token = || path_parameter("token", "Secret token of the incoming webhook");
+    let room_not_found = || error_response("The room does not exist.", &[error_ids::ROOM_NOT_FOUND]);
+    let room_or_user_not_found = || error_response("The room or the user does not exist.", &[error_ids::ROOM_NOT_FOUND, error_ids::USER_NOT_FOUND]);
+    let user_not_in_room = || error_response("The user is not in the room.", &[error_ids::USER_NOT_IN_ROOM]);
+    let idempotency_key = || json!({
+        "name": "Idempotency-Key",
+        "in": "header",
+        "required": false,
+        "description": "Client message id, as an alternative to the client_message_id field: a UUID, used as is, or any other key, hashed into a UUID",
+        "schema": {"type": "string", "minLength": 1, "maxLength": IDEMPOTENCY_KEY_MAX_LENGTH}
+    });
+
+    json!({
+        "/": {
+            "get": operation("getWebClient", "web client", "Returns the page of the browser chat client", vec![], None, vec![
+                ("200", json!({"description": "The page of the browser chat client.", "content": {"text/html": {"schema": {"type": "string"}}}}))
+            ])
+        },
+        "/web/{file_name}": {
+            "get": operation("getWebClientAsset", "web client", "Returns a script or stylesheet of the browser chat client", vec![
+                path_parameter("file_name", "Name of the file, e.g. app.js")
+            ], None, vec![
+                ("200", json!({"description": "The file.", "content": {"text/javascript": {"schema": {"type": "string"}}, "text/css": {"schema": {"type": "string"}}}})),
+                ("404", error_response("The file does not exist.", &[error_ids::ROUTE_NOT_FOUND]))
+            ])
+        },
+        "/openapi.json": {
+            "get": operation("getOpenApiDocument", "server", "Returns this OpenAPI document", vec![], None, vec![
+                ("200", success_response("The OpenAPI document of the REST API.", json!({"type": "object"})))
+            ])
+        },
+        "/v1/status": {
+            "get": operation("getStatus", "server", "Checks that the server is reachable", vec![], None, vec![
+                ("200", success_response("The server is reachable.", schema_ref("StatusResponse"))),
+                ("503", error_response("The server state is unavailable.", &[error_ids::SERVER_UNAVAILABLE]))
+            ])
+        },
+        "/metrics": {
+            "get": operation("getMetrics", "server", "Returns the metrics of the server in the Prometheus text format", vec![], None, vec![
+                ("200", json!({"description": "Request counts and latencies per route, registered users, rooms, messages and in-flight requests.", "content": {"text/plain": {"schema": {"type": "string"}}}}))
+            ])
+        },
+        "/health/live": {
+            "get": operation("getLiveness", "server", "Liveness probe, reporting the server version and uptime", vec![], None, vec![
+                ("200", success_response("The server answers.", schema_ref("LivenessResponse")))
+            ])
+        },
+        "/health/ready": {
+            "get": operation("getReadiness", "server", "Readiness probe, also reporting the storage availability and the lock contention", vec![], None, vec![
+                ("200", success_response("The server can serve requests.", schema_ref("ReadinessResponse"))),
+                ("503", success_response("The storage is unavailable or locked for too long, or the server is shutting down.", schema_ref("ReadinessResponse")))
+            ])
+        },
+        "/v1/users/{username}": {
+            "get": operation("getUserByUsername", "users", "Returns the user registered with the given username", vec![username()], None, vec![
+                ("200", success_response("The user.", schema_ref("User"))),
+                ("404", error_response("The user does not exist.", &[error_ids::USER_NOT_FOUND]))
+            ]),
+            "post": operation("registerUser", "users", "Registers a new user", vec![username()], None, vec![
+                ("201", success_response("The user registered.", schema_ref("User"))),
+                ("409", error_response("The username is already taken.", &[error_ids::USER_ALREADY_EXISTS]))
+            ])
+        },
+        "/v1/users/by-id/{user_id}": {
+            "get": operation("getUserById", "users", "Returns the user registered with the given id", vec![path_parameter("user_id", "Id of the user")], None, vec![
+                ("200", success_response("The user.", schema_ref("User"))),
+                ("404", error_response("The user does not exist.", &[error_ids::USER_NOT_FOUND]))
+            ])
+        },
+        "/v1/rooms": {
+            "get": operation("getRooms", "rooms", "Returns all the rooms, sorted by name", vec![], None, vec![
+                ("200", success_response("The rooms, empty if there are none.", json!({"type": "array", "items": schema_ref("RoomSummary")})))
+            ])
+        },
+        "/v1/rooms/by-id/{room_id}": {
+            "get": operation("getRoomById", "rooms", "Returns the room with the given id", vec![path_parameter("room_id", "Id of the room")], None, vec![
+                ("200", success_response("The room.", schema_ref("RoomSummary"))),
+                ("404", room_not_found())
+            ])
+        },
+        "/v1/rooms/{room_name}": {
+            "get": operation("getRoomByName", "rooms", "Returns the room with the given name", vec![room_name()], None, vec![
+                ("200", success_response("The room.", schema_ref("RoomSummary"))),
+                ("404", room_not_found())
+            ]),
+            "post": operation("createRoom", "rooms", "Creates a new room and adds its creator to it", vec![
+                room_name(),
+                query_parameter("creator_username", "Username of the user creating the room", true)
+            ], None, vec![
+                ("201", success_response("The room created.", schema_ref("RoomSummary"))),
+                ("400", error_response("The creator username is missing.", &[error_ids::BAD_REQUEST])),
+                ("404", error_response("The creator user does not exist.", &[error_ids::USER_NOT_FOUND])),
+                ("409", error_response("The room already exists.", &[error_ids::ROOM_ALREADY_EXISTS]))
+            ])
+        },
+        "/v1/rooms/{room_name}/users/{username}": {
+            "get": operation("getUserInRoom", "rooms", "Returns the user if they are in the room", vec![room_name(), username()], None, vec![
+                ("200", success_response("The user.", schema_ref("User"))),
+                ("404", error_response("The room or the user does not exist, or the user is not in the room.", &[error_ids::ROOM_NOT_FOUND, error_ids::USER_NOT_FOUND, error_ids::USER_NOT_IN_ROOM]))
+            ]),
+            "post": operation("addUserToRoom", "rooms", "Adds a user to the room", vec![room_name(), username()], None, vec![
+                ("201", success_response("The room, with the user added.", schema_ref("RoomSummary"))),
+                ("404", room_or_user_not_found()),
+                ("409", error_response("The user is already in the room.", &[error_ids::USER_ALREADY_IN_ROOM]))
+            ]),
+            "delete": operation("removeUserFromRoom", "rooms", "Removes a user from the room", vec![room_name(), username()], None, vec![
+                ("200", success_response("The room, with the user removed.", schema_ref("RoomSummary"))),
+                ("403", user_not_in_room()),
+                ("404", room_or_user_not_found())
+            ])
+        },
+        "/v1/rooms/{room_name}/messages": {
+            "get": operation("getRoomMessages", "messages", "Returns the messages of the room", vec![
+                room_name(),
+                json!({"name": "after_seq", "in": "query", "required": false, "description": "Sequence number of the last message received, to return only the ones posted after it", "schema": {"type": "integer", "minimum": 0}})
+            ], None, vec![
+                ("200", success_response("The messages, oldest first.", json!({"type": "array", "items": schema_ref("Message")}))),
+                ("400", error_response("The sequence number is invalid.", &[error_ids::BAD_REQUEST])),
+                ("404", room_not_found())
+            ]),
+            "post": operation("postMessageToRoom", "messages", "Posts a message in the room", vec![room_name(), idempotency_key()], Some(request_body("PostMessageRequest")), vec![
+                ("200", success_response("The message already posted with this client message id, not posted again.", schema_ref("Message"))),
+                ("201", success_response("The message posted.", schema_ref("Message"))),
+                ("400", error_response("The message is missing or too long, or the client message id is invalid.", &[error_ids::BAD_REQUEST, error_ids::MESSAGE_TOO_LONG])),
+                ("403", user_not_in_room()),
+                ("404", room_or_user_not_found()),
+                ("409", error_response("Another message was already posted with this client message id.", &[error_ids::IDEMPOTENCY_KEY_REUSED]))
+            ])
+        },
+        "/v1/rooms/{room_name}/export": {
+            "get": operation("exportRoomMessages", "messages", "Exports the messages of the room, streamed as an attachment", vec![
+                room_name(),
+                json!({"name": "format", "in": "query", "required": false, "description": "Format of the export, JSON lines by default", "schema": {"type": "string", "enum": ["jsonl", "csv", "markdown", "html"]}}),
+                json!({"name": "since", "in": "query", "required": false, "description": "Exports only the messages posted from this RFC 3339 timestamp, included", "schema": {"type": "string", "format": "date-time"}}),
+                json!({"name": "until", "in": "query", "required": false, "description": "Exports only the messages posted before this RFC 3339 timestamp, excluded", "schema": {"type": "string", "format": "date-time"}})
+            ], None, vec![
+                ("200", json!({
+                    "description": "The messages, oldest first, one per line in JSON lines and CSV.",
+                    "content": {
+                        "application/x-ndjson": {"schema": {"type": "string"}},
+                        "text/csv": {"schema": {"type": "string"}},
+                        "text/markdown": {"schema": {"type": "string"}},
+                        "text/html": {"schema": {"type": "string"}}
+                    }
+                })),
+                ("400", error_response("The format or a timestamp is invalid.", &[error_ids::BAD_REQUEST])),
+                ("404", room_not_found())
+            ])
+        },
+        "/v1/rooms/{room_name}/webhooks": {
+            "get": operation("getRoomWebhooks", "webhooks", "Returns the outgoing webhooks of the room, without their secrets", vec![room_name()], None, vec![
+                ("200", success_response("The webhooks.", json!({"type": "array", "items": schema_ref("Webhook")}))),
+                ("404", room_not_found())
+            ]),
+            "post": operation("registerWebhookToRoom", "webhooks", "Registers an outgoing webhook, notified on every message posted and user joined", vec![room_name()], Some(request_body("RegisterWebhookRequest")), vec![
+                ("201", success_response("The webhook registered, with its signing secret.", schema_ref("RegisteredWebhook"))),
+                ("400", error_response("The URL is missing or invalid.", &[error_ids::BAD_REQUEST, error_ids::WEBHOOK_URL_INVALID])),
+                ("404", room_not_found()),
+                ("409", error_response("The URL is already registered in the room.", &[error_ids::WEBHOOK_ALREADY_REGISTERED]))
+            ])
+        },
+        "/v1/rooms/{room_name}/webhooks/deliveries": {
+            "get": operation("getRoomWebhookDeliveries", "webhooks", "Returns the most recent webhook delivery attempts of the room", vec![room_name()], None, vec![
+                ("200", success_response("The delivery attempts.", json!({"type": "array", "items": schema_ref("WebhookDelivery")}))),
+                ("404", room_not_found())
+            ])
+        },
+        "/v1/rooms/{room_name}/hooks": {
+            "post": operation("createIncomingWebhook", "webhooks", "Creates an incoming webhook posting in the room as a bot", vec![
+                room_name(),
+                query_parameter("bot_name", "Name of the bot authoring the messages, webhook by default", false)
+            ], None, vec![
+                ("201", success_response("The incoming webhook, with its token.", schema_ref("IncomingWebhook"))),
+                ("400", error_response("The bot name is empty or the username of a registered user.", &[error_ids::BOT_NAME_INVALID])),
+                ("404", room_not_found())
+            ])
+        },
+        "/v1/hooks/{token}": {
+            "post": operation("postIncomingWebhookMessage", "webhooks", "Posts a message through an incoming webhook", vec![token()], Some(request_body("IncomingWebhookMessageRequest")), vec![
+                ("201", success_response("The message posted.", schema_ref("Message"))),
+                ("400", error_response("The message is missing or too long.", &[error_ids::BAD_REQUEST, error_ids::MESSAGE_TOO_LONG])),
+                ("404", error_response("The token is unknown.", &[error_ids::INCOMING_WEBHOOK_NOT_FOUND]))
+            ]),
+            "delete": operation("revokeIncomingWebhook", "webhooks", "Revokes an incoming webhook", vec![token()], None, vec![
+                ("200", success_response("The incoming webhook was revoked.", schema_ref("StatusResponse"))),
+                ("404", error_response("The token is unknown.", &[error_ids::INCOMING_WEBHOOK_NOT_FOUND]))
+            ])
+        }
+    })
+}
+
+fn schemas() -> Value {
+    let uuid = json!({"type": "string", "format": "uuid"});
+    json!({
+        "ErrorDetailsResponse": {
+            "type": "object",
+            "required": ["error_id", "error_message"],
+            "properties": {
+                "error_id": {"type": "string", "enum": error_ids::SERVER_ERROR_IDS},
+                "error_message": {"type": "string"},
+                "request_id": {"type": "string", "description": "Id of the request, also echoed in the X-Request-Id header"}
+            }
+        },
+        "StatusResponse": {
+            "type": "object",
+            "required": ["status"],
+            "properties": {"status": {"type": "string"}}
+        },
+        "LivenessResponse": {
+            "type": "object",
+            "required": ["status", "version", "uptime_seconds", "api_versions"],
+            "properties": {
+                "status": {"type": "string"},
+                "version": {"type": "string"},
+                "uptime_seconds": {"type": "integer", "minimum": 0},
+                "api_versions": {"type": "array", "items": {"type": "string"}, "description": "Versions of the REST API served, e.g. v1 for the paths prefixed with /v1"}
+            }
+        },
+        "ReadinessCheck": {
+            "type": "object",
+            "required": ["status"],
+            "properties": {"status": {"type": "string", "enum": ["up", "degraded", "down"]}},
+            "additionalProperties": true
+        },
+        "ReadinessResponse": {
+            "type": "object",
+            "required": ["status", "version", "uptime_seconds", "storage", "lock"],
+            "properties": {
+                "status": {"type": "string", "enum": ["OK__READY", "ERR__SHUTTING_DOWN", "ERR__NOT_READY"]},
+                "version": {"type": "string"},
+                "uptime_seconds": {"type": "integer", "minimum": 0},
+                "storage": schema_ref("ReadinessCheck"),
+                "lock": schema_ref("ReadinessCheck")
+            }
+        },
+        "Timestamp": {
+            "type": "string",
+            "format": "date-time",
+            "description": "RFC 3339 timestamp in UTC, e.g. 2023-11-14T22:13:20.5Z. Objects with secs_since_epoch and nanos_since_epoch fields, as answered by the previous versions, are accepted too."
+        },
+        "User": {
+            "type": "object",
+            "required": ["id", "username", "is_bot"],
+            "properties": {
+                "id": uuid,
+                "username": {"type": "string"},
+                "is_bot": {"type": "boolean"}
+            }
+        },
+        "RoomSummary": {
+            "type": "object",
+            "required": ["id", "name", "users"],
+            "properties": {
+                "id": uuid,
+                "name": {"type": "string"},
+                "users": {"type": "array", "items": schema_ref("User")}
+            }
+        },
+        "Message": {
+            "type": "object",
+            "required": ["id", "author", "content", "timestamp", "seq"],
+            "properties": {
+                "id": uuid,
+                "author": schema_ref("User"),
+                "content": {"type": "string"},
+                "timestamp": schema_ref("Timestamp"),
+                "seq": {"type": "integer", "minimum": 1, "description": "Position of the message in its room, strictly increasing in the order the messages are posted"},
+                "client_message_id": {"type": "string", "format": "uuid", "description": "Id generated by the client that sent the message, if any"}
+            }
+        },
+        "PostMessageRequest": {
+            "type": "object",
+            "required": ["username", "message"],
+            "properties": {
+                "username": {"type": "string"},
+                "message": {"type": "string"},
+                "client_message_id": {"type": "string", "format": "uuid", "description": "Id generated by the client, so that the message sent again while the room remembers its recent ids is answered instead of being posted twice"}
+            }
+        },
+        "Webhook": {
+            "type": "object",
+            "required": ["id", "url"],
+            "properties": {
+                "id": uuid,
+                "url": {"type": "string", "format": "uri"}
+            }
+        },
+        "RegisteredWebhook": {
+            "type": "object",
+            "required": ["id", "url", "secret"],
+            "properties": {
+                "id": uuid,
+                "url": {"type": "string", "format": "uri"},
+                "secret": {"type": "string", "description": "Key of the HMAC-SHA256 signature sent with every delivery"}
+            }
+        },
+        "RegisterWebhookRequest": {
+            "type": "object",
+            "required": ["url"],
+            "properties": {
+                "url": {"type": "string", "format": "uri"},
+                "secret": {"type": "string", "description": "Generated if missing"}
+            }
+        },
+        "WebhookDelivery": {
+            "type": "object",
+            "required": ["payload_id", "webhook_id", "event", "attempt", "success", "timestamp"],
+            "properties": {
+                "payload_id": uuid,
+                "webhook_id": uuid,
+                "event": {"type": "string"},
+                "attempt": {"type": "integer", "minimum": 1},
+                "status_code": {"type": "integer", "nullable": true},
+                "success": {"type": "boolean"},
+                "error": {"type": "string", "nullable": true},
+                "timestamp": schema_ref("Timestamp")
+            }
+        },
+        "IncomingWebhook": {
+            "type": "object",
+            "required": ["id", "token", "room_name", "bot"],
+            "properties": {
+                "id": uuid,
+                "token": {"type": "string"},
+                "room_name": {"type": "string"},
+                "bot": schema_ref("User")
+            }
+        },
+        "IncomingWebhookMessageRequest": {
+            "type": "object",
+            "description": "Either the message field, or the Slack-compatible text field",
+            "properties": {
+                "message": {"type": "string"},
+                "text": {"type": "string"}
+            }
+        }
+    })
+}
+
+/// OpenAPI 3 document describing every route of the REST API, served at `GET /openapi.json`.
+pub fn openapi_document() -> Value {
+    json!({
+        "openapi": "3.0.3",
+        "info": {
+            "title": "Rust Live Chat",
+            "version": SERVER_VERSION,
+            "description": format!(
+                "REST API of the chat server. Errors are answered as an ErrorDetailsResponse, any route possibly failing with: {}. \
+                The paths of the REST API are also served without their /{} prefix, as deprecated aliases answering with the Deprecation header.",
+                COMMON_ERROR_IDS.join(", "),
+                API_VERSION
+            )
+        },
+        "paths": paths(),
+        "components": {"schemas": schemas()}
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{CorsConfig, FeaturesConfig};
+    use crate::entities::server::Server;
+    use crate::web_server::{handlers::ErrorDetailsResponse, metrics::Metrics, routes::{self, ROUTES}};
+    use regex::Regex;
+    use std::collections::BTreeSet;
+    use std::sync::Arc;
+    use uuid::Uuid;
+
+    /// Routes listed in `web_server::routes::ROUTES`, in the OpenAPI path syntax, e.g. `/v1/rooms/{room_name}`.
+    fn declared_routes() -> BTreeSet<(String, String)> {
+        let path_parameter = Regex::new(r":(\w+)").unwrap();
+        ROUTES.iter()
+            .map(|(method, path)| (method.to_lowercase(), path_parameter.replace_all(path, "{$1}").to_string()))
+            .collect()
+    }
+
+    fn described_routes() -> BTreeSet<(String, String)> {
+        let document = openapi_document();
+        document["paths"].as_object().unwrap().iter()
+            .flat_map(|(path, operations)| operations.as_object().unwrap().keys().map(move |method| (method.clone(), path.clone())))
+            .collect()
+    }
+
+    #[test]
+    fn test_openapi_document_describes_every_route() {
+        let declared_routes = declared_routes();
+        let described_routes = described_routes();
+        assert!(declared_routes.len() > 20);
+
+        let undescribed: Vec<_> = declared_routes.difference(&described_routes).collect();
+        assert!(undescribed.is_empty(), "Routes missing from the OpenAPI document: {:?}", undescribed);
+        let undeclared: Vec<_> = described_routes.difference(&declared_routes).collect();
+        assert!(undeclared.is_empty(), "Routes described in the OpenAPI document but not declared: {:?}", undeclared);
+    }
+
+    #[tokio::test]
+    async fn test_declared_routes_are_served() {
+        let server = Server::new_shared();
+        let routes = routes::routes(server, Arc::new(Metrics::new()), &FeaturesConfig::default(), &CorsConfig::default());
+
+        let path_parameter = Regex::new(r":\w+").unwrap();
+        for (method, path) in ROUTES {
+            let path = path.replace(":file_name", "app.js").replace(":user_id", &Uuid::nil().to_string()).replace(":room_id", &Uuid::nil().to_string());
+            let path = path_parameter.replace_all(&path, "test").to_string();
+            let response = warp::test::request()
+                .method(method)
+                .path(&path)
+                .json(&serde_json::json!({}))
+                .reply(&routes)
+                .await;
+            if let Ok(error_details) = serde_json::from_slice::<ErrorDetailsResponse>(response.body()) {
+                assert!(
+                    ![error_ids::ROUTE_NOT_FOUND, error_ids::METHOD_NOT_ALLOWED].contains(&error_details.error_id.as_str()),
+                    "{} {} is not served: {}", method, path, error_details.error_id
+                );
+            }
+        }
+    }
+
+    /// Literal path segments of the filters, read from the sources of the routes so a route missing from `ROUTES` is still probed.
+    fn filter_path_segments() -> BTreeSet<String> {
+        let sources = [
+            include_str!("routes/mod.rs"),
+            include_str!("routes/health.rs"),
+            include_str!("routes/hooks.rs"),
+            include_str!("routes/rooms.rs"),
+            include_str!("routes/users.rs"),
+            include_str!("routes/web_client.rs")
+        ];
+        let path_filter = Regex::new(r"warp::path!?\(([^)]*)\)").unwrap();
+        let literal = Regex::new(r#""([^"]+)""#).unwrap();
+        sources.iter()
+            .map(|source| source.split("#[cfg(test)]").next().unwrap())
+            .flat_map(|source| path_filter.captures_iter(source).map(|captures| captures[1].to_string()))
+            .flat_map(|arguments| literal.captures_iter(&arguments).map(|captures| captures[1].to_string()).collect::<Vec<_>>())
+            .collect()
+    }
+
+    /// Whether the path matches the declared route, served with or without its API version prefix.
+    fn route_matches(route_path: &str, path: &str) -> bool {
+        let route_segments: Vec<&str> = route_path.split('/').filter(|segment| !segment.is_empty()).collect();
+        let segments: Vec<&str> = path.split('/').filter(|segment| !segment.is_empty()).collect();
+        let matches = |route_segments: &[&str]| route_segments.len() == segments.len()
+            && route_segments.iter().zip(&segments).all(|(route_segment, segment)| route_segment.starts_with(':') || route_segment == segment);
+        matches(&route_segments) || (route_segments.first() == Some(&API_VERSION) && matches(&route_segments[1..]))
+    }
+
+    #[tokio::test]
+    async fn test_served_routes_are_declared() {
+        let server = Server::new_shared();
+        let routes = routes::routes(server, Arc::new(Metrics::new()), &FeaturesConfig::default(), &CorsConfig::default());
+
+        let mut segments = filter_path_segments();
+        assert!(segments.contains("deliveries"));
+        segments.extend([API_VERSION.to_string(), Uuid::nil().to_string(), "app.js".to_string(), "test".to_string()]);
+        // Every prefix of the declared paths, then extended by one more segment
+        let path_parameter = Regex::new(r":\w+").unwrap();
+        let mut prefixes = BTreeSet::new();
+        for (_, path) in ROUTES {
+            let path = path_parameter.replace_all(path, "test").to_string();
+            let path = path.strip_prefix(&format!("/{}", API_VERSION)).unwrap_or(&path).to_string();
+            let path_segments: Vec<&str> = path.split('/').filter(|segment| !segment.is_empty()).collect();
+            for length in 0..=path_segments.len() {
+                prefixes.insert(path_segments[..length].join("/"));
+            }
+        }
+        let mut paths = BTreeSet::new();
+        for prefix in &prefixes {
+            for version in ["".to_string(), format!("/{}", API_VERSION)] {
+                let prefix = format!("{}/{}", version, prefix);
+                paths.insert(prefix.clone());
+                for segment in &segments {
+                    paths.insert(format!("{}/{}", prefix.trim_end_matches('/'), segment));
+                }
+            }
+        }
+
+        for path in &paths {
+            for method in ["GET", "POST", "PUT", "PATCH", "DELETE"] {
+                let response = warp::test::request()
+                    .method(method)
+                    .path(path)
+                    .json(&serde_json::json!({}))
+                    .reply(&routes)
+                    .await;
+                if let Ok(error_details) = serde_json::from_slice::<ErrorDetailsResponse>(response.body()) {
+                    if [error_ids::ROUTE_NOT_FOUND, error_ids::METHOD_NOT_ALLOWED].contains(&error_details.error_id.as_str()) {
+                        continue;
+                    }
+                }
+                assert!(
+                    ROUTES.iter().any(|(route_method, route_path)| *route_method == method && route_matches(route_path, path)),
+                    "{} {} is served but missing from ROUTES", method, path
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_openapi_document_references_existing_schemas() {
+        let document = openapi_document();
+        let serialized = document.to_string();
+        let schema_reference = Regex::new(r"#/components/schemas/(\w+)").unwrap();
+        for captures in schema_reference.captures_iter(&serialized) {
+            assert!(document["components"]["schemas"].get(&captures[1]).is_some(), "Missing schema {}", &captures[1]);
+        }
+    }
+
+    #[test]
+    fn test_openapi_document_error_ids() {
+        let document = openapi_document();
+        let documented_error_ids = document["components"]["schemas"]["ErrorDetailsResponse"]["properties"]["error_id"]["enum"].as_array().unwrap();
+        assert!(documented_error_ids.contains(&json!(error_ids::ROOM_NOT_FOUND)));
+        assert!(!documented_error_ids.contains(&json!(error_ids::CLIENT_FETCH_API)));
+    }
+}
@@ -0,0 +1,144 @@
+use crate::entities::server::SharedServer;
+use crate::web_server::handlers;
+use warp::Filter;
+use super::with_server;
+
+pub fn health_routes(server: SharedServer) -> impl Filter<Extract = impl warp::Reply, Error = warp::Rejection> + Clone {
+    get_liveness()
+        .or(get_readiness(server.clone()))
+}
+
+/**
+ * GET /health/live
+ * Liveness probe, reports the server version and uptime without touching the server state.
+ * Returns 200 OK as long as the server answers.
+ */
+fn get_liveness() -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
+    warp::path!("health" / "live")
+        .and(warp::get())
+        .and_then(handlers::health::get_liveness)
+}
+
+/**
+ * GET /health/ready
+ * Readiness probe, reports the server version and uptime, the storage availability and the lock contention.
+ * Returns 200 OK if the server can serve requests, 503 SERVICE UNAVAILABLE if the storage is unavailable or locked for too long,
+ * or if the server is shutting down.
+ */
+fn get_readiness(server: SharedServer) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
+    warp::path!("health" / "ready")
+        .and(warp::get())
+        .and(with_server(server))
+        .and_then(handlers::health::get_readiness)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::entities::server::Server;
+    use crate::web_server::handlers::health::{LivenessResponse, ReadinessResponse, SERVER_VERSION};
+    use warp::http::StatusCode;
+    use warp::test::request;
+
+    #[tokio::test]
+    async fn test_get_liveness() {
+        let server = Server::new_shared();
+
+        let response = request()
+            .method("GET")
+            .path("/health/live")
+            .reply(&health_routes(server.clone()))
+            .await;
+
+        assert_eq!(response.status(), StatusCode::OK);
+        let liveness: LivenessResponse = serde_json::from_slice(response.body()).unwrap();
+        assert_eq!(liveness.status, "OK__LIVE");
+        assert_eq!(liveness.version, SERVER_VERSION);
+        assert_eq!(liveness.api_versions, vec!["v1"]);
+    }
+
+    #[tokio::test]
+    async fn test_get_readiness() {
+        let server = Server::new_shared();
+        server.write().unwrap().register_user("test_user").unwrap();
+
+        let response = request()
+            .method("GET")
+            .path("/health/ready")
+            .reply(&health_routes(server.clone()))
+            .await;
+
+        assert_eq!(response.status(), StatusCode::OK);
+        let readiness: ReadinessResponse = serde_json::from_slice(response.body()).unwrap();
+        assert_eq!(readiness.status, "OK__READY");
+        assert_eq!(readiness.storage.status, "up");
+        assert_eq!(readiness.storage.details["users"], 1);
+        assert_eq!(readiness.lock.details["contended"], false);
+    }
+
+    #[tokio::test]
+    async fn test_get_readiness_shutting_down() {
+        let server = Server::new_shared();
+        server.write().unwrap().begin_shutdown("The server is shutting down");
+
+        let response = request()
+            .method("GET")
+            .path("/health/ready")
+            .reply(&health_routes(server.clone()))
+            .await;
+
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+        let readiness: ReadinessResponse = serde_json::from_slice(response.body()).unwrap();
+        assert_eq!(readiness.status, "ERR__SHUTTING_DOWN");
+        assert_eq!(readiness.storage.status, "up");
+    }
+
+    #[tokio::test]
+    async fn test_get_readiness_storage_unavailable() {
+        let server = Server::new_shared();
+        let poisoning_server = server.clone();
+        let _ = std::thread::spawn(move || {
+            let _server = poisoning_server.write().unwrap();
+            panic!("poisoning the server state");
+        }).join();
+
+        let response = request()
+            .method("GET")
+            .path("/health/ready")
+            .reply(&health_routes(server.clone()))
+            .await;
+
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+        let readiness: ReadinessResponse = serde_json::from_slice(response.body()).unwrap();
+        assert_eq!(readiness.status, "ERR__NOT_READY");
+        assert_eq!(readiness.storage.status, "down");
+    }
+
+    #[tokio::test]
+    async fn test_get_readiness_lock_held() {
+        let server = Server::new_shared();
+        let (locked_sender, locked_receiver) = std::sync::mpsc::channel();
+        let (release_sender, release_receiver) = std::sync::mpsc::channel::<()>();
+        let writing_server = server.clone();
+        let writer = std::thread::spawn(move || {
+            let _server = writing_server.write().unwrap();
+            locked_sender.send(()).unwrap();
+            let _ = release_receiver.recv();
+        });
+        locked_receiver.recv().unwrap();
+
+        let response = request()
+            .method("GET")
+            .path("/health/ready")
+            .reply(&health_routes(server.clone()))
+            .await;
+        release_sender.send(()).unwrap();
+        writer.join().unwrap();
+
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+        let readiness: ReadinessResponse = serde_json::from_slice(response.body()).unwrap();
+        assert_eq!(readiness.status, "ERR__NOT_READY");
+        assert_eq!(readiness.storage.status, "down");
+        assert_eq!(readiness.lock.status, "degraded");
+    }
+}
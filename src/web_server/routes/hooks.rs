@@ -0,0 +1,163 @@
+use crate::entities::server::SharedServer;
+use crate::web_server::handlers;
+use warp::Filter;
+use super::with_server;
+
+pub fn hooks_routes(server: SharedServer) -> impl Filter<Extract = impl warp::Reply, Error = warp::Rejection> + Clone {
+    post_incoming_webhook_message(server.clone())
+        .or(revoke_incoming_webhook(server.clone()))
+}
+
+/**
+ * POST /v1/hooks/:token
+ * Posts a message in the room bound to the incoming webhook, authored by the webhook bot.
+ * Expects a JSON body with the message field, or the Slack-compatible text field.
+ * Returns 201 CREATED with the message if it was successfully posted, 404 NOT FOUND if the token is unknown.
+ * If missing fields, returns 400 BAD REQUEST.
+ */
+fn post_incoming_webhook_message(server: SharedServer) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
+    warp::path!("hooks" / String)
+        .and(warp::post())
+        .and(warp::body::json())
+        .and(with_server(server))
+        .and_then(handlers::hooks::post_incoming_webhook_message)
+}
+
+/**
+ * DELETE /v1/hooks/:token
+ * Revokes the incoming webhook, so that its token cannot be used anymore.
+ * Returns 200 OK if the incoming webhook was revoked, 404 NOT FOUND if the token is unknown.
+ */
+fn revoke_incoming_webhook(server: SharedServer) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
+    warp::path!("hooks" / String)
+        .and(warp::delete())
+        .and(with_server(server))
+        .and_then(handlers::hooks::revoke_incoming_webhook)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::entities::message::Message;
+    use crate::entities::server::Server;
+    use warp::http::StatusCode;
+    use serde_json::{self};
+    use warp::test::request;
+    use crate::web_server::handlers::ErrorDetailsResponse;
+
+    fn server_with_incoming_webhook() -> (SharedServer, String) {
+        let server = Server::new_shared();
+        server.clone().write().unwrap().register_user("test_user").unwrap();
+        server.clone().write().unwrap().create_room("test_room", "test_user").unwrap();
+        let token = server.clone().write().unwrap().create_incoming_webhook("test_room", "ci").unwrap().token.clone();
+        (server, token)
+    }
+
+    #[tokio::test]
+    async fn test_post_incoming_webhook_message() {
+        let (server, token) = server_with_incoming_webhook();
+
+        let response = request()
+            .method("POST")
+            .path(&format!("/hooks/{}", token))
+            .json(&serde_json::json!({
+                "message": "build passed"
+            }))
+            .reply(&hooks_routes(server.clone()))
+            .await;
+
+        assert_eq!(response.status(), StatusCode::CREATED);
+
+        let message: Message = serde_json::from_slice(response.body()).unwrap();
+        assert_eq!(message.content, "build passed");
+        assert_eq!(message.author.username, "ci");
+        assert!(message.author.is_bot);
+        assert_eq!(server.write().unwrap().get_room_messages("test_room").unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_post_incoming_webhook_message_slack_payload() {
+        let (server, token) = server_with_incoming_webhook();
+
+        let response = request()
+            .method("POST")
+            .path(&format!("/hooks/{}", token))
+            .json(&serde_json::json!({
+                "text": "deploy finished",
+                "mrkdwn": true
+            }))
+            .reply(&hooks_routes(server.clone()))
+            .await;
+
+        assert_eq!(response.status(), StatusCode::CREATED);
+
+        let message: Message = serde_json::from_slice(response.body()).unwrap();
+        assert_eq!(message.content, "deploy finished");
+    }
+
+    #[tokio::test]
+    async fn test_post_incoming_webhook_message_missing_fields() {
+        let (server, token) = server_with_incoming_webhook();
+
+        let response = request()
+            .method("POST")
+            .path(&format!("/hooks/{}", token))
+            .json(&serde_json::json!({}))
+            .reply(&hooks_routes(server.clone()))
+            .await;
+
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+        let error: ErrorDetailsResponse = serde_json::from_slice(response.body()).unwrap();
+        assert_eq!(error.error_id, "ERR__BAD_REQUEST");
+    }
+
+    #[tokio::test]
+    async fn test_post_incoming_webhook_message_token_not_found() {
+        let (server, _) = server_with_incoming_webhook();
+
+        let response = request()
+            .method("POST")
+            .path("/hooks/unknown_token")
+            .json(&serde_json::json!({
+                "text": "build passed"
+            }))
+            .reply(&hooks_routes(server.clone()))
+            .await;
+
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+        let error: ErrorDetailsResponse = serde_json::from_slice(response.body()).unwrap();
+        assert_eq!(error.error_id, "ERR__INCOMING_WEBHOOK_NOT_FOUND");
+    }
+
+    #[tokio::test]
+    async fn test_revoke_incoming_webhook() {
+        let (server, token) = server_with_incoming_webhook();
+
+        let response = request()
+            .method("DELETE")
+            .path(&format!("/hooks/{}", token))
+            .reply(&hooks_routes(server.clone()))
+            .await;
+
+        assert_eq!(response.status(), StatusCode::OK);
+        assert!(server.write().unwrap().get_incoming_webhook_by_token(&token).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_revoke_incoming_webhook_token_not_found() {
+        let (server, _) = server_with_incoming_webhook();
+
+        let response = request()
+            .method("DELETE")
+            .path("/hooks/unknown_token")
+            .reply(&hooks_routes(server.clone()))
+            .await;
+
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+        let error: ErrorDetailsResponse = serde_json::from_slice(response.body()).unwrap();
+        assert_eq!(error.error_id, "ERR__INCOMING_WEBHOOK_NOT_FOUND");
+    }
+}
@@ -1,42 +1,234 @@
 pub mod users;
 pub mod rooms;
+pub mod hooks;
+pub mod health;
+pub mod web_client;
 
-use crate::entities::server::Server;
-use std::sync::{Arc, Mutex};
+use crate::config::{CorsConfig, FeaturesConfig};
+use crate::entities::server::SharedServer;
+use super::{logging, metrics::{self, Metrics}};
+use std::{sync::Arc, time::Instant};
+use warp::http::HeaderValue;
 use warp::Filter;
 
 use super::handlers;
 
-pub fn routes(server: Arc<Mutex<Server>>) -> impl Filter<Extract = impl warp::Reply, Error = warp::Rejection> + Clone {
-    is_server_reachable_route(server.clone())
+/// Version prefixing the paths of the REST API, e.g. `/v1/rooms`.
+pub const API_VERSION: &str = "v1";
+
+/// Every route served, by method and path, the paths of the REST API being also served without their `/v1` prefix.
+/// The metrics are labelled by these paths, and the OpenAPI document is checked against them and against the filters.
+/// The literal segments are listed before the parameters they could be taken for, e.g. `by-id` before `:room_name`.
+pub const ROUTES: [(&str, &str); 26] = [
+    ("GET", "/"),
+    ("GET", "/web/:file_name"),
+    ("GET", "/openapi.json"),
+    ("GET", "/metrics"),
+    ("GET", "/health/live"),
+    ("GET", "/health/ready"),
+    ("GET", "/v1/status"),
+    ("GET", "/v1/users/by-id/:user_id"),
+    ("GET", "/v1/users/:username"),
+    ("POST", "/v1/users/:username"),
+    ("GET", "/v1/rooms"),
+    ("GET", "/v1/rooms/by-id/:room_id"),
+    ("GET", "/v1/rooms/:room_name"),
+    ("POST", "/v1/rooms/:room_name"),
+    ("GET", "/v1/rooms/:room_name/users/:username"),
+    ("POST", "/v1/rooms/:room_name/users/:username"),
+    ("DELETE", "/v1/rooms/:room_name/users/:username"),
+    ("GET", "/v1/rooms/:room_name/messages"),
+    ("POST", "/v1/rooms/:room_name/messages"),
+    ("GET", "/v1/rooms/:room_name/export"),
+    ("GET", "/v1/rooms/:room_name/webhooks"),
+    ("POST", "/v1/rooms/:room_name/webhooks"),
+    ("GET", "/v1/rooms/:room_name/webhooks/deliveries"),
+    ("POST", "/v1/rooms/:room_name/hooks"),
+    ("POST", "/v1/hooks/:token"),
+    ("DELETE", "/v1/hooks/:token"),
+];
+
+/// Date from which the unversioned paths of the REST API are deprecated, in seconds since the epoch (2026-10-18).
+const UNVERSIONED_API_DEPRECATION_DATE: u64 = 1792281600;
+
+pub fn routes(server: SharedServer, metrics: Arc<Metrics>, features: &FeaturesConfig, cors: &CorsConfig) -> impl Filter<Extract = impl warp::Reply, Error = warp::Rejection> + Clone {
+    let api_routes = is_server_reachable_route(server.clone())
         .or(users::users_routes(server.clone()))
         .or(rooms::rooms_routes(server.clone()))
+        .or(hooks::hooks_routes(server.clone()));
+
+    let routes = enabled_feature_filter(features.clone())
+        .and(web_client::web_client_routes()
+        .or(health::health_routes(server.clone()))
+        .or(get_metrics_route(metrics.clone(), server.clone()))
+        .or(get_openapi_document_route())
+        .or(warp::path(API_VERSION).and(api_routes.clone()))
+        .or(warp::path::full().and(api_routes).map(deprecated_alias_reply)))
+        .map(warp::Reply::into_response);
+    // The requests of the bundled web client are same-origin, and must not be held to the origins allowed for the others
+    let routes = match cors_policy(cors) {
+        Some(cors_policy) => origin_filter(true).and(routes.clone())
+            .or(origin_filter(false).and(routes.with(cors_policy).map(warp::Reply::into_response)))
+            .unify()
+            .boxed(),
+        None => routes.boxed()
+    };
+    let routes = routes.recover(handlers::handle_rejection);
+
+    // Assigns the request id, echoed in the response, and logs every request within its span
+    let routes = warp::header::optional::<String>(logging::REQUEST_ID_HEADER)
+        .map(logging::request_id_from_header)
+        .and(warp::any().map(Instant::now))
+        .and(routes)
+        .then(|request_id, started_at, reply: _| async move {
+            logging::finalize_response(request_id, started_at, warp::Reply::into_response(reply)).await
+        })
+        .with(warp::trace(logging::request_span));
+
+    // Records the request metrics of every route, rejections included
+    let in_flight_metrics = metrics.clone();
+    warp::any()
+        .map(move || in_flight_metrics.track_in_flight_request())
+        .and(routes)
+        .map(|_in_flight_request, reply| reply)
+        .with(warp::log::custom(move |info| {
+            metrics.observe_request(info.path(), info.method().as_str(), info.status().as_u16(), info.elapsed());
+        }))
+}
+
+/// Serves the routes with the default configuration on an ephemeral local port, for the tests of the clients.
+/// Returns the URL of the server, e.g. `http://127.0.0.1:40000`.
+#[cfg(test)]
+pub fn serve_ephemeral(server: SharedServer) -> String {
+    let routes = routes(server, Arc::new(Metrics::new()), &FeaturesConfig::default(), &CorsConfig::default());
+    let (address, serving) = warp::serve(routes).bind_ephemeral(([127, 0, 0, 1], 0));
+    tokio::spawn(serving);
+    format!("http://{}", address)
+}
+
+/// Marks the replies of the unversioned paths of the REST API as deprecated, pointing to their versioned successor.
+fn deprecated_alias_reply(path: warp::path::FullPath, reply: impl warp::Reply) -> warp::reply::Response {
+    let mut response = reply.into_response();
+    let headers = response.headers_mut();
+    headers.insert("Deprecation", HeaderValue::from_str(&format!("@{}", UNVERSIONED_API_DEPRECATION_DATE)).unwrap());
+    if let Ok(link) = HeaderValue::from_str(&format!("</{}{}>; rel=\"successor-version\"", API_VERSION, path.as_str())) {
+        headers.insert("Link", link);
+    }
+    response
+}
+
+/// CORS policy letting the browser clients hosted on the allowed origins call the API, if any origin is allowed.
+fn cors_policy(cors: &CorsConfig) -> Option<warp::cors::Builder> {
+    if cors.allowed_origins.is_empty() {
+        return None;
+    }
+    let cors_policy = warp::cors()
+        .allow_methods(["GET", "POST", "DELETE"])
+        .allow_headers(["content-type", logging::REQUEST_ID_HEADER])
+        .expose_header(logging::REQUEST_ID_HEADER);
+    if cors.allowed_origins.iter().any(|origin| origin == "*") {
+        Some(cors_policy.allow_any_origin())
+    } else {
+        Some(cors_policy.allow_origins(cors.allowed_origins.iter().map(String::as_str)))
+    }
+}
+
+/// Whether the request comes from a page served by this server, or from a client that is not a browser:
+/// its `Origin`, if any, names the host it was sent to.
+fn is_same_origin(origin: Option<&str>, host: Option<&str>) -> bool {
+    let Some(origin) = origin else {
+        return true;
+    };
+    let origin_authority = origin.parse::<warp::http::Uri>().ok().and_then(|origin| origin.authority().cloned());
+    matches!((origin_authority, host), (Some(origin_authority), Some(host)) if origin_authority.as_str().eq_ignore_ascii_case(host))
+}
+
+/// Passes the same-origin requests only, or the cross-origin ones only, rejecting the others as not found
+/// so that the rejection of the route actually tried prevails.
+fn origin_filter(same_origin: bool) -> impl Filter<Extract = (), Error = warp::Rejection> + Clone {
+    warp::header::optional::<String>("origin")
+        .and(warp::header::optional::<String>("host"))
+        .and_then(move |origin: Option<String>, host: Option<String>| async move {
+            if is_same_origin(origin.as_deref(), host.as_deref()) == same_origin {
+                Ok(())
+            } else {
+                Err(warp::reject::not_found())
+            }
+        })
+        .untuple_one()
+}
+
+/// Rejects the requests to the routes of the features disabled in the configuration, as if they did not exist.
+fn enabled_feature_filter(features: FeaturesConfig) -> impl Filter<Extract = (), Error = warp::Rejection> + Clone {
+    warp::path::full()
+        .and_then(move |path: warp::path::FullPath| {
+            let features = features.clone();
+            async move {
+                let is_enabled = match metrics::route_label(path.as_str()) {
+                    "/" | "/web/:file_name" => features.web_client,
+                    "/metrics" => features.metrics,
+                    "/rooms/:room_name/webhooks"
+                    | "/rooms/:room_name/webhooks/deliveries"
+                    | "/rooms/:room_name/hooks"
+                    | "/hooks/:token" => features.webhooks,
+                    _ => true
+                };
+                if is_enabled { Ok(()) } else { Err(warp::reject::not_found()) }
+            }
+        })
+        .untuple_one()
 }
 
 /**
- * GET /status
+ * GET /v1/status
  * Returns 200 OK if the server is reachable, with a JSON response body
- * If there is a problem with unwrapping server reference, returns 500 Internal Server Error
+ * If there is a problem with unwrapping server reference, returns 503 SERVICE UNAVAILABLE
  */
-fn is_server_reachable_route(server: Arc<Mutex<Server>>) -> impl Filter<Extract = impl warp::Reply, Error = warp::Rejection> + Clone {
-    warp::path("status")
+fn is_server_reachable_route(server: SharedServer) -> impl Filter<Extract = impl warp::Reply, Error = warp::Rejection> + Clone {
+    warp::path!("status")
         .and(warp::get())
         .and(with_server(server.clone()))
         .and_then(handlers::is_server_reachable)
 }
 
-fn with_server(server: Arc<Mutex<Server>>) -> impl Filter<Extract = (Arc<Mutex<Server>>,), Error = std::convert::Infallible> + Clone {
+/**
+ * GET /openapi.json
+ * Returns the OpenAPI 3 document describing every route of the REST API, with its schemas and error ids.
+ * Returns 200 OK.
+ */
+fn get_openapi_document_route() -> impl Filter<Extract = impl warp::Reply, Error = warp::Rejection> + Clone {
+    warp::path!("openapi.json")
+        .and(warp::get())
+        .and_then(handlers::get_openapi_document)
+}
+
+/**
+ * GET /metrics
+ * Returns the metrics of the server in the Prometheus text format:
+ * request counts and latencies per route, registered users, rooms, messages and in-flight requests.
+ * Returns 200 OK.
+ */
+fn get_metrics_route(metrics: Arc<Metrics>, server: SharedServer) -> impl Filter<Extract = impl warp::Reply, Error = warp::Rejection> + Clone {
+    warp::path!("metrics")
+        .and(warp::get())
+        .and(warp::any().map(move || metrics.clone()))
+        .and(with_server(server))
+        .and_then(handlers::get_metrics)
+}
+
+fn with_server(server: SharedServer) -> impl Filter<Extract = (SharedServer,), Error = std::convert::Infallible> + Clone {
     warp::any().map(move || server.clone())
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::entities::server::Server;
     use warp::http::StatusCode;
 
     #[tokio::test]
     async fn test_is_server_reachable_route() {
-        let server = Arc::new(Mutex::new(Server::new()));
+        let server = Server::new_shared();
         let route = is_server_reachable_route(server.clone());
 
         let response = warp::test::request()
@@ -47,4 +239,253 @@ mod tests {
 
         assert_eq!(response.status(), StatusCode::OK);
     }
-}
\ No newline at end of file
+
+    #[tokio::test]
+    async fn test_get_metrics_route() {
+        let server = Server::new_shared();
+        server.write().unwrap().register_user("test_user").unwrap();
+        let routes = routes(server.clone(), Arc::new(Metrics::new()), &FeaturesConfig::default(), &CorsConfig::default());
+
+        warp::test::request()
+            .method("GET")
+            .path("/v1/users/test_user")
+            .reply(&routes)
+            .await;
+        let response = warp::test::request()
+            .method("GET")
+            .path("/metrics")
+            .reply(&routes)
+            .await;
+
+        assert_eq!(response.status(), StatusCode::OK);
+        let metrics_text = String::from_utf8(response.body().to_vec()).unwrap();
+        assert!(metrics_text.contains(r#"http_requests_total{method="GET",route="/users/:username",status="200"} 1"#));
+        assert!(metrics_text.contains("chat_registered_users 1"));
+    }
+
+    #[tokio::test]
+    async fn test_get_openapi_document_route() {
+        let server = Server::new_shared();
+        let routes = routes(server.clone(), Arc::new(Metrics::new()), &FeaturesConfig::default(), &CorsConfig::default());
+
+        let response = warp::test::request()
+            .method("GET")
+            .path("/openapi.json")
+            .reply(&routes)
+            .await;
+
+        assert_eq!(response.status(), StatusCode::OK);
+        let document: serde_json::Value = serde_json::from_slice(response.body()).unwrap();
+        assert_eq!(document["openapi"], "3.0.3");
+        assert!(document["paths"]["/v1/rooms/{room_name}/messages"]["post"].is_object());
+    }
+
+    #[tokio::test]
+    async fn test_routes_versioned_api() {
+        let server = Server::new_shared();
+        server.write().unwrap().register_user("test_user").unwrap();
+        let routes = routes(server.clone(), Arc::new(Metrics::new()), &FeaturesConfig::default(), &CorsConfig::default());
+
+        let response = warp::test::request()
+            .method("GET")
+            .path("/v1/users/test_user")
+            .reply(&routes)
+            .await;
+
+        assert_eq!(response.status(), StatusCode::OK);
+        assert!(response.headers().get("Deprecation").is_none());
+
+        let response = warp::test::request()
+            .method("GET")
+            .path("/v2/users/test_user")
+            .reply(&routes)
+            .await;
+
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_routes_unversioned_api_deprecated_aliases() {
+        let server = Server::new_shared();
+        server.write().unwrap().register_user("test_user").unwrap();
+        let routes = routes(server.clone(), Arc::new(Metrics::new()), &FeaturesConfig::default(), &CorsConfig::default());
+
+        let response = warp::test::request()
+            .method("GET")
+            .path("/users/test_user")
+            .reply(&routes)
+            .await;
+
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()["Deprecation"], "@1792281600");
+        assert_eq!(response.headers()["Link"], r#"</v1/users/test_user>; rel="successor-version""#);
+
+        let response = warp::test::request()
+            .method("GET")
+            .path("/health/live")
+            .reply(&routes)
+            .await;
+
+        assert_eq!(response.status(), StatusCode::OK);
+        assert!(response.headers().get("Deprecation").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_routes_echo_request_id_in_errors() {
+        let server = Server::new_shared();
+        let routes = routes(server.clone(), Arc::new(Metrics::new()), &FeaturesConfig::default(), &CorsConfig::default());
+
+        let response = warp::test::request()
+            .method("GET")
+            .path("/v1/rooms/test_room")
+            .header(logging::REQUEST_ID_HEADER, "abc-123")
+            .reply(&routes)
+            .await;
+
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        assert_eq!(response.headers()[logging::REQUEST_ID_HEADER], "abc-123");
+        let error_details: handlers::ErrorDetailsResponse = serde_json::from_slice(response.body()).unwrap();
+        assert_eq!(error_details.error_id, "ERR__ROOM_NOT_FOUND");
+        assert_eq!(error_details.request_id, Some("abc-123".to_string()));
+    }
+
+    #[tokio::test]
+    async fn test_routes_unmatched_route() {
+        let server = Server::new_shared();
+        let routes = routes(server.clone(), Arc::new(Metrics::new()), &FeaturesConfig::default(), &CorsConfig::default());
+
+        let response = warp::test::request()
+            .method("GET")
+            .path("/unknown")
+            .reply(&routes)
+            .await;
+
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        let error_details: handlers::ErrorDetailsResponse = serde_json::from_slice(response.body()).unwrap();
+        assert_eq!(error_details.error_id, "ERR__ROUTE_NOT_FOUND");
+        assert_eq!(error_details.request_id.as_deref(), response.headers()[logging::REQUEST_ID_HEADER].to_str().ok());
+    }
+
+    #[tokio::test]
+    async fn test_routes_disabled_features() {
+        let server = Server::new_shared();
+        server.write().unwrap().register_user("test_user").unwrap();
+        server.write().unwrap().create_room("test_room", "test_user").unwrap();
+        let features = FeaturesConfig { webhooks: false, metrics: false, web_client: false };
+        let routes = routes(server.clone(), Arc::new(Metrics::new()), &features, &CorsConfig::default());
+
+        for path in ["/", "/web/app.js", "/metrics", "/rooms/test_room/webhooks", "/v1/rooms/test_room/webhooks", "/rooms/test_room/hooks"] {
+            let response = warp::test::request()
+                .method("GET")
+                .path(path)
+                .reply(&routes)
+                .await;
+            assert_eq!(response.status(), StatusCode::NOT_FOUND, "{}", path);
+        }
+        let response = warp::test::request()
+            .method("GET")
+            .path("/rooms/test_room")
+            .reply(&routes)
+            .await;
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_routes_cors() {
+        let server = Server::new_shared();
+        let cors = CorsConfig { allowed_origins: vec!["https://chat.example.com".to_string()] };
+        let routes = routes(server.clone(), Arc::new(Metrics::new()), &FeaturesConfig::default(), &cors);
+
+        let response = warp::test::request()
+            .method("OPTIONS")
+            .path("/rooms/test_room/messages")
+            .header("origin", "https://chat.example.com")
+            .header("access-control-request-method", "POST")
+            .header("access-control-request-headers", "content-type")
+            .reply(&routes)
+            .await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()["access-control-allow-origin"], "https://chat.example.com");
+
+        let response = warp::test::request()
+            .method("GET")
+            .path("/rooms")
+            .header("origin", "https://chat.example.com")
+            .reply(&routes)
+            .await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()["access-control-allow-origin"], "https://chat.example.com");
+
+        let response = warp::test::request()
+            .method("GET")
+            .path("/rooms")
+            .header("origin", "https://evil.example.com")
+            .reply(&routes)
+            .await;
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+        let error_details: handlers::ErrorDetailsResponse = serde_json::from_slice(response.body()).unwrap();
+        assert_eq!(error_details.error_id, "ERR__ORIGIN_NOT_ALLOWED");
+    }
+
+    #[tokio::test]
+    async fn test_routes_web_client_with_cors() {
+        let server = Server::new_shared();
+        let cors = CorsConfig { allowed_origins: vec!["https://chat.example.com".to_string()] };
+        let routes = routes(server.clone(), Arc::new(Metrics::new()), &FeaturesConfig::default(), &cors);
+
+        // As sent by the browser from the bundled web client, served by this same server
+        let response = warp::test::request()
+            .method("POST")
+            .path("/v1/users/test_user")
+            .header("host", "127.0.0.1:3030")
+            .header("origin", "http://127.0.0.1:3030")
+            .reply(&routes)
+            .await;
+        assert_eq!(response.status(), StatusCode::CREATED);
+        assert!(!response.headers().contains_key("access-control-allow-origin"));
+
+        let response = warp::test::request()
+            .method("GET")
+            .path("/v1/unknown")
+            .header("host", "127.0.0.1:3030")
+            .header("origin", "http://127.0.0.1:3030")
+            .reply(&routes)
+            .await;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+        let response = warp::test::request()
+            .method("POST")
+            .path("/v1/users/other_user")
+            .header("host", "127.0.0.1:3030")
+            .header("origin", "http://evil.example.com")
+            .reply(&routes)
+            .await;
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+        assert!(server.read().unwrap().get_user_by_username("other_user").is_none());
+    }
+
+    #[test]
+    fn test_is_same_origin() {
+        assert!(is_same_origin(None, Some("127.0.0.1:3030")));
+        assert!(is_same_origin(Some("http://127.0.0.1:3030"), Some("127.0.0.1:3030")));
+        assert!(is_same_origin(Some("https://Chat.example.com"), Some("chat.example.com")));
+        assert!(!is_same_origin(Some("https://chat.example.com"), Some("chat.example.com:8443")));
+        assert!(!is_same_origin(Some("https://chat.example.com"), None));
+        assert!(!is_same_origin(Some("null"), Some("chat.example.com")));
+    }
+
+    #[tokio::test]
+    async fn test_routes_same_origin_without_cors() {
+        let server = Server::new_shared();
+        let routes = routes(server.clone(), Arc::new(Metrics::new()), &FeaturesConfig::default(), &CorsConfig::default());
+
+        let response = warp::test::request()
+            .method("GET")
+            .path("/rooms")
+            .header("origin", "http://127.0.0.1:3030")
+            .reply(&routes)
+            .await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert!(!response.headers().contains_key("access-control-allow-origin"));
+    }
+}
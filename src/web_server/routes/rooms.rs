@@ -1,24 +1,57 @@
 use crate::web_server::handlers;
-use crate::entities::server::Server;
-use std::{collections::HashMap, sync::{Arc, Mutex}};
+use crate::entities::server::SharedServer;
+use std::collections::HashMap;
+use uuid::Uuid;
 use warp::Filter;
 use super::with_server;
 
-pub fn rooms_routes(server: Arc<Mutex<Server>>) -> impl Filter<Extract = impl warp::Reply, Error = warp::Rejection> + Clone {
-    get_room_by_name(server.clone())
+pub fn rooms_routes(server: SharedServer) -> impl Filter<Extract = impl warp::Reply, Error = warp::Rejection> + Clone {
+    get_rooms(server.clone())
+        .or(get_room_by_id(server.clone()))
+        .or(get_room_by_name(server.clone()))
         .or(get_user_in_room_by_name(server.clone()))
         .or(create_room(server.clone()))
         .or(add_user_to_room(server.clone()))
+        .or(remove_user_from_room(server.clone()))
         .or(get_room_messages(server.clone()))
         .or(post_message_to_room(server.clone()))
+        .or(export_room_messages(server.clone()))
+        .or(register_webhook_to_room(server.clone()))
+        .or(get_room_webhooks(server.clone()))
+        .or(get_room_webhook_deliveries(server.clone()))
+        .or(create_incoming_webhook(server.clone()))
 }
 
 /**
- * GET /rooms/:room_name
+ * GET /v1/rooms
+ * Returns all rooms in the server.
+ * Returns 200 OK with the list of rooms, empty if there are none.
+ */
+fn get_rooms(server: SharedServer) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
+    warp::path!("rooms")
+        .and(warp::get())
+        .and(with_server(server))
+        .and_then(handlers::rooms::get_rooms)
+}
+
+/**
+ * GET /v1/rooms/by-id/:room_id
+ * Returns the room registered in the server with the given id.
+ * Returns 200 OK if the room exists in the server, 404 NOT FOUND otherwise.
+ */
+fn get_room_by_id(server: SharedServer) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
+    warp::path!("rooms" / "by-id" / Uuid)
+        .and(warp::get())
+        .and(with_server(server))
+        .and_then(handlers::rooms::get_room_by_id)
+}
+
+/**
+ * GET /v1/rooms/:room_name
  * Checks if a room exists in the server and returns it.
  * Returns 200 OK if the room exists in the server, 404 NOT FOUND otherwise.
  */
-fn get_room_by_name(server: Arc<Mutex<Server>>) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
+fn get_room_by_name(server: SharedServer) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
     warp::path!("rooms" / String)
         .and(warp::get())
         .and(with_server(server))
@@ -26,11 +59,11 @@ fn get_room_by_name(server: Arc<Mutex<Server>>) -> impl Filter<Extract = (impl w
 }
 
 /**
- * GET /rooms/:room_name/users/:username
+ * GET /v1/rooms/:room_name/users/:username
  * Checks if a user exists in a room and returns it.
- * Returns 200 OK if the user exists in the room, 404 NOT FOUND otherwise.
+ * Returns 200 OK if the user exists in the room, 404 NOT FOUND if the room or user do not exist or if the user is not in the room.
  */
-fn get_user_in_room_by_name(server: Arc<Mutex<Server>>) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
+fn get_user_in_room_by_name(server: SharedServer) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
     warp::path!("rooms" / String / "users" / String)
         .and(warp::get())
         .and(with_server(server))
@@ -38,12 +71,12 @@ fn get_user_in_room_by_name(server: Arc<Mutex<Server>>) -> impl Filter<Extract =
 }
 
 /**
- * POST /rooms/:room_name?creator_username=:username
+ * POST /v1/rooms/:room_name?creator_username=:username
  * Creates a new room in the server and adds the creator user to it.
- * Returns 201 CREATED if the room was successfully created, 409 CONFLICT if a conflict occurs.
+ * Returns 201 CREATED if the room was successfully created, 404 NOT FOUND if the creator user does not exist, 409 CONFLICT if the room already exists.
  * If missing query parameter, returns 400 BAD REQUEST.
  */
-fn create_room(server: Arc<Mutex<Server>>) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
+fn create_room(server: SharedServer) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
     warp::path!("rooms" / String)
         .and(warp::post())
         .and(warp::query::<HashMap<String, String>>())
@@ -52,11 +85,11 @@ fn create_room(server: Arc<Mutex<Server>>) -> impl Filter<Extract = (impl warp::
 }
 
 /**
- * POST /rooms/:room_name/users/:username
+ * POST /v1/rooms/:room_name/users/:username
  * Adds a user to a room in the server.
- * Returns 200 OK if the user was successfully added to the room, 409 CONFLICT if a conflict occurs.
+ * Returns 201 CREATED if the user was successfully added to the room, 404 NOT FOUND if the room or user do not exist, 409 CONFLICT if the user is already in the room.
  */
-fn add_user_to_room(server: Arc<Mutex<Server>>) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
+fn add_user_to_room(server: SharedServer) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
     warp::path!("rooms" / String / "users" / String)
         .and(warp::post())
         .and(with_server(server))
@@ -64,33 +97,119 @@ fn add_user_to_room(server: Arc<Mutex<Server>>) -> impl Filter<Extract = (impl w
 }
 
 /**
- * GET /rooms/:room_name/messages
- * Returns all messages in the room.
- * Returns 200 OK if the room exists in the server, 409 CONFLICT if a conflict occurs.
+ * DELETE /v1/rooms/:room_name/users/:username
+ * Removes a user from a room in the server.
+ * Returns 200 OK if the user was successfully removed from the room, 404 NOT FOUND if the room or user do not exist, 403 FORBIDDEN if the user is not in the room.
  */
-fn get_room_messages(server: Arc<Mutex<Server>>) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
+fn remove_user_from_room(server: SharedServer) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
+    warp::path!("rooms" / String / "users" / String)
+        .and(warp::delete())
+        .and(with_server(server))
+        .and_then(handlers::rooms::remove_user_from_room)
+}
+
+/**
+ * GET /v1/rooms/:room_name/messages?after_seq=:seq
+ * Returns all messages in the room, or only the ones posted after the given sequence number if any.
+ * Returns 200 OK if the room exists in the server, 404 NOT FOUND otherwise.
+ * If the sequence number is invalid, returns 400 BAD REQUEST.
+ */
+fn get_room_messages(server: SharedServer) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
     warp::path!("rooms" / String / "messages")
         .and(warp::get())
+        .and(warp::query::<HashMap<String, String>>())
         .and(with_server(server))
         .and_then(handlers::rooms::get_room_messages)
 }
 
 /**
- * POST /rooms/:room_name/messages
+ * POST /v1/rooms/:room_name/messages
  * Adds a message to the room.
- * Expects a JSON body with the username and message fields.
- * Returns 200 OK if the message was successfully added to the room, 409 CONFLICT if a conflict occurs.
+ * Expects a JSON body with the username and message fields, and an optional client_message_id field or Idempotency-Key header.
+ * Returns 201 CREATED if the message was successfully added to the room, 404 NOT FOUND if the room or user do not exist, 403 FORBIDDEN if the user is not in the room.
+ * Returns 200 OK with the message posted the first time if the same message was already posted with this client message id,
+ * 409 CONFLICT if another message was.
  * If missing fields, returns 400 BAD REQUEST.
  * When OK returns the message.
  */
-fn post_message_to_room(server: Arc<Mutex<Server>>) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
+fn post_message_to_room(server: SharedServer) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
     warp::path!("rooms" / String / "messages")
         .and(warp::post())
+        .and(warp::header::optional::<String>("idempotency-key"))
         .and(warp::body::json())
         .and(with_server(server))
         .and_then(handlers::rooms::post_message_to_room)
 }
 
+/**
+ * GET /v1/rooms/:room_name/export?format=:format&since=:timestamp&until=:timestamp
+ * Exports the messages of the room as JSON lines (the default), CSV, a Markdown transcript or a standalone HTML page,
+ * optionally only the ones posted from since, included, to until, excluded, both RFC 3339 timestamps.
+ * Returns 200 OK with the export streamed as an attachment, 404 NOT FOUND if the room does not exist.
+ * If the format or a timestamp is invalid, returns 400 BAD REQUEST.
+ */
+fn export_room_messages(server: SharedServer) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
+    warp::path!("rooms" / String / "export")
+        .and(warp::get())
+        .and(warp::query::<HashMap<String, String>>())
+        .and(with_server(server))
+        .and_then(handlers::rooms::export_room_messages)
+}
+
+/**
+ * POST /v1/rooms/:room_name/webhooks
+ * Registers an outgoing webhook in the room, notified on every message posted and user joined.
+ * Expects a JSON body with the url field and an optional secret field, generated if missing.
+ * Returns 201 CREATED with the webhook and its signing secret, 404 NOT FOUND if the room does not exist, 409 CONFLICT if the URL is already registered.
+ * If missing fields or invalid URL, returns 400 BAD REQUEST.
+ */
+fn register_webhook_to_room(server: SharedServer) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
+    warp::path!("rooms" / String / "webhooks")
+        .and(warp::post())
+        .and(warp::body::json())
+        .and(with_server(server))
+        .and_then(handlers::rooms::register_webhook_to_room)
+}
+
+/**
+ * GET /v1/rooms/:room_name/webhooks
+ * Returns all webhooks registered in the room, without their secrets.
+ * Returns 200 OK if the room exists in the server, 404 NOT FOUND otherwise.
+ */
+fn get_room_webhooks(server: SharedServer) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
+    warp::path!("rooms" / String / "webhooks")
+        .and(warp::get())
+        .and(with_server(server))
+        .and_then(handlers::rooms::get_room_webhooks)
+}
+
+/**
+ * GET /v1/rooms/:room_name/webhooks/deliveries
+ * Returns the log of the most recent webhook delivery attempts in the room.
+ * Returns 200 OK if the room exists in the server, 404 NOT FOUND otherwise.
+ */
+fn get_room_webhook_deliveries(server: SharedServer) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
+    warp::path!("rooms" / String / "webhooks" / "deliveries")
+        .and(warp::get())
+        .and(with_server(server))
+        .and_then(handlers::rooms::get_room_webhook_deliveries)
+}
+
+/**
+ * POST /v1/rooms/:room_name/hooks?bot_name=:bot_name
+ * Creates an incoming webhook bound to the room, returning the token to be used with POST /v1/hooks/:token.
+ * Messages posted through it are authored by a bot with the given name, not registered in the server users.
+ * Returns 201 CREATED with the incoming webhook, 404 NOT FOUND if the room does not exist,
+ * 400 BAD REQUEST if the bot name is empty or the username of a registered user.
+ */
+fn create_incoming_webhook(server: SharedServer) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
+    warp::path!("rooms" / String / "hooks")
+        .and(warp::post())
+        .and(warp::query::<HashMap<String, String>>())
+        .and(with_server(server))
+        .and_then(handlers::rooms::create_incoming_webhook)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -101,11 +220,46 @@ mod tests {
     use warp::test::request;
     use crate::web_server::handlers::ErrorDetailsResponse;
 
+    #[tokio::test]
+    async fn test_get_rooms() {
+        let server = Server::new_shared();
+        server.clone().write().unwrap().register_user("test_user").unwrap();
+        server.clone().write().unwrap().create_room("test_room", "test_user").unwrap();
+        server.clone().write().unwrap().create_room("test_room2", "test_user").unwrap();
+
+        let response = request()
+            .method("GET")
+            .path("/rooms")
+            .reply(&rooms_routes(server.clone()))
+            .await;
+
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let rooms: Vec<serde_json::Value> = serde_json::from_slice(response.body()).unwrap();
+        assert_eq!(rooms.len(), 2);
+        assert_eq!(rooms[0]["name"], "test_room");
+        assert_eq!(rooms[1]["name"], "test_room2");
+    }
+
+    #[tokio::test]
+    async fn test_get_rooms_empty() {
+        let server = Server::new_shared();
+
+        let response = request()
+            .method("GET")
+            .path("/rooms")
+            .reply(&rooms_routes(server.clone()))
+            .await;
+
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(std::str::from_utf8(response.body()).unwrap(), "[]");
+    }
+
     #[tokio::test]
     async fn test_get_room_by_name() {
-        let server = Arc::new(Mutex::new(Server::new()));
-        server.clone().lock().unwrap().register_user("test_user").unwrap();
-        server.clone().lock().unwrap().create_room("test_room", "test_user").unwrap();
+        let server = Server::new_shared();
+        server.clone().write().unwrap().register_user("test_user").unwrap();
+        server.clone().write().unwrap().create_room("test_room", "test_user").unwrap();
 
         let response = request()
             .method("GET")
@@ -120,9 +274,44 @@ mod tests {
         assert!(response_string.contains("test_room"));
     }
 
+    #[tokio::test]
+    async fn test_get_room_by_id() {
+        let server = Server::new_shared();
+        server.clone().write().unwrap().register_user("test_user").unwrap();
+        server.clone().write().unwrap().create_room("test_room", "test_user").unwrap();
+        let room_id = server.read().unwrap().get_room_by_name("test_room").unwrap().read().unwrap().id;
+
+        let response = request()
+            .method("GET")
+            .path(&format!("/rooms/by-id/{}", room_id))
+            .reply(&rooms_routes(server.clone()))
+            .await;
+
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let response_string: String = String::from_utf8(response.body().to_vec()).unwrap();
+        assert!(response_string.contains("test_room"));
+    }
+
+    #[tokio::test]
+    async fn test_get_room_by_id_room_not_found() {
+        let server = Server::new_shared();
+
+        let response = request()
+            .method("GET")
+            .path(&format!("/rooms/by-id/{}", uuid::Uuid::new_v4()))
+            .reply(&rooms_routes(server.clone()))
+            .await;
+
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+        let error_response: ErrorDetailsResponse = serde_json::from_slice(response.body()).unwrap();
+        assert_eq!(error_response.error_id, "ERR__ROOM_NOT_FOUND");
+    }
+
     #[tokio::test]
     async fn test_get_room_by_name_room_not_found() {
-        let server = Arc::new(Mutex::new(Server::new()));
+        let server = Server::new_shared();
 
         let response = request()
             .method("GET")
@@ -139,9 +328,9 @@ mod tests {
 
     #[tokio::test]
     async fn test_get_user_in_room_by_name() {
-        let server = Arc::new(Mutex::new(Server::new()));
-        server.clone().lock().unwrap().register_user("test_user").unwrap();
-        server.clone().lock().unwrap().create_room("test_room", "test_user").unwrap();
+        let server = Server::new_shared();
+        server.clone().write().unwrap().register_user("test_user").unwrap();
+        server.clone().write().unwrap().create_room("test_room", "test_user").unwrap();
 
         let response = request()
             .method("GET")
@@ -158,8 +347,8 @@ mod tests {
 
     #[tokio::test]
     async fn test_get_user_in_room_by_name_room_not_found() {
-        let server = Arc::new(Mutex::new(Server::new()));
-        server.clone().lock().unwrap().register_user("test_user").unwrap();
+        let server = Server::new_shared();
+        server.clone().write().unwrap().register_user("test_user").unwrap();
 
         let response = request()
             .method("GET")
@@ -176,9 +365,9 @@ mod tests {
 
     #[tokio::test]
     async fn test_get_user_in_room_by_name_user_not_found() {
-        let server = Arc::new(Mutex::new(Server::new()));
-        server.clone().lock().unwrap().register_user("test_user").unwrap();
-        server.clone().lock().unwrap().create_room("test_room", "test_user").unwrap();
+        let server = Server::new_shared();
+        server.clone().write().unwrap().register_user("test_user").unwrap();
+        server.clone().write().unwrap().create_room("test_room", "test_user").unwrap();
 
         let response = request()
             .method("GET")
@@ -193,10 +382,29 @@ mod tests {
         assert_eq!(error.error_id, "ERR__USER_NOT_FOUND");
     }
 
+    #[tokio::test]
+    async fn test_get_user_in_room_by_name_user_not_in_room() {
+        let server = Server::new_shared();
+        server.clone().write().unwrap().register_user("test_user").unwrap();
+        server.clone().write().unwrap().create_room("test_room", "test_user").unwrap();
+        server.clone().write().unwrap().register_user("test_user2").unwrap();
+
+        let response = request()
+            .method("GET")
+            .path("/rooms/test_room/users/test_user2")
+            .reply(&rooms_routes(server.clone()))
+            .await;
+
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+        let error: ErrorDetailsResponse = serde_json::from_slice(response.body()).unwrap();
+        assert_eq!(error.error_id, "ERR__USER_NOT_IN_ROOM");
+    }
+
     #[tokio::test]
     async fn test_create_room() {
-        let server = Arc::new(Mutex::new(Server::new()));
-        server.clone().lock().unwrap().register_user("test_user").unwrap();
+        let server = Server::new_shared();
+        server.clone().write().unwrap().register_user("test_user").unwrap();
 
         let response = request()
             .method("POST")
@@ -213,8 +421,8 @@ mod tests {
 
     #[tokio::test]
     async fn test_create_room_missing_query_param() {
-        let server = Arc::new(Mutex::new(Server::new()));
-        server.clone().lock().unwrap().register_user("test_user").unwrap();
+        let server = Server::new_shared();
+        server.clone().write().unwrap().register_user("test_user").unwrap();
 
         let response = request()
             .method("POST")
@@ -226,12 +434,12 @@ mod tests {
         assert_ne!(response.body().len(), 0);
         
         let error: ErrorDetailsResponse = serde_json::from_slice(response.body()).unwrap();
-        assert_eq!(error.error_id, "ERR__ROOM_CREATE_BAD_REQUEST");
+        assert_eq!(error.error_id, "ERR__BAD_REQUEST");
     }
 
     #[tokio::test]
     async fn test_create_room_user_not_found() {
-        let server = Arc::new(Mutex::new(Server::new()));
+        let server = Server::new_shared();
 
         let response = request()
             .method("POST")
@@ -239,18 +447,18 @@ mod tests {
             .reply(&rooms_routes(server.clone()))
             .await;
 
-        assert_eq!(response.status(), StatusCode::CONFLICT);
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
         assert_ne!(response.body().len(), 0);
         
         let error: ErrorDetailsResponse = serde_json::from_slice(response.body()).unwrap();
-        assert_eq!(error.error_id, "ERR__ROOM_CREATE_CONFLICT");
+        assert_eq!(error.error_id, "ERR__USER_NOT_FOUND");
     }
 
     #[tokio::test]
     async fn test_create_room_room_already_exists() {
-        let server = Arc::new(Mutex::new(Server::new()));
-        server.clone().lock().unwrap().register_user("test_user").unwrap();
-        server.clone().lock().unwrap().create_room("test_room", "test_user").unwrap();
+        let server = Server::new_shared();
+        server.clone().write().unwrap().register_user("test_user").unwrap();
+        server.clone().write().unwrap().create_room("test_room", "test_user").unwrap();
 
         let response = request()
             .method("POST")
@@ -262,15 +470,15 @@ mod tests {
         assert_ne!(response.body().len(), 0);
         
         let error: ErrorDetailsResponse = serde_json::from_slice(response.body()).unwrap();
-        assert_eq!(error.error_id, "ERR__ROOM_CREATE_CONFLICT");
+        assert_eq!(error.error_id, "ERR__ROOM_ALREADY_EXISTS");
     }
 
     #[tokio::test]
     async fn test_add_user_to_room() {
-        let server = Arc::new(Mutex::new(Server::new()));
-        server.clone().lock().unwrap().register_user("test_user").unwrap();
-        server.clone().lock().unwrap().create_room("test_room", "test_user").unwrap();
-        server.clone().lock().unwrap().register_user("test_user2").unwrap();
+        let server = Server::new_shared();
+        server.clone().write().unwrap().register_user("test_user").unwrap();
+        server.clone().write().unwrap().create_room("test_room", "test_user").unwrap();
+        server.clone().write().unwrap().register_user("test_user2").unwrap();
 
         let response = request()
             .method("POST")
@@ -287,8 +495,8 @@ mod tests {
 
     #[tokio::test]
     async fn test_add_user_to_room_room_not_found() {
-        let server = Arc::new(Mutex::new(Server::new()));
-        server.clone().lock().unwrap().register_user("test_user").unwrap();
+        let server = Server::new_shared();
+        server.clone().write().unwrap().register_user("test_user").unwrap();
 
         let response = request()
             .method("POST")
@@ -296,18 +504,18 @@ mod tests {
             .reply(&rooms_routes(server.clone()))
             .await;
 
-        assert_eq!(response.status(), StatusCode::CONFLICT);
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
         assert_ne!(response.body().len(), 0);
         
         let error: ErrorDetailsResponse = serde_json::from_slice(response.body()).unwrap();
-        assert_eq!(error.error_id, "ERR__USER_ADD_TO_ROOM_CONFLICT");
+        assert_eq!(error.error_id, "ERR__ROOM_NOT_FOUND");
     }
 
     #[tokio::test]
     async fn test_add_user_to_room_user_not_found() {
-        let server = Arc::new(Mutex::new(Server::new()));
-        server.clone().lock().unwrap().register_user("test_user").unwrap();
-        server.clone().lock().unwrap().create_room("test_room", "test_user").unwrap();
+        let server = Server::new_shared();
+        server.clone().write().unwrap().register_user("test_user").unwrap();
+        server.clone().write().unwrap().create_room("test_room", "test_user").unwrap();
 
         let response = request()
             .method("POST")
@@ -315,18 +523,18 @@ mod tests {
             .reply(&rooms_routes(server.clone()))
             .await;
 
-        assert_eq!(response.status(), StatusCode::CONFLICT);
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
         assert_ne!(response.body().len(), 0);
         
         let error: ErrorDetailsResponse = serde_json::from_slice(response.body()).unwrap();
-        assert_eq!(error.error_id, "ERR__USER_ADD_TO_ROOM_CONFLICT");
+        assert_eq!(error.error_id, "ERR__USER_NOT_FOUND");
     }
 
     #[tokio::test]
     async fn test_add_user_to_room_user_already_in_room() {
-        let server = Arc::new(Mutex::new(Server::new()));
-        server.clone().lock().unwrap().register_user("test_user").unwrap();
-        server.clone().lock().unwrap().create_room("test_room", "test_user").unwrap();
+        let server = Server::new_shared();
+        server.clone().write().unwrap().register_user("test_user").unwrap();
+        server.clone().write().unwrap().create_room("test_room", "test_user").unwrap();
 
         let response = request()
             .method("POST")
@@ -338,15 +546,54 @@ mod tests {
         assert_ne!(response.body().len(), 0);
         
         let error: ErrorDetailsResponse = serde_json::from_slice(response.body()).unwrap();
-        assert_eq!(error.error_id, "ERR__USER_ADD_TO_ROOM_CONFLICT");
+        assert_eq!(error.error_id, "ERR__USER_ALREADY_IN_ROOM");
+    }
+
+    #[tokio::test]
+    async fn test_remove_user_from_room() {
+        let server = Server::new_shared();
+        server.clone().write().unwrap().register_user("test_user").unwrap();
+        server.clone().write().unwrap().create_room("test_room", "test_user").unwrap();
+        server.clone().write().unwrap().register_user("test_user2").unwrap();
+        server.clone().write().unwrap().add_user_to_room("test_room", "test_user2").unwrap();
+
+        let response = request()
+            .method("DELETE")
+            .path("/rooms/test_room/users/test_user2")
+            .reply(&rooms_routes(server.clone()))
+            .await;
+
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let response_string = String::from_utf8(response.body().to_vec()).unwrap();
+        assert!(!response_string.contains("test_user2"));
+    }
+
+    #[tokio::test]
+    async fn test_remove_user_from_room_user_not_in_room() {
+        let server = Server::new_shared();
+        server.clone().write().unwrap().register_user("test_user").unwrap();
+        server.clone().write().unwrap().create_room("test_room", "test_user").unwrap();
+        server.clone().write().unwrap().register_user("test_user2").unwrap();
+
+        let response = request()
+            .method("DELETE")
+            .path("/rooms/test_room/users/test_user2")
+            .reply(&rooms_routes(server.clone()))
+            .await;
+
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+        let error: ErrorDetailsResponse = serde_json::from_slice(response.body()).unwrap();
+        assert_eq!(error.error_id, "ERR__USER_NOT_IN_ROOM");
     }
 
     #[tokio::test]
     async fn test_get_room_messages() {
-        let server = Arc::new(Mutex::new(Server::new()));
-        server.clone().lock().unwrap().register_user("test_user").unwrap();
-        server.clone().lock().unwrap().create_room("test_room", "test_user").unwrap();
-        server.clone().lock().unwrap().post_message_to_room("test_room", "test_user", "test message").unwrap();
+        let server = Server::new_shared();
+        server.clone().write().unwrap().register_user("test_user").unwrap();
+        server.clone().write().unwrap().create_room("test_room", "test_user").unwrap();
+        server.clone().write().unwrap().post_message_to_room("test_room", "test_user", "test message").unwrap();
 
         let response = request()
             .method("GET")
@@ -362,11 +609,43 @@ mod tests {
         assert_eq!(messages[0].author.username, "test_user");
     }
 
+    #[tokio::test]
+    async fn test_get_room_messages_after_seq() {
+        let server = Server::new_shared();
+        server.clone().write().unwrap().register_user("test_user").unwrap();
+        server.clone().write().unwrap().create_room("test_room", "test_user").unwrap();
+        for content in ["first", "second", "third"] {
+            server.clone().write().unwrap().post_message_to_room("test_room", "test_user", content).unwrap();
+        }
+
+        let response = request()
+            .method("GET")
+            .path("/rooms/test_room/messages?after_seq=1")
+            .reply(&rooms_routes(server.clone()))
+            .await;
+
+        assert_eq!(response.status(), StatusCode::OK);
+        let messages: Vec<Message> = serde_json::from_slice(response.body()).unwrap();
+        let seqs: Vec<u64> = messages.iter().map(|message| message.seq).collect();
+        assert_eq!(seqs, vec![2, 3]);
+        assert_eq!(messages[0].content, "second");
+
+        let response = request()
+            .method("GET")
+            .path("/rooms/test_room/messages?after_seq=-1")
+            .reply(&rooms_routes(server.clone()))
+            .await;
+
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        let error: ErrorDetailsResponse = serde_json::from_slice(response.body()).unwrap();
+        assert_eq!(error.error_id, "ERR__BAD_REQUEST");
+    }
+
     #[tokio::test]
     async fn test_get_room_messages_no_messages() {
-        let server = Arc::new(Mutex::new(Server::new()));
-        server.clone().lock().unwrap().register_user("test_user").unwrap();
-        server.clone().lock().unwrap().create_room("test_room", "test_user").unwrap();
+        let server = Server::new_shared();
+        server.clone().write().unwrap().register_user("test_user").unwrap();
+        server.clone().write().unwrap().create_room("test_room", "test_user").unwrap();
 
         let response = request()
             .method("GET")
@@ -382,7 +661,7 @@ mod tests {
 
     #[tokio::test]
     async fn test_get_room_messages_room_not_found() {
-        let server = Arc::new(Mutex::new(Server::new()));
+        let server = Server::new_shared();
 
         let response = request()
             .method("GET")
@@ -390,18 +669,88 @@ mod tests {
             .reply(&rooms_routes(server.clone()))
             .await;
 
-        assert_eq!(response.status(), StatusCode::CONFLICT);
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
         assert_ne!(response.body().len(), 0);
         
         let error: ErrorDetailsResponse = serde_json::from_slice(response.body()).unwrap();
-        assert_eq!(error.error_id, "ERR__ROOM_MESSAGES_CONFLICT");
+        assert_eq!(error.error_id, "ERR__ROOM_NOT_FOUND");
+    }
+
+    #[tokio::test]
+    async fn test_export_room_messages() {
+        let server = Server::new_shared();
+        server.clone().write().unwrap().register_user("test_user").unwrap();
+        server.clone().write().unwrap().create_room("test_room", "test_user").unwrap();
+        server.clone().write().unwrap().post_message_to_room("test_room", "test_user", "Hello, world").unwrap();
+        let routes = rooms_routes(server.clone());
+
+        let response = request()
+            .method("GET")
+            .path("/rooms/test_room/export?format=csv")
+            .reply(&routes)
+            .await;
+
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()["content-type"], "text/csv; charset=utf-8");
+        assert_eq!(response.headers()["content-disposition"], "attachment; filename=\"test_room.csv\"");
+        let csv = String::from_utf8(response.body().to_vec()).unwrap();
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0], "seq,timestamp,author,is_bot,content");
+        assert!(lines[1].starts_with("1,") && lines[1].ends_with(",test_user,false,\"Hello, world\""));
+
+        let response = request()
+            .method("GET")
+            .path("/rooms/test_room/export?format=html&until=2000-01-01T00:00:00Z")
+            .reply(&routes)
+            .await;
+
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()["content-type"], "text/html; charset=utf-8");
+        let html = String::from_utf8(response.body().to_vec()).unwrap();
+        assert!(html.starts_with("<!DOCTYPE html>") && html.ends_with("</html>\n"));
+        assert!(!html.contains("<li>"));
+    }
+
+    #[tokio::test]
+    async fn test_export_room_messages_invalid_format() {
+        let server = Server::new_shared();
+        server.clone().write().unwrap().register_user("test_user").unwrap();
+        server.clone().write().unwrap().create_room("test_room", "test_user").unwrap();
+        let routes = rooms_routes(server.clone());
+
+        let response = request()
+            .method("GET")
+            .path("/rooms/test_room/export?format=pdf")
+            .reply(&routes)
+            .await;
+
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        let error: ErrorDetailsResponse = serde_json::from_slice(response.body()).unwrap();
+        assert_eq!(error.error_id, "ERR__BAD_REQUEST");
+    }
+
+    #[tokio::test]
+    async fn test_export_room_messages_room_not_found() {
+        let server = Server::new_shared();
+        let routes = rooms_routes(server.clone());
+
+        let response = request()
+            .method("GET")
+            .path("/rooms/test_room/export")
+            .reply(&routes)
+            .await;
+
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        let error: ErrorDetailsResponse = serde_json::from_slice(response.body()).unwrap();
+        assert_eq!(error.error_id, "ERR__ROOM_NOT_FOUND");
     }
 
     #[tokio::test]
     async fn test_post_message_to_room() {
-        let server = Arc::new(Mutex::new(Server::new()));
-        server.clone().lock().unwrap().register_user("test_user").unwrap();
-        server.clone().lock().unwrap().create_room("test_room", "test_user").unwrap();
+        let server = Server::new_shared();
+        server.clone().write().unwrap().register_user("test_user").unwrap();
+        server.clone().write().unwrap().create_room("test_room", "test_user").unwrap();
 
         let response = request()
             .method("POST")
@@ -420,11 +769,94 @@ mod tests {
         assert_eq!(message.author.username, "test_user");
     }
 
+    #[tokio::test]
+    async fn test_post_message_to_room_replayed() {
+        let server = Server::new_shared();
+        server.clone().write().unwrap().register_user("test_user").unwrap();
+        server.clone().write().unwrap().create_room("test_room", "test_user").unwrap();
+        let client_message_id = Uuid::new_v4();
+        let routes = rooms_routes(server.clone());
+        let post = |body: serde_json::Value| request()
+            .method("POST")
+            .path("/rooms/test_room/messages")
+            .header("Idempotency-Key", client_message_id.to_string())
+            .json(&body)
+            .reply(&routes);
+
+        let response = post(serde_json::json!({"username": "test_user", "message": "test message"})).await;
+        assert_eq!(response.status(), StatusCode::CREATED);
+        let message: Message = serde_json::from_slice(response.body()).unwrap();
+        assert_eq!(message.client_message_id, Some(client_message_id));
+
+        let response = post(serde_json::json!({"username": "test_user", "message": "test message", "client_message_id": client_message_id})).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let replayed_message: Message = serde_json::from_slice(response.body()).unwrap();
+        assert_eq!(replayed_message.id, message.id);
+
+        let response = post(serde_json::json!({"username": "test_user", "message": "other message"})).await;
+        assert_eq!(response.status(), StatusCode::CONFLICT);
+        let error: ErrorDetailsResponse = serde_json::from_slice(response.body()).unwrap();
+        assert_eq!(error.error_id, "ERR__IDEMPOTENCY_KEY_REUSED");
+
+        assert_eq!(server.read().unwrap().get_room_messages("test_room").unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_post_message_to_room_replayed_opaque_idempotency_key() {
+        let server = Server::new_shared();
+        server.clone().write().unwrap().register_user("test_user").unwrap();
+        server.clone().write().unwrap().create_room("test_room", "test_user").unwrap();
+        let routes = rooms_routes(server.clone());
+        let post = |idempotency_key: &'static str| request()
+            .method("POST")
+            .path("/rooms/test_room/messages")
+            .header("Idempotency-Key", idempotency_key)
+            .json(&serde_json::json!({"username": "test_user", "message": "test message"}))
+            .reply(&routes);
+
+        let response = post("order-42").await;
+        assert_eq!(response.status(), StatusCode::CREATED);
+        let message: Message = serde_json::from_slice(response.body()).unwrap();
+        assert!(message.client_message_id.is_some());
+
+        let response = post("order-42").await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let replayed_message: Message = serde_json::from_slice(response.body()).unwrap();
+        assert_eq!(replayed_message.id, message.id);
+
+        let response = post("order-43").await;
+        assert_eq!(response.status(), StatusCode::CREATED);
+
+        assert_eq!(server.read().unwrap().get_room_messages("test_room").unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_post_message_to_room_invalid_idempotency_key() {
+        let server = Server::new_shared();
+        server.clone().write().unwrap().register_user("test_user").unwrap();
+        server.clone().write().unwrap().create_room("test_room", "test_user").unwrap();
+
+        for (idempotency_key, client_message_id) in [(" ".to_string(), None), ("k".repeat(256), None), (Uuid::new_v4().to_string(), Some(Uuid::new_v4()))] {
+            let response = request()
+                .method("POST")
+                .path("/rooms/test_room/messages")
+                .header("Idempotency-Key", idempotency_key)
+                .json(&serde_json::json!({"username": "test_user", "message": "test message", "client_message_id": client_message_id}))
+                .reply(&rooms_routes(server.clone()))
+                .await;
+
+            assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+            let error: ErrorDetailsResponse = serde_json::from_slice(response.body()).unwrap();
+            assert_eq!(error.error_id, "ERR__BAD_REQUEST");
+        }
+        assert!(server.read().unwrap().get_room_messages("test_room").unwrap().is_empty());
+    }
+
     #[tokio::test]
     async fn test_post_message_to_room_missing_fields() {
-        let server = Arc::new(Mutex::new(Server::new()));
-        server.clone().lock().unwrap().register_user("test_user").unwrap();
-        server.clone().lock().unwrap().create_room("test_room", "test_user").unwrap();
+        let server = Server::new_shared();
+        server.clone().write().unwrap().register_user("test_user").unwrap();
+        server.clone().write().unwrap().create_room("test_room", "test_user").unwrap();
 
         let response = request()
             .method("POST")
@@ -439,13 +871,13 @@ mod tests {
         assert_ne!(response.body().len(), 0);
         
         let error: ErrorDetailsResponse = serde_json::from_slice(response.body()).unwrap();
-        assert_eq!(error.error_id, "ERR__MESSAGE_POST_TO_ROOM_BAD_REQUEST");
+        assert_eq!(error.error_id, "ERR__BAD_REQUEST");
     }
 
     #[tokio::test]
     async fn test_post_message_to_room_room_not_found() {
-        let server = Arc::new(Mutex::new(Server::new()));
-        server.clone().lock().unwrap().register_user("test_user").unwrap();
+        let server = Server::new_shared();
+        server.clone().write().unwrap().register_user("test_user").unwrap();
 
         let response = request()
             .method("POST")
@@ -457,18 +889,41 @@ mod tests {
             .reply(&rooms_routes(server.clone()))
             .await;
 
-        assert_eq!(response.status(), StatusCode::CONFLICT);
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
         assert_ne!(response.body().len(), 0);
         
         let error: ErrorDetailsResponse = serde_json::from_slice(response.body()).unwrap();
-        assert_eq!(error.error_id, "ERR__MESSAGE_POST_TO_ROOM_CONFLICT");
+        assert_eq!(error.error_id, "ERR__ROOM_NOT_FOUND");
+    }
+
+    #[tokio::test]
+    async fn test_post_message_to_room_user_not_in_room() {
+        let server = Server::new_shared();
+        server.clone().write().unwrap().register_user("test_user").unwrap();
+        server.clone().write().unwrap().create_room("test_room", "test_user").unwrap();
+        server.clone().write().unwrap().register_user("test_user2").unwrap();
+
+        let response = request()
+            .method("POST")
+            .path("/rooms/test_room/messages")
+            .json(&serde_json::json!({
+                "username": "test_user2",
+                "message": "test message"
+            }))
+            .reply(&rooms_routes(server.clone()))
+            .await;
+
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+        let error: ErrorDetailsResponse = serde_json::from_slice(response.body()).unwrap();
+        assert_eq!(error.error_id, "ERR__USER_NOT_IN_ROOM");
     }
 
     #[tokio::test]
     async fn test_post_message_to_room_user_not_found() {
-        let server = Arc::new(Mutex::new(Server::new()));
-        server.clone().lock().unwrap().register_user("test_user").unwrap();
-        server.clone().lock().unwrap().create_room("test_room", "test_user").unwrap();
+        let server = Server::new_shared();
+        server.clone().write().unwrap().register_user("test_user").unwrap();
+        server.clone().write().unwrap().create_room("test_room", "test_user").unwrap();
 
         let response = request()
             .method("POST")
@@ -480,10 +935,177 @@ mod tests {
             .reply(&rooms_routes(server.clone()))
             .await;
 
-        assert_eq!(response.status(), StatusCode::CONFLICT);
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
         assert_ne!(response.body().len(), 0);
         
         let error: ErrorDetailsResponse = serde_json::from_slice(response.body()).unwrap();
-        assert_eq!(error.error_id, "ERR__MESSAGE_POST_TO_ROOM_CONFLICT");
+        assert_eq!(error.error_id, "ERR__USER_NOT_FOUND");
+    }
+
+    #[tokio::test]
+    async fn test_register_webhook_to_room() {
+        let server = Server::new_shared();
+        server.clone().write().unwrap().register_user("test_user").unwrap();
+        server.clone().write().unwrap().create_room("test_room", "test_user").unwrap();
+
+        let response = request()
+            .method("POST")
+            .path("/rooms/test_room/webhooks")
+            .json(&serde_json::json!({
+                "url": "http://localhost/hook",
+                "secret": "test_secret"
+            }))
+            .reply(&rooms_routes(server.clone()))
+            .await;
+
+        assert_eq!(response.status(), StatusCode::CREATED);
+
+        let webhook: serde_json::Value = serde_json::from_slice(response.body()).unwrap();
+        assert_eq!(webhook["url"], "http://localhost/hook");
+        assert_eq!(webhook["secret"], "test_secret");
+    }
+
+    #[tokio::test]
+    async fn test_register_webhook_to_room_missing_fields() {
+        let server = Server::new_shared();
+        server.clone().write().unwrap().register_user("test_user").unwrap();
+        server.clone().write().unwrap().create_room("test_room", "test_user").unwrap();
+
+        let response = request()
+            .method("POST")
+            .path("/rooms/test_room/webhooks")
+            .json(&serde_json::json!({}))
+            .reply(&rooms_routes(server.clone()))
+            .await;
+
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+        let error: ErrorDetailsResponse = serde_json::from_slice(response.body()).unwrap();
+        assert_eq!(error.error_id, "ERR__BAD_REQUEST");
+    }
+
+    #[tokio::test]
+    async fn test_register_webhook_to_room_room_not_found() {
+        let server = Server::new_shared();
+
+        let response = request()
+            .method("POST")
+            .path("/rooms/test_room/webhooks")
+            .json(&serde_json::json!({
+                "url": "http://localhost/hook"
+            }))
+            .reply(&rooms_routes(server.clone()))
+            .await;
+
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+        let error: ErrorDetailsResponse = serde_json::from_slice(response.body()).unwrap();
+        assert_eq!(error.error_id, "ERR__ROOM_NOT_FOUND");
+    }
+
+    #[tokio::test]
+    async fn test_get_room_webhooks() {
+        let server = Server::new_shared();
+        server.clone().write().unwrap().register_user("test_user").unwrap();
+        server.clone().write().unwrap().create_room("test_room", "test_user").unwrap();
+        server.clone().write().unwrap().register_webhook_to_room("test_room", "http://localhost/hook", Some("test_secret")).unwrap();
+
+        let response = request()
+            .method("GET")
+            .path("/rooms/test_room/webhooks")
+            .reply(&rooms_routes(server.clone()))
+            .await;
+
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let response_string = String::from_utf8(response.body().to_vec()).unwrap();
+        assert!(response_string.contains("http://localhost/hook"));
+        assert!(!response_string.contains("test_secret"));
+    }
+
+    #[tokio::test]
+    async fn test_get_room_webhook_deliveries() {
+        let server = Server::new_shared();
+        server.clone().write().unwrap().register_user("test_user").unwrap();
+        server.clone().write().unwrap().create_room("test_room", "test_user").unwrap();
+
+        let response = request()
+            .method("GET")
+            .path("/rooms/test_room/webhooks/deliveries")
+            .reply(&rooms_routes(server.clone()))
+            .await;
+
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(std::str::from_utf8(response.body()).unwrap(), "[]");
+    }
+
+    #[tokio::test]
+    async fn test_get_room_webhook_deliveries_room_not_found() {
+        let server = Server::new_shared();
+
+        let response = request()
+            .method("GET")
+            .path("/rooms/test_room/webhooks/deliveries")
+            .reply(&rooms_routes(server.clone()))
+            .await;
+
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+        let error: ErrorDetailsResponse = serde_json::from_slice(response.body()).unwrap();
+        assert_eq!(error.error_id, "ERR__ROOM_NOT_FOUND");
+    }
+
+    #[tokio::test]
+    async fn test_create_incoming_webhook() {
+        let server = Server::new_shared();
+        server.clone().write().unwrap().register_user("test_user").unwrap();
+        server.clone().write().unwrap().create_room("test_room", "test_user").unwrap();
+
+        let response = request()
+            .method("POST")
+            .path("/rooms/test_room/hooks?bot_name=ci")
+            .reply(&rooms_routes(server.clone()))
+            .await;
+
+        assert_eq!(response.status(), StatusCode::CREATED);
+
+        let incoming_webhook: serde_json::Value = serde_json::from_slice(response.body()).unwrap();
+        assert_eq!(incoming_webhook["room_name"], "test_room");
+        assert_eq!(incoming_webhook["bot"]["username"], "ci");
+        assert!(incoming_webhook["token"].as_str().is_some());
+    }
+
+    #[tokio::test]
+    async fn test_create_incoming_webhook_default_bot_name() {
+        let server = Server::new_shared();
+        server.clone().write().unwrap().register_user("test_user").unwrap();
+        server.clone().write().unwrap().create_room("test_room", "test_user").unwrap();
+
+        let response = request()
+            .method("POST")
+            .path("/rooms/test_room/hooks")
+            .reply(&rooms_routes(server.clone()))
+            .await;
+
+        assert_eq!(response.status(), StatusCode::CREATED);
+
+        let incoming_webhook: serde_json::Value = serde_json::from_slice(response.body()).unwrap();
+        assert_eq!(incoming_webhook["bot"]["username"], "incoming-webhook");
+    }
+
+    #[tokio::test]
+    async fn test_create_incoming_webhook_room_not_found() {
+        let server = Server::new_shared();
+
+        let response = request()
+            .method("POST")
+            .path("/rooms/test_room/hooks")
+            .reply(&rooms_routes(server.clone()))
+            .await;
+
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+        let error: ErrorDetailsResponse = serde_json::from_slice(response.body()).unwrap();
+        assert_eq!(error.error_id, "ERR__ROOM_NOT_FOUND");
     }
 }
\ No newline at end of file
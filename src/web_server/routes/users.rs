@@ -1,20 +1,21 @@
-use crate::entities::server::Server;
+use crate::entities::server::SharedServer;
 use crate::web_server::handlers;
-use std::sync::{Arc, Mutex};
+use uuid::Uuid;
 use warp::Filter;
 use super::with_server;
 
-pub fn users_routes(server: Arc<Mutex<Server>>) -> impl Filter<Extract = impl warp::Reply, Error = warp::Rejection> + Clone {
-    get_user_in_server_by_username(server.clone())
+pub fn users_routes(server: SharedServer) -> impl Filter<Extract = impl warp::Reply, Error = warp::Rejection> + Clone {
+    get_user_in_server_by_id(server.clone())
+        .or(get_user_in_server_by_username(server.clone()))
         .or(register_user_to_server(server.clone()))
 }
 
 /**
- * GET /users/:username
+ * GET /v1/users/:username
  * Checks if a user exists in the server and returns it.
  * Returns 200 OK if the user exists in the server, 404 NOT FOUND otherwise.
  */
-fn get_user_in_server_by_username(server: Arc<Mutex<Server>>) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
+fn get_user_in_server_by_username(server: SharedServer) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
     warp::path!("users" / String)
         .and(warp::get())   
         .and(with_server(server))
@@ -22,11 +23,23 @@ fn get_user_in_server_by_username(server: Arc<Mutex<Server>>) -> impl Filter<Ext
 }
 
 /**
- * POST /users/:username
+ * GET /v1/users/by-id/:user_id
+ * Returns the user registered in the server with the given id.
+ * Returns 200 OK if the user exists in the server, 404 NOT FOUND otherwise.
+ */
+fn get_user_in_server_by_id(server: SharedServer) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
+    warp::path!("users" / "by-id" / Uuid)
+        .and(warp::get())
+        .and(with_server(server))
+        .and_then(handlers::users::get_user_in_server_by_id)
+}
+
+/**
+ * POST /v1/users/:username
  * Registers a new user to the server.
- * Returns 201 CREATED if the user was successfully registered, 400 BAD REQUEST otherwise.
+ * Returns 201 CREATED if the user was successfully registered, 409 CONFLICT if the username is already taken.
  */
-fn register_user_to_server(server: Arc<Mutex<Server>>) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
+fn register_user_to_server(server: SharedServer) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
     warp::path!("users" / String)
         .and(warp::post())   
         .and(with_server(server))
@@ -46,8 +59,8 @@ mod tests {
 
     #[tokio::test]
     async fn test_get_user_in_server_by_username() {
-        let server = Arc::new(Mutex::new(Server::new()));
-        server.clone().lock().unwrap().register_user("test_user").unwrap();
+        let server = Server::new_shared();
+        server.clone().write().unwrap().register_user("test_user").unwrap();
 
         let response = request()
             .method("GET")
@@ -62,9 +75,43 @@ mod tests {
         assert_eq!(user.username, "test_user");
     }
 
+    #[tokio::test]
+    async fn test_get_user_in_server_by_id() {
+        let server = Server::new_shared();
+        server.clone().write().unwrap().register_user("test_user").unwrap();
+        let user_id = server.read().unwrap().get_user_by_username("test_user").unwrap().id;
+
+        let response = request()
+            .method("GET")
+            .path(&format!("/users/by-id/{}", user_id))
+            .reply(&users_routes(server.clone()))
+            .await;
+
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let user: User = serde_json::from_slice(response.body()).unwrap();
+        assert_eq!(user.username, "test_user");
+    }
+
+    #[tokio::test]
+    async fn test_get_user_in_server_by_id_not_found() {
+        let server = Server::new_shared();
+
+        let response = request()
+            .method("GET")
+            .path(&format!("/users/by-id/{}", uuid::Uuid::new_v4()))
+            .reply(&users_routes(server.clone()))
+            .await;
+
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+        let error_response: ErrorDetailsResponse = serde_json::from_slice(response.body()).unwrap();
+        assert_eq!(error_response.error_id, "ERR__USER_NOT_FOUND");
+    }
+
     #[tokio::test]
     async fn test_get_user_in_server_by_username_not_found() {
-        let server = Arc::new(Mutex::new(Server::new()));
+        let server = Server::new_shared();
 
         let response = request()
             .method("GET")
@@ -76,7 +123,8 @@ mod tests {
 
         let error_response = &ErrorDetailsResponse {
             error_id: "ERR__USER_NOT_FOUND".to_string(),
-            error_message: "User with username test_user not found in server".to_string()
+            error_message: "User with username test_user not found in server".to_string(),
+            request_id: None
         };
         let error_response_to_serialized_string = serde_json::to_string(&error_response).unwrap();
         let response_body = std::str::from_utf8(response.body()).unwrap();
@@ -85,7 +133,7 @@ mod tests {
 
     #[tokio::test]
     async fn test_register_user_to_server() {
-        let server = Arc::new(Mutex::new(Server::new()));
+        let server = Server::new_shared();
 
         let response = request()
             .method("POST")
@@ -102,9 +150,9 @@ mod tests {
 
     #[tokio::test]
     async fn test_register_user_to_server_already_exists() {
-        let server = Arc::new(Mutex::new(Server::new()));
+        let server = Server::new_shared();
         let user = User::new("test_user".to_string());
-        server.lock().unwrap().register_user(&user.username).unwrap();
+        server.write().unwrap().register_user(&user.username).unwrap();
 
         let response = request()
             .method("POST")
@@ -116,7 +164,8 @@ mod tests {
 
         let error_response = &ErrorDetailsResponse {
             error_id: "ERR__USER_ALREADY_EXISTS".to_string(),
-            error_message: "User with username test_user already exists in server".to_string()
+            error_message: "User with username test_user already exists in server".to_string(),
+            request_id: None
         };
         let error_response_to_serialized_string = serde_json::to_string(&error_response).unwrap();
         let response_body = std::str::from_utf8(response.body()).unwrap();
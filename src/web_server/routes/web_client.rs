@@ -0,0 +1,77 @@
+use crate::web_server::handlers;
+use warp::Filter;
+
+pub fn web_client_routes() -> impl Filter<Extract = impl warp::Reply, Error = warp::Rejection> + Clone {
+    get_web_client_index()
+        .or(get_web_client_asset())
+}
+
+/**
+ * GET /
+ * Returns the page of the browser chat client, which uses the REST API of the server.
+ * Returns 200 OK.
+ */
+fn get_web_client_index() -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
+    warp::path::end()
+        .and(warp::get())
+        .and_then(handlers::web_client::get_web_client_index)
+}
+
+/**
+ * GET /web/:file_name
+ * Returns a script or stylesheet of the browser chat client.
+ * Returns 200 OK if the file exists, 404 NOT FOUND otherwise.
+ */
+fn get_web_client_asset() -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
+    warp::path!("web" / String)
+        .and(warp::get())
+        .and_then(handlers::web_client::get_web_client_asset)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use warp::http::StatusCode;
+
+    #[tokio::test]
+    async fn test_get_web_client_index() {
+        let routes = web_client_routes();
+
+        let response = warp::test::request()
+            .method("GET")
+            .path("/")
+            .reply(&routes)
+            .await;
+
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()["content-type"], "text/html; charset=utf-8");
+        assert!(String::from_utf8_lossy(response.body()).contains(r#"<script src="web/app.js"></script>"#));
+    }
+
+    #[tokio::test]
+    async fn test_get_web_client_asset() {
+        let routes = web_client_routes();
+
+        let response = warp::test::request()
+            .method("GET")
+            .path("/web/app.js")
+            .reply(&routes)
+            .await;
+
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()["content-type"], "text/javascript; charset=utf-8");
+    }
+
+    #[tokio::test]
+    async fn test_get_web_client_asset_not_found() {
+        let routes = web_client_routes();
+
+        let response = warp::test::request()
+            .method("GET")
+            .path("/web/unknown.js")
+            .reply(&routes)
+            .await;
+
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+}
@@ -0,0 +1,43 @@
+use std::time::Duration;
+
+/// Message posted in every room when the server starts shutting down.
+pub const SHUTDOWN_NOTICE: &str = "The server is shutting down, messages sent from now on may not be delivered";
+
+#[derive(Debug, Clone, Copy)]
+pub struct ShutdownPolicy {
+    /// How long in-flight requests are given to complete once the server stops accepting connections
+    pub drain_timeout: Duration,
+    /// How long pending webhook deliveries are given to complete once the requests are drained
+    pub flush_timeout: Duration
+}
+
+impl Default for ShutdownPolicy {
+    fn default() -> Self {
+        ShutdownPolicy {
+            drain_timeout: Duration::from_secs(10),
+            flush_timeout: Duration::from_secs(10)
+        }
+    }
+}
+
+/// Completes when the process is asked to stop, either with Ctrl+C (SIGINT) or by `docker stop` (SIGTERM).
+pub async fn shutdown_signal() {
+    let ctrl_c = async {
+        tokio::signal::ctrl_c().await.expect("Failed to listen for Ctrl+C");
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate())
+            .expect("Failed to listen for SIGTERM")
+            .recv()
+            .await;
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => tracing::info!("Received SIGINT"),
+        _ = terminate => tracing::info!("Received SIGTERM"),
+    }
+}
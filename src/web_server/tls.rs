@@ -0,0 +1,93 @@
+use crate::config::{ConfigError, TlsConfig};
+use std::{fs, path::Path};
+
+/// Certificate chain and private key served over HTTPS, read at startup so that missing files are reported before binding.
+#[derive(Clone)]
+pub struct ServerTls {
+    pub cert_pem: Vec<u8>,
+    pub key_pem: Vec<u8>
+}
+
+impl ServerTls {
+    /// Reads the certificate and the key configured, if TLS is enabled.
+    pub fn from_config(tls: &TlsConfig) -> Result<Option<ServerTls>, ConfigError> {
+        if !tls.enabled {
+            return Ok(None);
+        }
+        let (Some(cert_path), Some(key_path)) = (&tls.cert_path, &tls.key_path) else {
+            return Err(ConfigError::InvalidValue {
+                key: "tls.cert_path".to_string(),
+                value: String::new(),
+                reason: "tls.cert_path and tls.key_path are required to serve HTTPS".to_string()
+            });
+        };
+        Ok(Some(ServerTls {
+            cert_pem: read_pem_file(cert_path)?,
+            key_pem: read_pem_file(key_path)?
+        }))
+    }
+}
+
+fn read_pem_file(path: &Path) -> Result<Vec<u8>, ConfigError> {
+    fs::read(path).map_err(|error| ConfigError::FileUnreadable {
+        path: path.to_path_buf(),
+        reason: error.to_string()
+    })
+}
+
+/// Certificates generated for the tests: a certificate authority, and a certificate it issued for `localhost` and `127.0.0.1`.
+#[cfg(test)]
+pub struct TestCertificates {
+    pub ca_cert_pem: String,
+    pub cert_pem: String,
+    pub key_pem: String
+}
+
+#[cfg(test)]
+impl TestCertificates {
+    pub fn generate() -> TestCertificates {
+        use rcgen::{BasicConstraints, CertificateParams, DnType, IsCa, Issuer, KeyPair};
+
+        let ca_key = KeyPair::generate().unwrap();
+        let mut ca_params = CertificateParams::new(Vec::new()).unwrap();
+        ca_params.is_ca = IsCa::Ca(BasicConstraints::Unconstrained);
+        ca_params.distinguished_name.push(DnType::CommonName, "rust-live-chat test authority");
+        let ca_cert = ca_params.self_signed(&ca_key).unwrap();
+        let ca_issuer = Issuer::new(ca_params, ca_key);
+
+        let key = KeyPair::generate().unwrap();
+        let mut params = CertificateParams::new(vec!["localhost".to_string(), "127.0.0.1".to_string()]).unwrap();
+        params.distinguished_name.push(DnType::CommonName, "localhost");
+        let cert = params.signed_by(&key, &ca_issuer).unwrap();
+
+        TestCertificates {
+            ca_cert_pem: ca_cert.pem(),
+            cert_pem: cert.pem(),
+            key_pem: key.serialize_pem()
+        }
+    }
+
+    pub fn server_tls(&self) -> ServerTls {
+        ServerTls {
+            cert_pem: self.cert_pem.clone().into_bytes(),
+            key_pem: self.key_pem.clone().into_bytes()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    #[test]
+    fn test_server_tls_from_config() {
+        assert!(ServerTls::from_config(&TlsConfig::default()).unwrap().is_none());
+
+        let missing_key = TlsConfig { enabled: true, cert_path: Some(PathBuf::from("server.crt")), ..TlsConfig::default() };
+        assert!(matches!(ServerTls::from_config(&missing_key), Err(ConfigError::InvalidValue { .. })));
+
+        let missing_files = TlsConfig { key_path: Some(PathBuf::from("/nonexistent/server.key")), ..missing_key };
+        assert!(matches!(ServerTls::from_config(&missing_files), Err(ConfigError::FileUnreadable { .. })));
+    }
+}
@@ -0,0 +1,157 @@
+// Browser chat client, talking to the REST API of the server.
+// The API is expected on the same origin, unless given with the `server` query parameter,
+// e.g. `?server=https://chat.internal:3030` when the client is hosted separately.
+"use strict";
+
+const POLL_INTERVAL_MS = 2000;
+
+const API_VERSION = "v1";
+const apiBase = (new URLSearchParams(window.location.search).get("server") || "").replace(/\/+$/, "") + "/" + API_VERSION;
+
+const state = {
+    username: null,
+    roomName: null,
+    pollTimer: null
+};
+
+const element = (id) => document.getElementById(id);
+
+class ApiError extends Error {
+    constructor(details) {
+        super(details.error_message);
+        this.errorId = details.error_id;
+        this.requestId = details.request_id;
+    }
+}
+
+async function api(method, path, body) {
+    const options = { method, headers: {} };
+    if (body !== undefined) {
+        options.headers["Content-Type"] = "application/json";
+        options.body = JSON.stringify(body);
+    }
+    let response;
+    try {
+        response = await fetch(apiBase + path, options);
+    } catch (error) {
+        throw new ApiError({ error_id: "ERR__CLIENT_FETCH_API", error_message: `Failed to reach the server: ${error.message}` });
+    }
+    const payload = await response.json().catch(() => null);
+    if (!response.ok) {
+        throw new ApiError(payload || { error_id: "ERR__CLIENT_FETCH_API", error_message: `Server answered with status ${response.status}` });
+    }
+    return payload;
+}
+
+const segment = encodeURIComponent;
+
+function showError(error) {
+    const errorElement = element("error");
+    if (!error) {
+        errorElement.hidden = true;
+        return;
+    }
+    errorElement.textContent = error.requestId ? `${error.message} (request id: ${error.requestId})` : error.message;
+    errorElement.hidden = false;
+}
+
+async function authenticate(username, register) {
+    if (register) {
+        await api("POST", `/users/${segment(username)}`);
+    } else {
+        await api("GET", `/users/${segment(username)}`);
+    }
+    state.username = username;
+    element("session").textContent = `Signed in as ${username}`;
+    element("login-view").hidden = true;
+    element("chat-view").hidden = false;
+    await refreshRooms();
+}
+
+async function refreshRooms() {
+    const rooms = await api("GET", "/rooms");
+    const roomsList = element("rooms");
+    roomsList.replaceChildren(...rooms.map((room) => {
+        const item = document.createElement("li");
+        item.textContent = room.name;
+        item.classList.toggle("current", room.name === state.roomName);
+        item.addEventListener("click", () => enterRoom(room.name).catch(showError));
+        return item;
+    }));
+}
+
+async function enterRoom(roomName) {
+    try {
+        await api("POST", `/rooms/${segment(roomName)}/users/${segment(state.username)}`);
+    } catch (error) {
+        if (error.errorId !== "ERR__USER_ALREADY_IN_ROOM") {
+            throw error;
+        }
+    }
+    state.roomName = roomName;
+    element("room-name").textContent = roomName;
+    element("message-form").hidden = false;
+    showError(null);
+    await Promise.all([refreshRooms(), refreshMessages()]);
+    clearInterval(state.pollTimer);
+    state.pollTimer = setInterval(() => refreshMessages().catch(showError), POLL_INTERVAL_MS);
+}
+
+async function refreshMessages() {
+    if (!state.roomName) {
+        return;
+    }
+    const messages = await api("GET", `/rooms/${segment(state.roomName)}/messages`);
+    const messagesList = element("messages");
+    const isScrolledToBottom = messagesList.scrollTop + messagesList.clientHeight >= messagesList.scrollHeight - 1;
+    messagesList.replaceChildren(...messages.map(renderMessage));
+    if (isScrolledToBottom) {
+        messagesList.scrollTop = messagesList.scrollHeight;
+    }
+}
+
+function renderMessage(message) {
+    const item = document.createElement("li");
+    item.classList.toggle("bot", message.author.is_bot);
+    const author = document.createElement("span");
+    author.className = "author";
+    author.textContent = `${message.author.username}: `;
+    const content = document.createElement("span");
+    content.textContent = message.content;
+    const time = document.createElement("time");
+    const postedAt = new Date(message.timestamp);
+    time.dateTime = message.timestamp;
+    time.textContent = postedAt.toLocaleTimeString();
+    item.append(author, content, time);
+    return item;
+}
+
+element("login-form").addEventListener("submit", (event) => {
+    event.preventDefault();
+    const username = element("username").value.trim();
+    const register = event.submitter && event.submitter.value === "register";
+    authenticate(username, register).then(() => showError(null), showError);
+});
+
+element("create-room-form").addEventListener("submit", (event) => {
+    event.preventDefault();
+    const roomName = element("new-room-name").value.trim();
+    api("POST", `/rooms/${segment(roomName)}?creator_username=${segment(state.username)}`)
+        .then(() => {
+            element("new-room-name").value = "";
+            return enterRoom(roomName);
+        })
+        .catch(showError);
+});
+
+element("message-form").addEventListener("submit", (event) => {
+    event.preventDefault();
+    const messageInput = element("message");
+    api("POST", `/rooms/${segment(state.roomName)}/messages`, { username: state.username, message: messageInput.value })
+        .then(() => {
+            messageInput.value = "";
+            showError(null);
+            return refreshMessages();
+        })
+        .catch(showError);
+});
@@ -0,0 +1,244 @@
+use crate::entities::webhook::{WebhookDelivery, WebhookDispatch, WebhookDispatchSender};
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use std::time::{Duration, SystemTime};
+use tokio::sync::mpsc::{self, UnboundedReceiver};
+use tokio::task::{JoinHandle, JoinSet};
+
+pub const WEBHOOK_SIGNATURE_HEADER: &str = "X-Webhook-Signature";
+pub const WEBHOOK_EVENT_HEADER: &str = "X-Webhook-Event";
+
+#[derive(Debug, Clone, Copy)]
+pub struct WebhookDeliveryPolicy {
+    pub max_attempts: u32,
+    pub initial_backoff: Duration,
+    pub request_timeout: Duration
+}
+
+impl Default for WebhookDeliveryPolicy {
+    fn default() -> Self {
+        WebhookDeliveryPolicy {
+            max_attempts: 5,
+            initial_backoff: Duration::from_millis(500),
+            request_timeout: Duration::from_secs(10)
+        }
+    }
+}
+
+/// Signs the payload with HMAC-SHA256 using the webhook secret.
+/// Receivers recompute it over the raw request body to authenticate the sender.
+pub fn sign_payload(secret: &str, body: &[u8]) -> String {
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts keys of any size");
+    mac.update(body);
+    format!("sha256={}", hex::encode(mac.finalize().into_bytes()))
+}
+
+/// Spawns the background worker delivering webhook events and returns the sender the `Server` emits to.
+/// Once every sender is dropped, the worker finishes the pending deliveries and the returned handle completes.
+pub fn spawn_webhook_dispatcher(policy: WebhookDeliveryPolicy) -> (WebhookDispatchSender, JoinHandle<()>) {
+    let (sender, receiver) = mpsc::unbounded_channel();
+    let handle = tokio::spawn(run_webhook_dispatcher(receiver, policy));
+    (sender, handle)
+}
+
+async fn run_webhook_dispatcher(mut receiver: UnboundedReceiver<WebhookDispatch>, policy: WebhookDeliveryPolicy) {
+    let client = reqwest::Client::builder()
+        .timeout(policy.request_timeout)
+        .build()
+        .expect("Failed to build webhook HTTP client");
+
+    let mut deliveries = JoinSet::new();
+    while let Some(dispatch) = receiver.recv().await {
+        deliveries.spawn(deliver_webhook(client.clone(), dispatch, policy));
+        while deliveries.try_join_next().is_some() {}
+    }
+
+    while deliveries.join_next().await.is_some() {}
+}
+
+/// Posts the payload to the webhook, retrying with exponential backoff until it succeeds
+/// or the attempts are exhausted. Every attempt is recorded in the room delivery log.
+pub async fn deliver_webhook(client: reqwest::Client, dispatch: WebhookDispatch, policy: WebhookDeliveryPolicy) -> bool {
+    let body = serde_json::to_vec(&dispatch.payload).expect("Webhook payload should serialize");
+    let signature = sign_payload(&dispatch.webhook.secret, &body);
+    let event_name = dispatch.payload.event.name();
+    let mut backoff = policy.initial_backoff;
+
+    for attempt in 1..=policy.max_attempts {
+        let response = client
+            .post(&dispatch.webhook.url)
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+            .header(WEBHOOK_SIGNATURE_HEADER, &signature)
+            .header(WEBHOOK_EVENT_HEADER, event_name)
+            .body(body.clone())
+            .send()
+            .await;
+
+        let (status_code, success, error) = match response {
+            Ok(response) => {
+                let status = response.status();
+                let error = (!status.is_success()).then(|| format!("Webhook endpoint answered {}", status));
+                (Some(status.as_u16()), status.is_success(), error)
+            },
+            Err(error) => (None, false, Some(format!("Failed to reach webhook endpoint: {}", error)))
+        };
+
+        dispatch.room.write().unwrap().log_webhook_delivery(WebhookDelivery {
+            payload_id: dispatch.payload.id,
+            webhook_id: dispatch.webhook.id,
+            event: event_name.to_string(),
+            attempt,
+            status_code,
+            success,
+            error,
+            timestamp: SystemTime::now()
+        });
+
+        if success {
+            return true;
+        }
+
+        if attempt < policy.max_attempts {
+            tokio::time::sleep(backoff).await;
+            backoff *= 2;
+        }
+    }
+
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::entities::{room::Room, user::User, webhook::{Webhook, WebhookEvent, WebhookPayload}};
+    use std::sync::{Arc, RwLock};
+
+    fn test_policy() -> WebhookDeliveryPolicy {
+        WebhookDeliveryPolicy {
+            max_attempts: 3,
+            initial_backoff: Duration::from_millis(1),
+            request_timeout: Duration::from_secs(2)
+        }
+    }
+
+    fn test_dispatch(url: String) -> WebhookDispatch {
+        let user = Arc::new(User::new("test_user".to_string()));
+        WebhookDispatch {
+            room: Arc::new(RwLock::new(Room::new("test_room".to_string()))),
+            webhook: Arc::new(Webhook::new(url, "secret".to_string())),
+            payload: WebhookPayload::new("test_room".to_string(), WebhookEvent::UserJoined(user))
+        }
+    }
+
+    #[test]
+    fn test_sign_payload() {
+        let signature = sign_payload("secret", b"body");
+        assert_eq!(signature, "sha256=dc46983557fea127b43af721467eb9b3fde2338fe3e14f51952aa8478c13d355");
+    }
+
+    #[tokio::test]
+    async fn test_deliver_webhook() {
+        let mut server = mockito::Server::new_async().await;
+        let dispatch = test_dispatch(format!("{}/hook", server.url()));
+        let body = serde_json::to_vec(&dispatch.payload).unwrap();
+        let mock = server.mock("POST", "/hook")
+            .match_header(WEBHOOK_SIGNATURE_HEADER, sign_payload("secret", &body).as_str())
+            .match_header(WEBHOOK_EVENT_HEADER, "user_joined")
+            .with_status(200)
+            .create_async().await;
+
+        let room = dispatch.room.clone();
+        let delivered = deliver_webhook(reqwest::Client::new(), dispatch, test_policy()).await;
+        assert!(delivered);
+        mock.assert();
+
+        let room = room.read().unwrap();
+        assert_eq!(room.webhook_deliveries.len(), 1);
+        assert!(room.webhook_deliveries[0].success);
+        assert_eq!(room.webhook_deliveries[0].status_code, Some(200));
+    }
+
+    #[tokio::test]
+    async fn test_deliver_webhook_retries_until_success() {
+        let mut server = mockito::Server::new_async().await;
+        let failing_mock = server.mock("POST", "/hook")
+            .with_status(500)
+            .expect(2)
+            .create_async().await;
+
+        let dispatch = test_dispatch(format!("{}/hook", server.url()));
+        let room = dispatch.room.clone();
+        let client = reqwest::Client::new();
+        let delivery = tokio::spawn(deliver_webhook(client, dispatch, WebhookDeliveryPolicy {
+            initial_backoff: Duration::from_millis(200),
+            ..test_policy()
+        }));
+
+        // Answers successfully once the second attempt failed, well before the third one is due
+        while room.read().unwrap().webhook_deliveries.len() < 2 {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        failing_mock.remove_async().await;
+        let succeeding_mock = server.mock("POST", "/hook")
+            .with_status(204)
+            .create_async().await;
+
+        assert!(delivery.await.unwrap());
+        succeeding_mock.assert();
+
+        let room = room.read().unwrap();
+        let attempts: Vec<(u32, bool)> = room.webhook_deliveries.iter().map(|d| (d.attempt, d.success)).collect();
+        assert_eq!(attempts, vec![(1, false), (2, false), (3, true)]);
+    }
+
+    #[tokio::test]
+    async fn test_deliver_webhook_gives_up() {
+        let mut server = mockito::Server::new_async().await;
+        let mock = server.mock("POST", "/hook")
+            .with_status(500)
+            .expect(3)
+            .create_async().await;
+
+        let dispatch = test_dispatch(format!("{}/hook", server.url()));
+        let room = dispatch.room.clone();
+        let delivered = deliver_webhook(reqwest::Client::new(), dispatch, test_policy()).await;
+        assert!(!delivered);
+        mock.assert();
+
+        let room = room.read().unwrap();
+        assert_eq!(room.webhook_deliveries.len(), 3);
+        assert!(room.webhook_deliveries.iter().all(|d| !d.success));
+    }
+
+    #[tokio::test]
+    async fn test_deliver_webhook_unreachable() {
+        let dispatch = test_dispatch("http://localhost-non-existent:3012/hook".to_string());
+        let room = dispatch.room.clone();
+        let delivered = deliver_webhook(reqwest::Client::new(), dispatch, test_policy()).await;
+        assert!(!delivered);
+
+        let room = room.read().unwrap();
+        assert_eq!(room.webhook_deliveries.len(), 3);
+        assert!(room.webhook_deliveries[0].status_code.is_none());
+        assert!(room.webhook_deliveries[0].error.is_some());
+    }
+
+    #[tokio::test]
+    async fn test_spawn_webhook_dispatcher() {
+        let mut server = mockito::Server::new_async().await;
+        let mock = server.mock("POST", "/hook")
+            .with_status(200)
+            .create_async().await;
+
+        let (sender, handle) = spawn_webhook_dispatcher(test_policy());
+        let dispatch = test_dispatch(format!("{}/hook", server.url()));
+        let room = dispatch.room.clone();
+        sender.send(dispatch).unwrap();
+
+        // Dropping the sender lets the dispatcher flush the pending delivery and stop
+        drop(sender);
+        handle.await.unwrap();
+        mock.assert();
+        assert!(room.read().unwrap().webhook_deliveries[0].success);
+    }
+}